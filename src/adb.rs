@@ -0,0 +1,192 @@
+#![cfg(feature = "adb")]
+
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::{Pkcs1v15Sign, RsaPrivateKey};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub const DEFAULT_PORT: u16 = 5555;
+
+const CNXN: u32 = 0x4e58_4e43;
+const AUTH: u32 = 0x4854_5541;
+const OPEN: u32 = 0x4e45_504f;
+const OKAY: u32 = 0x5941_4b4f;
+const CLSE: u32 = 0x4553_4c43;
+const WRTE: u32 = 0x4554_5257;
+
+const VERSION: u32 = 0x0100_0000;
+const MAX_DATA: u32 = 256 * 1024;
+
+const AUTH_TOKEN: u32 = 1;
+const AUTH_SIGNATURE: u32 = 2;
+const AUTH_RSA_PUBLIC_KEY: u32 = 3;
+
+/// DER prefix of a SHA-1 digest in a PKCS#1 signature. The device sends a token that is signed
+/// as if it were a SHA-1 digest.
+const SHA1_DIGEST_INFO: [u8; 15] = [
+    0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14,
+];
+
+/// The key to authenticate with, as generated by `adb keygen`: the private key and the public
+/// key in the format of Android, in a file next to it with `.pub` appended.
+pub struct Key {
+    private: RsaPrivateKey,
+    public: Vec<u8>,
+}
+
+impl Key {
+    pub fn read(path: &Path) -> Result<Self> {
+        let pem = fs::read_to_string(path)
+            .map_err(|e| format!("failed reading ADB key {}: {e}", path.display()))?;
+        let private = RsaPrivateKey::from_pkcs8_pem(&pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(&pem))
+            .map_err(|e| format!("invalid ADB key {}: {e}", path.display()))?;
+        let mut public_path = path.as_os_str().to_owned();
+        public_path.push(".pub");
+        let mut public = fs::read(&public_path).map_err(|e| {
+            format!(
+                "failed reading ADB public key {}: {e}",
+                Path::new(&public_path).display()
+            )
+        })?;
+        public.retain(|b| *b != b'\n');
+        public.push(0);
+        Ok(Self { private, public })
+    }
+
+    fn sign(&self, token: &[u8]) -> Result<Vec<u8>> {
+        let mut digest_info = SHA1_DIGEST_INFO.to_vec();
+        digest_info.extend_from_slice(token);
+        Ok(self
+            .private
+            .sign(Pkcs1v15Sign::new_unprefixed(), &digest_info)?)
+    }
+}
+
+struct Message {
+    command: u32,
+    arg0: u32,
+    arg1: u32,
+    data: Vec<u8>,
+}
+
+/// An authenticated ADB connection over TCP.
+pub struct Connection {
+    stream: TcpStream,
+    next_local_id: u32,
+}
+
+impl Connection {
+    /// Connect and authenticate. If the device doesn't know the key yet, it asks to allow it on
+    /// the screen, and this waits until it is allowed.
+    pub async fn connect(host: &str, key: &Key) -> Result<Self> {
+        let stream = if host.contains(':') {
+            TcpStream::connect(host).await?
+        } else {
+            TcpStream::connect((host, DEFAULT_PORT)).await?
+        };
+        let mut connection = Self {
+            stream,
+            next_local_id: 1,
+        };
+        connection
+            .send(CNXN, VERSION, MAX_DATA, b"host::\0")
+            .await?;
+        let mut signed = false;
+        loop {
+            let message = connection.recv().await?;
+            match (message.command, message.arg0) {
+                (CNXN, _) => return Ok(connection),
+                (AUTH, AUTH_TOKEN) if !signed => {
+                    let signature = key.sign(&message.data)?;
+                    connection.send(AUTH, AUTH_SIGNATURE, 0, &signature).await?;
+                    signed = true;
+                }
+                (AUTH, AUTH_TOKEN) => {
+                    info!("The key is not allowed yet, allow it on the screen of {host}.");
+                    connection
+                        .send(AUTH, AUTH_RSA_PUBLIC_KEY, 0, &key.public)
+                        .await?;
+                }
+                _ => return Err("unexpected message while connecting".into()),
+            }
+        }
+    }
+
+    /// Run a shell command and return its output.
+    pub async fn shell(&mut self, command: &str) -> Result<String> {
+        let local_id = self.next_local_id;
+        self.next_local_id += 1;
+        debug!("Running {command}");
+        self.send(OPEN, local_id, 0, format!("shell:{command}\0").as_bytes())
+            .await?;
+        let mut output = Vec::new();
+        loop {
+            let message = self.recv().await?;
+            if message.arg1 != local_id {
+                continue;
+            }
+            match message.command {
+                OKAY => {}
+                WRTE => {
+                    output.extend_from_slice(&message.data);
+                    self.send(OKAY, local_id, message.arg0, &[]).await?;
+                }
+                CLSE => {
+                    // Acknowledged, unless the command couldn't be opened at all.
+                    if message.arg0 != 0 {
+                        self.send(CLSE, local_id, message.arg0, &[]).await?;
+                    }
+                    return Ok(String::from_utf8_lossy(&output).into_owned());
+                }
+                _ => return Err("unexpected message while running command".into()),
+            }
+        }
+    }
+
+    async fn send(&mut self, command: u32, arg0: u32, arg1: u32, data: &[u8]) -> Result<()> {
+        let checksum = data.iter().map(|b| *b as u32).fold(0, u32::wrapping_add);
+        let mut packet = Vec::with_capacity(24 + data.len());
+        for field in [
+            command,
+            arg0,
+            arg1,
+            data.len() as u32,
+            checksum,
+            command ^ 0xffff_ffff,
+        ] {
+            packet.extend_from_slice(&field.to_le_bytes());
+        }
+        packet.extend_from_slice(data);
+        self.stream.write_all(&packet).await?;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Result<Message> {
+        let mut header = [0; 24];
+        self.stream.read_exact(&mut header).await?;
+        let field = |i: usize| u32::from_le_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+        let (command, length, magic) = (field(0), field(3), field(5));
+        if magic != command ^ 0xffff_ffff {
+            return Err("invalid ADB message".into());
+        }
+        if length > MAX_DATA {
+            return Err("ADB message too large".into());
+        }
+        let mut data = vec![0; length as usize];
+        self.stream.read_exact(&mut data).await?;
+        Ok(Message {
+            command,
+            arg0: field(1),
+            arg1: field(2),
+            data,
+        })
+    }
+}
@@ -1,31 +1,82 @@
 use std::future::Future;
-use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::task::{Context, Poll};
+use tokio::sync::{watch, Notify};
+use tokio_util::sync::CancellationToken;
 
-/// A struct of which references can be used as futures that can be manually woken up by another
-/// source.
-/// It will then yield nothing. After yielding, it must be woken
-/// up to yield again. It can be woken up multiple times before it's
-/// been polled.
-pub struct Wakeup(AtomicBool);
+/// A signal that can be manually woken up by another task.
+/// Waiting on it completes once it has been woken up. After that, it must be woken
+/// up again to complete again. It can be woken up multiple times before it's
+/// been waited on.
+pub struct Wakeup(Notify);
 
 impl Wakeup {
     pub fn new(initially_woken_up: bool) -> Self {
-        Self(AtomicBool::new(initially_woken_up))
+        let notify = Notify::new();
+        if initially_woken_up {
+            notify.notify_one();
+        }
+        Self(notify)
     }
     pub fn wakeup(&self) {
-        self.0.store(true, Ordering::Release);
+        self.0.notify_one();
+    }
+    pub async fn wait(&self) {
+        self.0.notified().await
     }
 }
 
-impl Future for &Wakeup {
-    type Output = ();
+/// Tracks futures in progress, so that they can be waited for once no new ones are started.
+pub struct Drain {
+    closed: AtomicBool,
+    running: watch::Sender<usize>,
+    aborted: CancellationToken,
+}
+
+impl Drain {
+    pub fn new() -> Self {
+        Self {
+            closed: AtomicBool::new(false),
+            running: watch::channel(0).0,
+            aborted: CancellationToken::new(),
+        }
+    }
+
+    /// Run the future to completion, unless the drain is closed or the future is aborted.
+    pub async fn run<F: Future>(&self, future: F) -> Option<F::Output> {
+        // Counted before checking, so that closing can't miss a future that is just starting.
+        self.running.send_modify(|running| *running += 1);
+        let _running = Running(&self.running);
+        if self.closed.load(Ordering::SeqCst) {
+            return None;
+        }
+        tokio::select! {
+            output = future => Some(output),
+            _ = self.aborted.cancelled() => None,
+        }
+    }
 
-    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match self.0.swap(false, Ordering::AcqRel) {
-            true => Poll::Ready(()),
-            false => Poll::Pending,
+    /// Start no new futures and wait until the running ones completed.
+    pub async fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        let mut running = self.running.subscribe();
+        while *running.borrow_and_update() > 0 {
+            // The sender is not dropped while borrowed.
+            running.changed().await.ok();
         }
     }
+
+    /// Drop the running futures, and wait until they are dropped.
+    pub async fn abort(&self) {
+        self.aborted.cancel();
+        self.close().await;
+    }
+}
+
+/// Counts a running future, also if it is dropped before completing.
+struct Running<'a>(&'a watch::Sender<usize>);
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.0.send_modify(|running| *running -= 1);
+    }
 }
@@ -0,0 +1,179 @@
+#![cfg(any(feature = "source-cec", feature = "sink-cec"))]
+
+use crate::error::Error;
+use cec_rs::{CecConnectionCfgBuilder, CecDeviceType, CecDeviceTypeVec, CecLogicalAddress};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex, OnceLock, Weak};
+use std::thread;
+use tokio::sync::oneshot;
+use tracing::{debug, error};
+
+/// Name the app announces itself with on the CEC bus.
+const DEVICE_NAME: &str = "power-ctrl";
+
+/// Logical address of a device on the CEC bus.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum LogicalAddress {
+    #[default]
+    Tv,
+    AudioSystem,
+    RecordingDevice1,
+    PlaybackDevice1,
+    PlaybackDevice2,
+    PlaybackDevice3,
+    Tuner1,
+    Broadcast,
+}
+
+impl From<LogicalAddress> for CecLogicalAddress {
+    fn from(value: LogicalAddress) -> Self {
+        match value {
+            LogicalAddress::Tv => CecLogicalAddress::Tv,
+            LogicalAddress::AudioSystem => CecLogicalAddress::Audiosystem,
+            LogicalAddress::RecordingDevice1 => CecLogicalAddress::Recordingdevice1,
+            LogicalAddress::PlaybackDevice1 => CecLogicalAddress::Playbackdevice1,
+            LogicalAddress::PlaybackDevice2 => CecLogicalAddress::Playbackdevice2,
+            LogicalAddress::PlaybackDevice3 => CecLogicalAddress::Playbackdevice3,
+            LogicalAddress::Tuner1 => CecLogicalAddress::Tuner1,
+            LogicalAddress::Broadcast => CecLogicalAddress::Broadcast,
+        }
+    }
+}
+
+enum Request {
+    #[cfg(feature = "source-cec")]
+    PowerStatus(CecLogicalAddress, oneshot::Sender<cec_rs::CecPowerStatus>),
+    #[cfg(feature = "sink-cec")]
+    PowerOn(CecLogicalAddress, oneshot::Sender<Result<(), String>>),
+    #[cfg(feature = "sink-cec")]
+    Standby(CecLogicalAddress, oneshot::Sender<Result<(), String>>),
+}
+
+/// A CEC adapter opened with libcec. Each adapter can only be opened once, so all sources and
+/// sinks on the same port share it. The connection is owned by a dedicated thread, since libcec
+/// calls are blocking.
+pub struct CecAdapter {
+    port: String,
+    requests: Mutex<mpsc::Sender<Request>>,
+}
+
+impl CecAdapter {
+    /// Open the adapter on the given port, e.g. `RPI` or `/dev/ttyACM0`, or return the already
+    /// opened one.
+    pub fn open(port: &str) -> Result<Arc<Self>, Error> {
+        static ADAPTERS: OnceLock<Mutex<HashMap<String, Weak<CecAdapter>>>> = OnceLock::new();
+        let mut adapters = ADAPTERS.get_or_init(Default::default).lock().unwrap();
+        if let Some(adapter) = adapters.get(port).and_then(Weak::upgrade) {
+            return Ok(adapter);
+        }
+        let adapter = Arc::new(Self::open_new(port)?);
+        adapters.insert(port.to_string(), Arc::downgrade(&adapter));
+        Ok(adapter)
+    }
+
+    fn open_new(port: &str) -> Result<Self, Error> {
+        let (requests, receiver) = mpsc::channel();
+        let (opened_tx, opened_rx) = mpsc::sync_channel(1);
+        let thread_port = port.to_string();
+        thread::Builder::new()
+            .name(format!("cec {port}"))
+            .spawn(move || Self::connection_thread(thread_port, receiver, opened_tx))?;
+        opened_rx
+            .recv()
+            .map_err(|_| "CEC connection thread exited")??;
+        Ok(Self {
+            port: port.to_string(),
+            requests: Mutex::new(requests),
+        })
+    }
+
+    fn connection_thread(
+        port: String,
+        requests: mpsc::Receiver<Request>,
+        opened: mpsc::SyncSender<Result<(), String>>,
+    ) {
+        let connection = CecConnectionCfgBuilder::default()
+            .port(port.clone())
+            .device_name(DEVICE_NAME.into())
+            .device_types(CecDeviceTypeVec::new(CecDeviceType::PlaybackDevice))
+            .activate_source(false)
+            .build()
+            .map_err(|e| e.to_string())
+            .and_then(|cfg| cfg.open().map_err(|e| format!("{e:?}")));
+        let connection = match connection {
+            Ok(v) => {
+                opened.send(Ok(())).ok();
+                v
+            }
+            Err(e) => {
+                opened
+                    .send(Err(format!("failed opening CEC adapter {port}: {e}")))
+                    .ok();
+                return;
+            }
+        };
+        // Runs until all handles to the adapter are dropped.
+        while let Ok(request) = requests.recv() {
+            match request {
+                #[cfg(feature = "source-cec")]
+                Request::PowerStatus(address, reply) => {
+                    reply.send(connection.get_device_power_status(address)).ok();
+                }
+                #[cfg(feature = "sink-cec")]
+                Request::PowerOn(address, reply) => {
+                    let result = connection.send_power_on_devices(address);
+                    reply.send(result.map_err(|e| format!("{e:?}"))).ok();
+                }
+                #[cfg(feature = "sink-cec")]
+                Request::Standby(address, reply) => {
+                    let result = connection.send_standby_devices(address);
+                    reply.send(result.map_err(|e| format!("{e:?}"))).ok();
+                }
+            }
+        }
+        debug!("Closing CEC adapter {port}.");
+    }
+
+    fn send(&self, request: Request) -> Result<(), String> {
+        self.requests.lock().unwrap().send(request).map_err(|_| {
+            error!("CEC connection thread for {} is gone.", self.port);
+            format!("CEC adapter {} is not connected", self.port)
+        })
+    }
+
+    /// Ask the device at the address for its power status.
+    #[cfg(feature = "source-cec")]
+    pub async fn power_status(
+        &self,
+        address: LogicalAddress,
+    ) -> Result<cec_rs::CecPowerStatus, Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Request::PowerStatus(address.into(), reply))?;
+        response.await.map_err(Error::other)
+    }
+
+    /// Send "image view on" to the device at the address.
+    #[cfg(feature = "sink-cec")]
+    pub async fn power_on(&self, address: LogicalAddress) -> Result<(), Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Request::PowerOn(address.into(), reply))?;
+        response
+            .await
+            .map_err(Error::other)?
+            .map_err(Error::protocol)
+    }
+
+    /// Send "standby" to the device at the address.
+    #[cfg(feature = "sink-cec")]
+    pub async fn standby(&self, address: LogicalAddress) -> Result<(), Error> {
+        let (reply, response) = oneshot::channel();
+        self.send(Request::Standby(address.into(), reply))?;
+        response
+            .await
+            .map_err(Error::other)?
+            .map_err(Error::protocol)
+    }
+}
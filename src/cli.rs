@@ -0,0 +1,145 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+pub mod capabilities;
+pub mod check_config;
+#[cfg(feature = "discover")]
+pub mod discover;
+#[cfg(feature = "monitor")]
+pub mod monitor;
+#[cfg(feature = "schema")]
+pub mod schema;
+pub mod set_override;
+pub mod stats;
+pub mod status;
+pub mod test;
+
+/// Controls the power of sinks based on whether sources are active.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path to the config file. `*.toml` files in a `conf.d` directory next to it are merged
+    /// into it.
+    #[arg(long, global = true, env = "PPC_CONFIG", default_value_os_t = default_config())]
+    pub config: PathBuf,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// `config.toml` in the working directory. On Windows, where services run in the system
+/// directory, it's in `%ProgramData%\personal-power-ctrl` instead.
+fn default_config() -> PathBuf {
+    #[cfg(windows)]
+    {
+        let program_data =
+            std::env::var_os("ProgramData").unwrap_or_else(|| r"C:\ProgramData".into());
+        PathBuf::from(program_data)
+            .join("personal-power-ctrl")
+            .join("config.toml")
+    }
+    #[cfg(not(windows))]
+    PathBuf::from("config.toml")
+}
+
+#[derive(Debug, Default, Subcommand)]
+pub enum Command {
+    /// Run the daemon. This is the default if no command is given.
+    #[default]
+    Run,
+    /// Run the daemon as a Windows service. Only for the service control manager, see the
+    /// README on how to install the service.
+    #[cfg(all(feature = "windows-service", windows))]
+    Service,
+    /// List the sink and source types this binary was built with and their settings, and its
+    /// other enabled features.
+    Capabilities,
+    /// Validate the configuration, including creating all enabled sinks and sources, and exit.
+    CheckConfig,
+    /// Print the current state of all sources and sinks of the running daemon.
+    Status,
+    /// Print how long the sources and sinks of the running daemon were on, and the estimated
+    /// energy use of sinks with a wattage.
+    Stats,
+    /// Show the state of the running daemon in a live-updating terminal UI.
+    #[cfg(feature = "monitor")]
+    Monitor,
+    /// Force a sink of the running daemon on or off regardless of the sources, or clear this
+    /// override again.
+    #[command(name = "override")]
+    Override {
+        /// Name of the sink.
+        name: String,
+        #[arg(value_enum)]
+        action: OverrideAction,
+    },
+    /// Print the JSON Schema of the configuration file, for editors and validating configs.
+    #[cfg(feature = "schema")]
+    Schema,
+    /// Let a sink that waits for a manual reset after a failed command follow the sources again.
+    Reset {
+        /// Name of the sink.
+        name: String,
+    },
+    /// Force load shedding of the running daemon on or off regardless of its source, or clear
+    /// this override again.
+    Shed {
+        #[arg(value_enum)]
+        action: OverrideAction,
+    },
+    /// Create a source or sink from the configuration and add it to the running daemon, even if
+    /// it is disabled in the configuration.
+    Enable {
+        #[arg(value_enum)]
+        kind: ComponentKind,
+        /// Name of the source or sink.
+        name: String,
+    },
+    /// Remove a source or sink from the running daemon until it is restarted. A removed sink is
+    /// left in its current state.
+    Disable {
+        #[arg(value_enum)]
+        kind: ComponentKind,
+        /// Name of the source or sink.
+        name: String,
+    },
+    /// Scan the local network for supported devices and print config snippets for them.
+    #[cfg(feature = "discover")]
+    Discover {
+        /// How long to wait for responses.
+        #[arg(long, default_value_t = 3)]
+        wait_sec: u64,
+    },
+    /// Create a single sink from the configuration and turn it on or off once.
+    TestSink {
+        /// Name of the sink.
+        name: String,
+        /// Whether to turn the sink on or off.
+        #[arg(value_enum)]
+        action: SinkAction,
+    },
+    /// Create a single source from the configuration and check whether it is active once.
+    TestSource {
+        /// Name of the source.
+        name: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum OverrideAction {
+    On,
+    Off,
+    /// Follow the sources again.
+    Clear,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ComponentKind {
+    Source,
+    Sink,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum SinkAction {
+    On,
+    Off,
+}
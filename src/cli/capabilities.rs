@@ -0,0 +1,51 @@
+use crate::registry::Registered;
+use crate::sink::AnySinkSettings;
+use crate::source::AnySourceSettings;
+
+/// Features that add something else than a sink or source type, with whether they are enabled.
+const FEATURES: &[(&str, bool)] = &[
+    ("dbus", cfg!(all(feature = "dbus", target_os = "linux"))),
+    ("discover", cfg!(feature = "discover")),
+    ("http", cfg!(feature = "http")),
+    ("monitor", cfg!(feature = "monitor")),
+    ("notifier-ntfy", cfg!(feature = "notifier-ntfy")),
+    ("notifier-smtp", cfg!(feature = "notifier-smtp")),
+    ("notifier-webhook", cfg!(feature = "notifier-webhook")),
+    ("schema", cfg!(feature = "schema")),
+    ("telegram", cfg!(feature = "telegram")),
+    (
+        "windows-service",
+        cfg!(all(feature = "windows-service", windows)),
+    ),
+];
+
+/// Print the sink and source types this binary was built with, with their settings, and its
+/// other enabled features.
+pub fn run() {
+    print_types::<dyn AnySinkSettings>("Sinks");
+    println!();
+    print_types::<dyn AnySourceSettings>("Sources");
+    println!();
+    let enabled: Vec<_> = FEATURES
+        .iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    println!("Other features: {}", enabled.join(", "));
+}
+
+fn print_types<T: ?Sized + Registered>(title: &str) {
+    println!("{title}:");
+    if T::types().is_empty() {
+        println!("  (none)");
+    }
+    for registration in T::types() {
+        println!(
+            "  {} ({}): {}",
+            registration.name,
+            registration.feature,
+            registration.fields.join(", ")
+        );
+    }
+    println!("  All of them also take: {}", T::BASE_FIELDS.join(", "));
+}
@@ -0,0 +1,202 @@
+use crate::identity::Named;
+use crate::settings::{self, Settings};
+use crate::{notifier, sink, source};
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Validate the configuration and print a report. Fails if there are any errors.
+pub async fn run(config_path: &Path) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            println!("ERROR: Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut errors = 0;
+    let mut warnings = 0;
+    check(&config, &mut errors, &mut warnings);
+
+    println!();
+    println!("{errors} error(s), {warnings} warning(s).");
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn check(config: &Settings, errors: &mut usize, warnings: &mut usize) {
+    let mut source_names = HashSet::new();
+    for (base, result) in source::try_create_all(&config.source) {
+        if !source_names.insert(base.name.as_str()) {
+            println!(
+                "{} WARNING: A source with this name already exists.",
+                base.identity()
+            );
+            *warnings += 1;
+        }
+        match result {
+            Ok(_) => println!("{} OK", base.identity()),
+            Err(e) => {
+                println!("{} ERROR: {e}", base.identity());
+                *errors += 1;
+            }
+        }
+    }
+
+    let mut sink_names = HashSet::new();
+    for (base, result) in sink::try_create_all(&config.sink) {
+        if !sink_names.insert(base.name.as_str()) {
+            println!(
+                "{} WARNING: A sink with this name already exists.",
+                base.identity()
+            );
+            *warnings += 1;
+        }
+        match result {
+            Ok(_) => println!("{} OK", base.identity()),
+            Err(e) => {
+                println!("{} ERROR: {e}", base.identity());
+                *errors += 1;
+            }
+        }
+        let referenced = [
+            ("on-source-whitelist", &base.on_source_whitelist),
+            ("on-source-blacklist", &base.on_source_blacklist),
+            ("off-source-whitelist", &base.off_source_whitelist),
+            ("off-source-blacklist", &base.off_source_blacklist),
+        ];
+        for (field, names) in referenced {
+            let names = names.iter().flatten();
+            check_references(
+                &base.identity(),
+                field,
+                "source",
+                names,
+                &source_names,
+                errors,
+            );
+        }
+    }
+
+    for group in config.sink.iter().filter(|cfg| cfg.is_group()) {
+        let identity = group.base().identity();
+        for name in group.members() {
+            let member = config.sink.iter().find(|cfg| &cfg.base().name == name);
+            match member {
+                _ if name == &group.base().name => {
+                    println!("{identity} ERROR: sinks lists the group itself.")
+                }
+                None => println!("{identity} ERROR: sinks references unknown sink \"{name}\"."),
+                Some(member) if member.is_group() => println!(
+                    "{identity} ERROR: sinks references group \"{name}\", which can't be a member."
+                ),
+                Some(_) => continue,
+            }
+            *errors += 1;
+        }
+    }
+
+    for composite in config.source.iter().filter(|cfg| cfg.is_group()) {
+        let identity = composite.base().identity();
+        for name in composite.members() {
+            let member = config.source.iter().find(|cfg| cfg.base().name == name);
+            match member {
+                _ if name == composite.base().name => {
+                    println!("{identity} ERROR: expression references the source itself.")
+                }
+                Some(member) if member.is_group() => println!(
+                    "{identity} ERROR: expression references composite source \"{name}\", which can't be combined."
+                ),
+                _ if !source_names.contains(name) => println!(
+                    "{identity} ERROR: expression references unknown or disabled source \"{name}\"."
+                ),
+                _ => continue,
+            }
+            *errors += 1;
+        }
+    }
+
+    for zone in config.zone.iter() {
+        let identity = zone.identity();
+        check_references(
+            &identity,
+            "sources",
+            "source",
+            &zone.sources,
+            &source_names,
+            errors,
+        );
+        check_references(&identity, "sinks", "sink", &zone.sinks, &sink_names, errors);
+    }
+
+    for route in config.route.iter() {
+        let identity = route.identity();
+        check_references(
+            &identity,
+            "sources",
+            "source",
+            &route.sources,
+            &source_names,
+            errors,
+        );
+        check_references(
+            &identity,
+            "sinks",
+            "sink",
+            &route.sinks,
+            &sink_names,
+            errors,
+        );
+    }
+
+    if let Some(shedding) = &config.general.load_shedding {
+        check_references(
+            &"[general]",
+            "load-shedding.source",
+            "source",
+            &shedding.source,
+            &source_names,
+            errors,
+        );
+    }
+
+    let mut notifier_names = HashSet::new();
+    for (base, result) in notifier::try_create_all(&config.notifier) {
+        if !notifier_names.insert(base.name.as_str()) {
+            println!(
+                "{} WARNING: A notifier with this name already exists.",
+                base.identity()
+            );
+            *warnings += 1;
+        }
+        match result {
+            Ok(_) => println!("{} OK", base.identity()),
+            Err(e) => {
+                println!("{} ERROR: {e}", base.identity());
+                *errors += 1;
+            }
+        }
+    }
+}
+
+/// Report each name that is not one of the enabled components of the kind.
+fn check_references<'a>(
+    identity: &impl Display,
+    field: &str,
+    kind: &str,
+    names: impl IntoIterator<Item = &'a String>,
+    known: &HashSet<&str>,
+    errors: &mut usize,
+) {
+    for name in names {
+        if !known.contains(name.as_str()) {
+            println!("{identity} ERROR: {field} references unknown or disabled {kind} \"{name}\".");
+            *errors += 1;
+        }
+    }
+}
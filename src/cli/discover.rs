@@ -0,0 +1,268 @@
+#![cfg(feature = "discover")]
+
+use crate::kasa;
+use serde_json::Value;
+use simple_dns::rdata::RData;
+use simple_dns::{Name, Packet, Question, CLASS, TYPE};
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{timeout_at, Instant};
+
+const MDNS_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(224, 0, 0, 251), 5353);
+const SSDP_ADDR: (Ipv4Addr, u16) = (Ipv4Addr::new(239, 255, 255, 250), 1900);
+
+/// mDNS services to look for, with the kind of device offering them.
+const MDNS_SERVICES: [(&str, Kind); 3] = [
+    ("_xbmc-jsonrpc-h._tcp.local", Kind::Kodi),
+    ("_googlecast._tcp.local", Kind::Chromecast),
+    ("_shelly._tcp.local", Kind::Shelly),
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum Kind {
+    Kasa,
+    Kodi,
+    Chromecast,
+    Shelly,
+    WebOs,
+    DenonAvr,
+}
+
+/// A device that answered one of the scans.
+#[derive(Debug)]
+struct Device {
+    kind: Kind,
+    addr: IpAddr,
+    port: u16,
+    name: String,
+    details: String,
+    /// ID of the outlet, for devices with multiple.
+    child_id: Option<String>,
+}
+
+impl Device {
+    /// A config snippet for the device, or a comment if nothing can control it.
+    fn snippet(&self) -> String {
+        let Device {
+            addr, port, name, ..
+        } = self;
+        let head = |section: &str| {
+            format!("[[{section}]]\nname = \"{name}\"\nenable = true\ntimeout-sec = 10\n")
+        };
+        match self.kind {
+            Kind::Kasa => {
+                let mut snippet = format!("{}host = \"{addr}:{port}\"\n", head("sink.hs100"));
+                if let Some(child_id) = &self.child_id {
+                    snippet.push_str(&format!("child-id = \"{child_id}\"\n"));
+                }
+                snippet
+            }
+            Kind::Kodi => format!(
+                "{}poll-interval-sec = {{ off = 5, on = 60 }}\njsonrpc = \"http://{addr}:{port}/jsonrpc\"\n",
+                head("source.kodi")
+            ),
+            Kind::WebOs => format!(
+                "{}host = \"{addr}\"\nmac = \"\" # the MAC address of the TV\n",
+                head("sink.webos")
+            ),
+            Kind::DenonAvr => format!("{}host = \"{addr}\"\n", head("sink.denon-avr")),
+            Kind::Chromecast | Kind::Shelly => {
+                "# Not supported by any sink or source yet.\n".to_string()
+            }
+        }
+    }
+}
+
+/// Scan the local network for supported devices and print config snippets for them.
+pub async fn run(wait_sec: u64) -> ExitCode {
+    eprintln!("Scanning for {wait_sec} seconds...");
+    let deadline = Instant::now() + Duration::from_secs(wait_sec);
+    let (kasa, mdns, ssdp) = tokio::join!(
+        scan_kasa(deadline),
+        scan_mdns(deadline),
+        scan_ssdp(deadline)
+    );
+    let mut devices = Vec::new();
+    for (scan, result) in [("Kasa", kasa), ("mDNS", mdns), ("SSDP", ssdp)] {
+        match result {
+            Ok(found) => devices.extend(found),
+            Err(e) => eprintln!("{scan} scan failed: {e}"),
+        }
+    }
+    if devices.is_empty() {
+        eprintln!("No devices found.");
+        return ExitCode::FAILURE;
+    }
+    devices.sort_by_key(|d| (d.kind, d.addr, d.port, d.child_id.clone()));
+    devices.dedup_by_key(|d| (d.kind, d.addr, d.port, d.child_id.clone()));
+    for device in devices {
+        println!("# {:?} \"{}\" at {}", device.kind, device.name, device.addr);
+        if !device.details.is_empty() {
+            println!("# {}", device.details);
+        }
+        println!("{}", device.snippet());
+    }
+    ExitCode::SUCCESS
+}
+
+async fn broadcast_socket() -> io::Result<UdpSocket> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    Ok(socket)
+}
+
+/// Collect all responses to the socket until the deadline, parsing them with `parse`.
+async fn receive_until<T>(
+    socket: &UdpSocket,
+    deadline: Instant,
+    mut parse: impl FnMut(&[u8], SocketAddr) -> Vec<T>,
+) -> Vec<T> {
+    let mut found = Vec::new();
+    let mut buf = vec![0; 9000];
+    while let Ok(Ok((len, from))) = timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        found.extend(parse(&buf[..len], from));
+    }
+    found
+}
+
+async fn scan_kasa(deadline: Instant) -> io::Result<Vec<Device>> {
+    let socket = broadcast_socket().await?;
+    let request = kasa::xor(br#"{"system":{"get_sysinfo":{}}}"#, true);
+    socket
+        .send_to(&request, (Ipv4Addr::BROADCAST, kasa::PORT))
+        .await?;
+    Ok(receive_until(&socket, deadline, |data, from| {
+        let Ok(response) = serde_json::from_slice::<Value>(&kasa::xor(data, false)) else {
+            return vec![];
+        };
+        let info = &response["system"]["get_sysinfo"];
+        let model = info["model"].as_str().unwrap_or("unknown");
+        let device = |name: &str, child_id| Device {
+            kind: Kind::Kasa,
+            addr: from.ip(),
+            port: kasa::PORT,
+            name: name.to_string(),
+            details: format!("Model {model}"),
+            child_id,
+        };
+        match info["children"].as_array() {
+            // Power strips, with a sink per outlet.
+            Some(children) => children
+                .iter()
+                .filter_map(|child| {
+                    let id = child["id"].as_str()?;
+                    // Some firmware versions only report the index of the outlet.
+                    let id = match info["deviceId"].as_str() {
+                        Some(device_id) if id.len() == 2 => format!("{device_id}{id}"),
+                        _ => id.to_string(),
+                    };
+                    Some(device(
+                        child["alias"].as_str().unwrap_or("Outlet"),
+                        Some(id),
+                    ))
+                })
+                .collect(),
+            None => vec![device(info["alias"].as_str().unwrap_or("Kasa"), None)],
+        }
+    })
+    .await)
+}
+
+async fn scan_mdns(deadline: Instant) -> io::Result<Vec<Device>> {
+    // Queries from a port other than 5353 get unicast responses, so no multicast group has to
+    // be joined.
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let mut query = Packet::new_query(0);
+    for (service, _) in MDNS_SERVICES {
+        query.questions.push(Question::new(
+            Name::new_unchecked(service),
+            TYPE::PTR.into(),
+            CLASS::IN.into(),
+            false,
+        ));
+    }
+    let query = query
+        .build_bytes_vec()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    socket.send_to(&query, MDNS_ADDR).await?;
+    Ok(receive_until(&socket, deadline, parse_mdns_response).await)
+}
+
+fn parse_mdns_response(data: &[u8], from: SocketAddr) -> Vec<Device> {
+    let Ok(packet) = Packet::parse(data) else {
+        return vec![];
+    };
+    let records = || packet.answers.iter().chain(&packet.additional_records);
+    let mut instances = BTreeMap::new();
+    for record in records() {
+        let RData::PTR(ptr) = &record.rdata else {
+            continue;
+        };
+        let service = record.name.to_string();
+        if let Some((_, kind)) = MDNS_SERVICES.iter().find(|(s, _)| *s == service) {
+            instances.insert(ptr.0.to_string(), *kind);
+        }
+    }
+    instances
+        .into_iter()
+        .map(|(instance, kind)| {
+            let port = records()
+                .find_map(|record| match &record.rdata {
+                    RData::SRV(srv) if record.name.to_string() == instance => Some(srv.port),
+                    _ => None,
+                })
+                .unwrap_or(80);
+            let name = instance.split('.').next().unwrap_or_default().to_string();
+            Device {
+                kind,
+                addr: from.ip(),
+                port,
+                name,
+                details: String::new(),
+                child_id: None,
+            }
+        })
+        .collect()
+}
+
+async fn scan_ssdp(deadline: Instant) -> io::Result<Vec<Device>> {
+    let socket = broadcast_socket().await?;
+    let request = "M-SEARCH * HTTP/1.1\r\n\
+        HOST: 239.255.255.250:1900\r\n\
+        MAN: \"ssdp:discover\"\r\n\
+        MX: 2\r\n\
+        ST: ssdp:all\r\n\r\n";
+    socket.send_to(request.as_bytes(), SSDP_ADDR).await?;
+    Ok(receive_until(&socket, deadline, |data, from| {
+        let response = String::from_utf8_lossy(data);
+        let header = |name: &str| {
+            response
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        let (server, st) = (header("SERVER"), header("ST"));
+        let kind = if st.contains("lge-com:service:webos") || server.contains("WebOS") {
+            Kind::WebOs
+        } else if st.contains("schemas-denon-com") {
+            Kind::DenonAvr
+        } else {
+            return vec![];
+        };
+        vec![Device {
+            kind,
+            addr: from.ip(),
+            port: 0,
+            name: from.ip().to_string(),
+            details: format!("{server} {st}").trim().to_string(),
+            child_id: None,
+        }]
+    })
+    .await)
+}
@@ -0,0 +1,159 @@
+#![cfg(feature = "monitor")]
+
+use crate::cli::status::{sink_rows, source_rows, SINK_HEADERS, SOURCE_HEADERS};
+use crate::control::{self, Request, Response, StatusReport};
+use crate::settings;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::{Frame, Terminal};
+use std::error::Error;
+use std::io::{self, Stdout};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+type Term = Terminal<CrosstermBackend<Stdout>>;
+
+/// Show the state of the running daemon in a live-updating terminal UI, until `q` is pressed.
+pub async fn run(config_path: &Path) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut terminal = match setup_terminal() {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed setting up terminal: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = monitor(&mut terminal, &config.general.control_socket).await;
+    restore_terminal(&mut terminal);
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn setup_terminal() -> Result<Term, Box<dyn Error>> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    Ok(Terminal::new(CrosstermBackend::new(stdout))?)
+}
+
+fn restore_terminal(terminal: &mut Term) {
+    disable_raw_mode().ok();
+    execute!(terminal.backend_mut(), LeaveAlternateScreen).ok();
+    terminal.show_cursor().ok();
+}
+
+async fn monitor(terminal: &mut Term, socket: &Path) -> Result<(), Box<dyn Error>> {
+    loop {
+        // Errors are shown instead of the state, so that the monitor survives daemon restarts.
+        let report = match control::request(socket, &Request::Status).await {
+            Ok(Response::Status(report)) => Ok(report),
+            Ok(Response::Error { message }) => Err(format!("Daemon returned an error: {message}")),
+            Ok(response) => Err(format!("Unexpected response from daemon: {response:?}")),
+            Err(e) => Err(e.to_string()),
+        };
+        terminal.draw(|frame| draw(frame, socket, &report))?;
+        if tokio::task::spawn_blocking(wait_for_quit).await?? {
+            return Ok(());
+        }
+    }
+}
+
+/// Wait up to the refresh interval for a key press. Returns whether the monitor should quit.
+fn wait_for_quit() -> io::Result<bool> {
+    if event::poll(REFRESH_INTERVAL)? {
+        if let Event::Key(key) = event::read()? {
+            return Ok(key.kind == KeyEventKind::Press
+                && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                    || (key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL))));
+        }
+    }
+    Ok(false)
+}
+
+fn draw(frame: &mut Frame, socket: &Path, report: &Result<StatusReport, String>) {
+    let title = format!(" personal-power-ctrl: {} (q to quit) ", socket.display());
+    let report = match report {
+        Ok(v) => v,
+        Err(e) => {
+            frame.render_widget(
+                Paragraph::new(e.as_str())
+                    .block(Block::default().title(title).borders(Borders::ALL)),
+                frame.size(),
+            );
+            return;
+        }
+    };
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(report.sources.len() as u16 + 3),
+            Constraint::Min(0),
+        ])
+        .split(frame.size());
+    draw_table(
+        frame,
+        areas[0],
+        title,
+        &SOURCE_HEADERS,
+        source_rows(report).collect(),
+    );
+    draw_table(
+        frame,
+        areas[1],
+        String::new(),
+        &SINK_HEADERS,
+        sink_rows(report).collect(),
+    );
+}
+
+fn draw_table(
+    frame: &mut Frame,
+    area: Rect,
+    title: String,
+    headers: &[&str],
+    rows: Vec<Vec<String>>,
+) {
+    let widths = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            let width = rows
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default();
+            Constraint::Length(width as u16)
+        })
+        .collect::<Vec<_>>();
+    let table = Table::new(rows.into_iter().map(Row::new))
+        .header(
+            Row::new(headers.iter().copied()).style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .column_spacing(2)
+        .widths(&widths);
+    frame.render_widget(table, area);
+}
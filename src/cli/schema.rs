@@ -0,0 +1,20 @@
+#![cfg(feature = "schema")]
+
+use crate::settings::Settings;
+use std::process::ExitCode;
+
+/// Print the JSON Schema of the configuration, covering the sinks, sources and notifiers of
+/// the enabled features.
+pub fn run() -> ExitCode {
+    let schema = schemars::schema_for!(Settings);
+    match serde_json::to_string_pretty(&schema) {
+        Ok(v) => {
+            println!("{v}");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Failed serializing schema: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
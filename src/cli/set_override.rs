@@ -0,0 +1,76 @@
+use crate::cli::{ComponentKind, OverrideAction};
+use crate::control::{self, Request, Response};
+use crate::settings;
+use crate::state::PowerState;
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Ask the running daemon to force a sink on or off, or to clear the override.
+pub async fn run(config_path: &Path, name: &str, action: OverrideAction) -> ExitCode {
+    let request = Request::SetOverride {
+        sink: name.to_string(),
+        forced: match action {
+            OverrideAction::On => Some(PowerState::On),
+            OverrideAction::Off => Some(PowerState::Off),
+            OverrideAction::Clear => None,
+        },
+    };
+    send(config_path, &request).await
+}
+
+/// Ask the running daemon to let a sink waiting for a manual reset follow the sources again.
+pub async fn reset(config_path: &Path, name: &str) -> ExitCode {
+    let request = Request::ResetSink {
+        sink: name.to_string(),
+    };
+    send(config_path, &request).await
+}
+
+/// Ask the running daemon to force load shedding on or off, or to clear the override.
+pub async fn shed(config_path: &Path, action: OverrideAction) -> ExitCode {
+    let request = Request::SetLoadShedding {
+        forced: match action {
+            OverrideAction::On => Some(true),
+            OverrideAction::Off => Some(false),
+            OverrideAction::Clear => None,
+        },
+    };
+    send(config_path, &request).await
+}
+
+/// Ask the running daemon to add a source or sink from its configuration, or to remove it.
+pub async fn enable(config_path: &Path, kind: ComponentKind, name: &str, enable: bool) -> ExitCode {
+    let name = name.to_string();
+    let request = match (kind, enable) {
+        (ComponentKind::Source, true) => Request::AddSource { source: name },
+        (ComponentKind::Source, false) => Request::RemoveSource { source: name },
+        (ComponentKind::Sink, true) => Request::AddSink { sink: name },
+        (ComponentKind::Sink, false) => Request::RemoveSink { sink: name },
+    };
+    send(config_path, &request).await
+}
+
+async fn send(config_path: &Path, request: &Request) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match control::request(&config.general.control_socket, request).await {
+        Ok(Response::Ok) => ExitCode::SUCCESS,
+        Ok(Response::Error { message }) => {
+            eprintln!("Daemon returned an error: {message}");
+            ExitCode::FAILURE
+        }
+        Ok(response) => {
+            eprintln!("Unexpected response from daemon: {response:?}");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
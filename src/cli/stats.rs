@@ -0,0 +1,56 @@
+use crate::cli::status::print_table;
+use crate::control::{self, Request, Response};
+use crate::settings;
+use crate::statistics::{format_duration, OnTimeReport};
+use std::path::Path;
+use std::process::ExitCode;
+
+/// Query the running daemon for the on-time statistics and print them.
+pub async fn run(config_path: &Path) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match control::request(&config.general.control_socket, &Request::Statistics).await {
+        Ok(Response::Statistics(report)) => {
+            print_table(
+                &["SOURCE", "TODAY", "7 DAYS", "TOTAL"],
+                report.sources.iter().map(|r| on_time_row(r, false)),
+            );
+            println!();
+            print_table(
+                &["SINK", "TODAY", "7 DAYS", "TOTAL"],
+                report.sinks.iter().map(|r| on_time_row(r, true)),
+            );
+            ExitCode::SUCCESS
+        }
+        Ok(Response::Error { message }) => {
+            eprintln!("Daemon returned an error: {message}");
+            ExitCode::FAILURE
+        }
+        Ok(response) => {
+            eprintln!("Unexpected response from daemon: {response:?}");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn on_time_row(report: &OnTimeReport, with_energy: bool) -> Vec<String> {
+    let cell = |sec: u64, wh: Option<f64>| match wh.filter(|_| with_energy) {
+        Some(wh) => format!("{} ({:.2} kWh)", format_duration(sec), wh / 1000.0),
+        None => format_duration(sec),
+    };
+    vec![
+        report.name.clone(),
+        cell(report.today_sec, report.today_wh),
+        cell(report.week_sec, report.week_wh),
+        cell(report.total_sec, report.total_wh),
+    ]
+}
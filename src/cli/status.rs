@@ -0,0 +1,144 @@
+use crate::control::{self, Request, Response, StatusReport};
+use crate::settings;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::SystemTime;
+
+/// Query the running daemon for its status and print it.
+pub async fn run(config_path: &Path) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match control::request(&config.general.control_socket, &Request::Status).await {
+        Ok(Response::Status(report)) => {
+            print_report(&report);
+            ExitCode::SUCCESS
+        }
+        Ok(Response::Error { message }) => {
+            eprintln!("Daemon returned an error: {message}");
+            ExitCode::FAILURE
+        }
+        Ok(response) => {
+            eprintln!("Unexpected response from daemon: {response:?}");
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub(super) const SOURCE_HEADERS: [&str; 5] =
+    ["SOURCE", "STATE", "LAST POLL", "NEXT POLL", "LAST ERROR"];
+pub(super) const SINK_HEADERS: [&str; 5] =
+    ["SINK", "STATE", "PENDING", "LAST COMMAND", "LAST ERROR"];
+
+fn print_report(report: &StatusReport) {
+    print_table(&SOURCE_HEADERS, source_rows(report));
+    println!();
+    print_table(&SINK_HEADERS, sink_rows(report));
+}
+
+/// Table rows for all sources, sorted by name.
+pub(super) fn source_rows(report: &StatusReport) -> impl Iterator<Item = Vec<String>> + '_ {
+    let mut sources = report.sources.iter().collect::<Vec<_>>();
+    sources.sort_by(|a, b| a.name.cmp(&b.name));
+    sources.into_iter().map(|s| {
+        let mut state = format!("{:?}", s.power_state).to_lowercase();
+        if s.asleep {
+            state.push_str(" (asleep)");
+        }
+        if s.degraded {
+            state.push_str(" (degraded)");
+        }
+        vec![
+            s.name.clone(),
+            state,
+            format_ago(s.last_poll),
+            match s.next_poll_in_sec {
+                Some(sec) => format!("in {sec}s"),
+                None => "now".to_string(),
+            },
+            s.last_error.clone().unwrap_or_else(|| "-".to_string()),
+        ]
+    })
+}
+
+/// Table rows for all sinks, sorted by name.
+pub(super) fn sink_rows(report: &StatusReport) -> impl Iterator<Item = Vec<String>> + '_ {
+    let mut sinks = report.sinks.iter().collect::<Vec<_>>();
+    sinks.sort_by(|a, b| a.name.cmp(&b.name));
+    sinks.into_iter().map(|s| {
+        let pending = if s.pending_on {
+            "on".to_string()
+        } else {
+            match s.power_off_pending_in_sec {
+                Some(sec) => format!("off in {sec}s"),
+                None => "-".to_string(),
+            }
+        };
+        let mut state = format!("{:?}", s.power_state).to_lowercase();
+        if s.gave_up {
+            state.push_str(" (gave up)");
+        }
+        if s.needs_reset {
+            state.push_str(" (needs reset)");
+        }
+        if s.shed {
+            state.push_str(" (shed)");
+        }
+        if let Some(forced) = s.forced {
+            state.push_str(&format!(
+                " (forced {})",
+                format!("{forced:?}").to_lowercase()
+            ));
+        }
+        vec![
+            s.name.clone(),
+            state,
+            pending,
+            format_ago(s.last_command),
+            s.last_error.clone().unwrap_or_else(|| "-".to_string()),
+        ]
+    })
+}
+
+fn format_ago(time: Option<SystemTime>) -> String {
+    match time.map(|t| t.elapsed()) {
+        None => "never".to_string(),
+        Some(Ok(elapsed)) => format!("{}s ago", elapsed.as_secs()),
+        Some(Err(_)) => "in the future".to_string(),
+    }
+}
+
+pub(super) fn print_table(headers: &[&str], rows: impl Iterator<Item = Vec<String>>) {
+    let rows = rows.collect::<Vec<_>>();
+    let widths = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([header.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    let print_row = |cells: &mut dyn Iterator<Item = &str>| {
+        let line = cells
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+    print_row(&mut headers.iter().copied());
+    for row in &rows {
+        print_row(&mut row.iter().map(String::as_str));
+    }
+}
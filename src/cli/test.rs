@@ -0,0 +1,116 @@
+use crate::cli::SinkAction;
+use crate::identity::Named;
+use crate::log::{panic_to_string, pwrst_log};
+use crate::settings;
+use crate::{sink, source};
+use futures::FutureExt;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+/// How long a timed-out source has to abort after cancellation.
+const CANCEL_GRACE: Duration = Duration::from_millis(100);
+
+/// Create the sink with the given name and run the action once.
+pub async fn run_sink(config_path: &Path, name: &str, action: SinkAction) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (base, sink) = match sink::try_create_named(&config.sink, name) {
+        None => {
+            eprintln!("No sink named \"{name}\" is configured.");
+            return ExitCode::FAILURE;
+        }
+        Some((base, Err(e))) => {
+            eprintln!("{} Failed creating sink: {e}", base.identity());
+            return ExitCode::FAILURE;
+        }
+        Some((base, Ok(sink))) => (base, sink),
+    };
+    let (action_name, fut) = match action {
+        SinkAction::On => ("on", sink.on()),
+        SinkAction::Off => ("off", sink.off()),
+    };
+    println!("{} Turning {action_name}...", base.identity());
+    let result = timeout(
+        Duration::from_secs(base.timeout_sec as u64),
+        AssertUnwindSafe(fut).catch_unwind(),
+    )
+    .await;
+    match result {
+        Ok(Ok(Ok(()))) => {
+            println!("{} OK", base.identity());
+            ExitCode::SUCCESS
+        }
+        Ok(Ok(Err(e))) => {
+            println!("{} Error: {e}", base.identity());
+            ExitCode::FAILURE
+        }
+        Ok(Err(panic)) => {
+            println!("{} Panic: {}", base.identity(), panic_to_string(panic));
+            ExitCode::FAILURE
+        }
+        Err(_) => {
+            println!("{} Timeout.", base.identity());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Create the source with the given name and check whether it is active once.
+pub async fn run_source(config_path: &Path, name: &str) -> ExitCode {
+    let config = match settings::read(config_path) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Failed reading config: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let (base, source) = match source::try_create_named(&config.source, name) {
+        None => {
+            eprintln!("No source named \"{name}\" is configured.");
+            return ExitCode::FAILURE;
+        }
+        Some((base, Err(e))) => {
+            eprintln!("{} Failed creating source: {e}", base.identity());
+            return ExitCode::FAILURE;
+        }
+        Some((base, Ok(source))) => (base, source),
+    };
+    println!("{} Checking...", base.identity());
+    let cancel = CancellationToken::new();
+    let result = timeout(
+        Duration::from_secs(base.timeout_sec as u64),
+        AssertUnwindSafe(source.is_active(&cancel)).catch_unwind(),
+    )
+    .await;
+    match result {
+        Ok(Ok(Ok(active))) => {
+            println!("{} Power state: {}", base.identity(), pwrst_log(active));
+            ExitCode::SUCCESS
+        }
+        Ok(Ok(Err(e))) => {
+            println!("{} Error: {e}", base.identity());
+            ExitCode::FAILURE
+        }
+        Ok(Err(panic)) => {
+            println!("{} Panic: {}", base.identity(), panic_to_string(panic));
+            ExitCode::FAILURE
+        }
+        Err(_) => {
+            println!("{} Timeout.", base.identity());
+            // Give the source a moment to abort what is still running, the runtime waits for
+            // blocking calls before shutting down.
+            cancel.cancel();
+            tokio::time::sleep(CANCEL_GRACE).await;
+            ExitCode::FAILURE
+        }
+    }
+}
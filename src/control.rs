@@ -0,0 +1,319 @@
+use crate::settings;
+use crate::sink;
+use crate::source;
+use crate::state::{PowerState, State};
+use crate::statistics::StatisticsReport;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::future::pending;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+#[cfg(windows)]
+use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeServer, ServerOptions};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tokio::time::timeout;
+use tracing::{debug, error, info, warn};
+
+/// Time a client has to send its request and receive the response.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A request sent to the daemon via the control socket. One JSON object per line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Request {
+    /// Get the current state of all sources and sinks.
+    Status,
+    /// Get the on-time and estimated energy use of all sources and sinks.
+    Statistics,
+    /// Force a sink on or off regardless of the sources, or with `null` return it to following
+    /// the sources.
+    SetOverride {
+        sink: String,
+        forced: Option<PowerState>,
+    },
+    /// Let a sink that waits for a manual reset after a failed command follow the sources again.
+    ResetSink { sink: String },
+    /// Force load shedding on or off regardless of its source, or with `null` return it to
+    /// following the source.
+    SetLoadShedding { forced: Option<bool> },
+    /// Create the source from the config file of the daemon, even if it is disabled there, and
+    /// start polling it. Replaces a running source with the same name.
+    AddSource { source: String },
+    /// Stop polling the source and forget about it until the daemon is restarted.
+    RemoveSource { source: String },
+    /// Create the sink from the config file of the daemon, even if it is disabled there.
+    /// Replaces a running sink with the same name.
+    AddSink { sink: String },
+    /// Stop controlling the sink, leaving it in its current state.
+    RemoveSink { sink: String },
+}
+
+/// A response from the daemon to a [`Request`]. One JSON object per line.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "kebab-case")]
+pub enum Response {
+    Status(StatusReport),
+    Statistics(StatisticsReport),
+    Ok,
+    Error { message: String },
+}
+
+/// The current state of all sources and sinks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub sources: Vec<SourceStatus>,
+    pub sinks: Vec<SinkStatus>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SourceStatus {
+    pub name: String,
+    pub power_state: PowerState,
+    /// Whether the source is considered to be asleep and is polled less often.
+    pub asleep: bool,
+    /// Whether polling keeps timing out and the source seems to be stuck.
+    pub degraded: bool,
+    pub last_poll: Option<SystemTime>,
+    /// Seconds until the next poll, if the source is waiting for it.
+    pub next_poll_in_sec: Option<u64>,
+    /// The error of the last poll, if it failed.
+    pub last_error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SinkStatus {
+    pub name: String,
+    pub power_state: PowerState,
+    /// Whether the sink will be turned on with the next sink check.
+    pub pending_on: bool,
+    /// Whether retrying failed commands was given up on until the next source transition.
+    pub gave_up: bool,
+    /// The state the sink is forced into regardless of the sources, if any.
+    pub forced: Option<PowerState>,
+    /// Whether the sink waits for a manual reset after a failed command.
+    pub needs_reset: bool,
+    /// Whether the sink is kept off because load is shed.
+    #[serde(default)]
+    pub shed: bool,
+    /// If all sources relevant for this sink are off, the seconds until it will be turned off.
+    pub power_off_pending_in_sec: Option<u64>,
+    pub last_command: Option<SystemTime>,
+    /// The error of the last command, if it failed.
+    pub last_error: Option<String>,
+}
+
+/// Listen on the control socket with the permissions `mode` and answer requests. Sources and
+/// sinks to add are read from the config file at `config_path`. Never completes.
+pub async fn serve(path: &Path, mode: u32, config_path: &Path, state: Arc<State>) {
+    let mut listener = match Listener::bind(path, mode) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed binding control socket {}: {}", path.display(), e);
+            return pending().await;
+        }
+    };
+    info!("Listening on control socket {}.", path.display());
+    loop {
+        match listener.accept().await {
+            Ok(stream) => {
+                let state = state.clone();
+                let config_path = config_path.to_path_buf();
+                tokio::spawn(async move {
+                    match timeout(CLIENT_TIMEOUT, handle_client(stream, &config_path, &state)).await
+                    {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => warn!("Failed handling control client: {}", e),
+                        Err(_) => warn!("Timeout while handling control client."),
+                    }
+                });
+            }
+            Err(e) => warn!("Failed accepting control client: {}", e),
+        }
+    }
+}
+
+#[cfg(unix)]
+struct Listener(UnixListener);
+
+#[cfg(unix)]
+impl Listener {
+    fn bind(path: &Path, mode: u32) -> io::Result<Self> {
+        use std::os::unix::fs::PermissionsExt;
+
+        // Remove a stale socket from a previous run.
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+        Ok(Self(listener))
+    }
+
+    async fn accept(&mut self) -> io::Result<UnixStream> {
+        Ok(self.0.accept().await?.0)
+    }
+}
+
+/// A named pipe. Each client connects to its own instance of the pipe, so a new one is created
+/// whenever one is connected to.
+#[cfg(windows)]
+struct Listener {
+    name: std::path::PathBuf,
+    next: NamedPipeServer,
+}
+
+#[cfg(windows)]
+impl Listener {
+    fn bind(name: &Path, _mode: u32) -> io::Result<Self> {
+        Ok(Self {
+            name: name.to_path_buf(),
+            next: ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(name)?,
+        })
+    }
+
+    async fn accept(&mut self) -> io::Result<NamedPipeServer> {
+        self.next.connect().await?;
+        let next = ServerOptions::new().create(&self.name)?;
+        Ok(std::mem::replace(&mut self.next, next))
+    }
+}
+
+async fn handle_client(
+    stream: impl AsyncRead + AsyncWrite,
+    config_path: &Path,
+    state: &State,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let (read, mut write) = tokio::io::split(stream);
+    let mut lines = BufReader::new(read).lines();
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                debug!("control request: {:?}", request);
+                handle_request(request, config_path, state)
+            }
+            Err(e) => Response::Error {
+                message: format!("invalid request: {e}"),
+            },
+        };
+        let mut response = serde_json::to_vec(&response)?;
+        response.push(b'\n');
+        write.write_all(&response).await?;
+    }
+    Ok(())
+}
+
+fn handle_request(request: Request, config_path: &Path, state: &State) -> Response {
+    match request {
+        Request::Status => Response::Status(state.status()),
+        Request::Statistics => Response::Statistics(state.statistics()),
+        Request::SetOverride { sink, forced } => {
+            let result = match forced.map(bool::try_from) {
+                None => state.set_override(&sink, None),
+                Some(Ok(on)) => state.set_override(&sink, Some(on)),
+                Some(Err(())) => Err("a sink can only be forced on or off".to_string()),
+            };
+            match result {
+                Ok(()) => Response::Ok,
+                Err(message) => Response::Error { message },
+            }
+        }
+        Request::ResetSink { sink } => match state.reset_sink(&sink) {
+            Ok(()) => Response::Ok,
+            Err(message) => Response::Error { message },
+        },
+        Request::SetLoadShedding { forced } => match state.set_shedding(forced) {
+            Ok(()) => Response::Ok,
+            Err(message) => Response::Error { message },
+        },
+        Request::AddSource { source } => match create_source(config_path, &source) {
+            Ok(source) => {
+                state.add_source(source);
+                Response::Ok
+            }
+            Err(e) => Response::Error {
+                message: e.to_string(),
+            },
+        },
+        Request::RemoveSource { source } => match state.remove_source(&source) {
+            Ok(()) => Response::Ok,
+            Err(message) => Response::Error { message },
+        },
+        Request::AddSink { sink } => match create_sink(config_path, &sink) {
+            Ok(sink) => {
+                state.add_sink(sink);
+                Response::Ok
+            }
+            Err(e) => Response::Error {
+                message: e.to_string(),
+            },
+        },
+        Request::RemoveSink { sink } => match state.remove_sink(&sink) {
+            Ok(()) => Response::Ok,
+            Err(message) => Response::Error { message },
+        },
+    }
+}
+
+/// Re-read the config file and create the source with the given name.
+fn create_source(
+    config_path: &Path,
+    name: &str,
+) -> Result<Box<dyn source::Source>, Box<dyn Error>> {
+    let config = settings::read(config_path)?;
+    let (_, result) = source::try_create_named(&config.source, name)
+        .ok_or_else(|| format!("no source named \"{name}\" in the config"))?;
+    result.map_err(|e| format!("failed creating source \"{name}\": {e}").into())
+}
+
+/// Re-read the config file and create the sink with the given name.
+fn create_sink(config_path: &Path, name: &str) -> Result<Box<dyn sink::Sink>, Box<dyn Error>> {
+    let config = settings::read(config_path)?;
+    let (_, result) = sink::try_create_named(&config.sink, name)
+        .ok_or_else(|| format!("no sink named \"{name}\" in the config"))?;
+    result.map_err(|e| format!("failed creating sink \"{name}\": {e}").into())
+}
+
+/// Send a single request to the daemon listening on the control socket.
+pub async fn request(path: &Path, request: &Request) -> Result<Response, Box<dyn Error>> {
+    let stream = connect(path).await.map_err(|e| {
+        format!(
+            "failed connecting to control socket {} (is the daemon running?): {e}",
+            path.display()
+        )
+    })?;
+    let (read, mut write) = tokio::io::split(stream);
+    let mut request = serde_json::to_vec(request)?;
+    request.push(b'\n');
+    write.write_all(&request).await?;
+    let line = BufReader::new(read)
+        .lines()
+        .next_line()
+        .await?
+        .ok_or("daemon closed the connection without responding")?;
+    Ok(serde_json::from_str(&line)?)
+}
+
+#[cfg(unix)]
+async fn connect(path: &Path) -> io::Result<UnixStream> {
+    UnixStream::connect(path).await
+}
+
+#[cfg(windows)]
+async fn connect(name: &Path) -> io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    /// All instances of the pipe are connected to by other clients.
+    const ERROR_PIPE_BUSY: i32 = 231;
+    loop {
+        match ClientOptions::new().open(name) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                tokio::time::sleep(Duration::from_millis(50)).await
+            }
+            result => return result,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+#![cfg(all(feature = "dbus", target_os = "linux"))]
+
+use crate::event::Event;
+use crate::settings::DbusBus;
+use crate::state::State;
+use std::collections::HashMap;
+use std::future::pending;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{error, info, warn};
+use zbus::{dbus_interface, fdo, Connection, ConnectionBuilder, SignalContext};
+
+const BUS_NAME: &str = "io.github.theCapypara.PersonalPowerCtrl";
+const OBJECT_PATH: &str = "/io/github/theCapypara/PersonalPowerCtrl";
+
+struct PowerCtrl {
+    state: Arc<State>,
+}
+
+#[dbus_interface(name = "io.github.theCapypara.PersonalPowerCtrl1")]
+impl PowerCtrl {
+    /// Power states of all sources by name: "on", "off" or "unknown".
+    fn source_states(&self) -> HashMap<String, String> {
+        self.state
+            .status()
+            .sources
+            .into_iter()
+            .map(|s| (s.name, s.power_state.to_string()))
+            .collect()
+    }
+
+    /// Power states of all sinks by name: "on", "off" or "unknown".
+    fn sink_states(&self) -> HashMap<String, String> {
+        self.state
+            .status()
+            .sinks
+            .into_iter()
+            .map(|s| (s.name, s.power_state.to_string()))
+            .collect()
+    }
+
+    /// The full status report, as JSON in the same format as on the control socket.
+    fn status(&self) -> fdo::Result<String> {
+        serde_json::to_string(&self.state.status()).map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Force a sink "on" or "off" regardless of the sources, or with "none" return it to
+    /// following the sources.
+    fn set_override(&self, sink: &str, forced: &str) -> fdo::Result<()> {
+        let forced = match forced {
+            "on" => Some(true),
+            "off" => Some(false),
+            "none" => None,
+            _ => {
+                return Err(fdo::Error::InvalidArgs(format!(
+                    "invalid override \"{forced}\", expected \"on\", \"off\" or \"none\""
+                )))
+            }
+        };
+        self.state
+            .set_override(sink, forced)
+            .map_err(fdo::Error::InvalidArgs)
+    }
+
+    /// Emitted whenever a source changes its power state.
+    #[dbus_interface(signal)]
+    async fn source_changed(
+        ctxt: &SignalContext<'_>,
+        source: &str,
+        power_state: &str,
+    ) -> zbus::Result<()>;
+
+    /// Emitted whenever a sink was turned on or off.
+    #[dbus_interface(signal)]
+    async fn sink_changed(
+        ctxt: &SignalContext<'_>,
+        sink: &str,
+        power_state: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// Provide the D-Bus service on the given bus and emit signals for all power transitions.
+/// Never completes.
+pub async fn serve(bus: DbusBus, state: Arc<State>) {
+    // Subscribe before connecting, so no transitions are missed.
+    let mut events = state.subscribe();
+    let connection = match connect(bus, state).await {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed providing D-Bus service on the {:?} bus: {}", bus, e);
+            return pending().await;
+        }
+    };
+    info!("Providing D-Bus service {} on the {:?} bus.", BUS_NAME, bus);
+    let ctxt = match SignalContext::new(&connection, OBJECT_PATH) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed creating D-Bus signal context: {}", e);
+            return pending().await;
+        }
+    };
+    loop {
+        let result = match events.recv().await {
+            Ok(Event::SourceChanged {
+                source,
+                power_state,
+            }) => PowerCtrl::source_changed(&ctxt, &source, &power_state.to_string()).await,
+            Ok(Event::SinkChanged { sink, power_state }) => {
+                PowerCtrl::sink_changed(&ctxt, &sink, &power_state.to_string()).await
+            }
+            Ok(_) => Ok(()),
+            Err(RecvError::Lagged(n)) => {
+                warn!("D-Bus service missed {} events.", n);
+                Ok(())
+            }
+            Err(RecvError::Closed) => return pending().await,
+        };
+        if let Err(e) = result {
+            warn!("Failed emitting D-Bus signal: {}", e);
+        }
+    }
+}
+
+async fn connect(bus: DbusBus, state: Arc<State>) -> zbus::Result<Connection> {
+    let builder = match bus {
+        DbusBus::Session => ConnectionBuilder::session()?,
+        DbusBus::System => ConnectionBuilder::system()?,
+    };
+    builder
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, PowerCtrl { state })?
+        .build()
+        .await
+}
@@ -0,0 +1,197 @@
+use crate::identity::Identity;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::io;
+use tokio::task::JoinError;
+use tokio::time::error::Elapsed;
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// What kind of failure an [`Error`] is, to decide whether trying again can help.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// The device could not be reached or the connection broke.
+    Network,
+    /// The device rejected the credentials.
+    Auth,
+    /// The device responded in an unexpected way or reported a failure.
+    Protocol,
+    /// The device did not respond in time.
+    Timeout,
+    /// Anything else, e.g. a failing local command.
+    Other,
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Auth => "authentication",
+            ErrorKind::Protocol => "protocol",
+            ErrorKind::Timeout => "timeout",
+            ErrorKind::Other => "other",
+        })
+    }
+}
+
+impl ErrorKind {
+    /// Whether trying again soon may succeed. Rejected credentials won't be accepted on the
+    /// next attempt either.
+    pub fn is_retryable(self) -> bool {
+        self != ErrorKind::Auth
+    }
+}
+
+/// The error of a sink command or a source poll.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Network(BoxError),
+    #[error(transparent)]
+    Auth(BoxError),
+    #[error(transparent)]
+    Protocol(BoxError),
+    #[error("timeout")]
+    Timeout,
+    #[error(transparent)]
+    Other(BoxError),
+    /// The error of another sink or source, e.g. a member of a group.
+    #[error("{identity} {source}")]
+    In {
+        identity: Identity<'static>,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    pub fn network(error: impl Into<BoxError>) -> Self {
+        Error::Network(error.into())
+    }
+
+    pub fn auth(error: impl Into<BoxError>) -> Self {
+        Error::Auth(error.into())
+    }
+
+    pub fn protocol(error: impl Into<BoxError>) -> Self {
+        Error::Protocol(error.into())
+    }
+
+    pub fn other(error: impl Into<BoxError>) -> Self {
+        Error::Other(error.into())
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Network(_) => ErrorKind::Network,
+            Error::Auth(_) => ErrorKind::Auth,
+            Error::Protocol(_) => ErrorKind::Protocol,
+            Error::Timeout => ErrorKind::Timeout,
+            Error::Other(_) => ErrorKind::Other,
+            Error::In { source, .. } => source.kind(),
+        }
+    }
+
+    /// Attribute the error to the sink or source with the identity.
+    pub fn of(self, identity: Identity<'static>) -> Self {
+        Error::In {
+            identity,
+            source: Box::new(self),
+        }
+    }
+}
+
+/// Classifies errors that were passed on as a [`BoxError`], keeping the kind of an [`Error`].
+impl From<BoxError> for Error {
+    fn from(error: BoxError) -> Self {
+        let error = match error.downcast::<Error>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<io::Error>() {
+            Ok(error) => return (*error).into(),
+            Err(error) => error,
+        };
+        #[cfg(feature = "reqwest")]
+        let error = match error.downcast::<reqwest::Error>() {
+            Ok(error) => return (*error).into(),
+            Err(error) => error,
+        };
+        match error.downcast::<Elapsed>() {
+            Ok(_) => Error::Timeout,
+            Err(error) => Error::Other(error),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::TimedOut => Error::Timeout,
+            io::ErrorKind::PermissionDenied => Error::Auth(error.into()),
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                Error::Protocol(error.into())
+            }
+            io::ErrorKind::NotFound | io::ErrorKind::Other => Error::Other(error.into()),
+            _ => Error::Network(error.into()),
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        use reqwest::StatusCode;
+        match error.status() {
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => Error::Auth(error.into()),
+            Some(_) => Error::Protocol(error.into()),
+            None if error.is_timeout() => Error::Timeout,
+            None if error.is_decode() => Error::Protocol(error.into()),
+            None => Error::Network(error.into()),
+        }
+    }
+}
+
+#[cfg(feature = "kodi-jsonrpc-client")]
+impl From<kodi_jsonrpc_client::Error> for Error {
+    fn from(error: kodi_jsonrpc_client::Error) -> Self {
+        Error::Network(error.into())
+    }
+}
+
+#[cfg(all(feature = "zbus", target_os = "linux"))]
+impl From<zbus::Error> for Error {
+    fn from(error: zbus::Error) -> Self {
+        Error::Other(error.into())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Error::Protocol(error.into())
+    }
+}
+
+impl From<Elapsed> for Error {
+    fn from(_: Elapsed) -> Self {
+        Error::Timeout
+    }
+}
+
+impl From<JoinError> for Error {
+    fn from(error: JoinError) -> Self {
+        Error::Other(error.into())
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Other(message.into())
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Other(message.into())
+    }
+}
@@ -0,0 +1,142 @@
+use crate::state::PowerState;
+use crate::statistics::PeriodReport;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Something that happened in the state machine or the app. See
+/// [`crate::state::State::subscribe`].
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// The daemon started.
+    Started,
+    /// The daemon is shutting down.
+    Stopping,
+    /// A source changed its power state.
+    SourceChanged {
+        source: String,
+        power_state: PowerState,
+    },
+    /// Polling a source failed.
+    SourceFailed { source: String, error: String },
+    /// Polling a source succeeded again after failing.
+    SourceRecovered { source: String },
+    /// Polling a source failed so often in a row, that its state can be considered unknown.
+    SourceUnknown {
+        source: String,
+        failures: u32,
+        error: String,
+    },
+    /// A sink was turned on or off.
+    SinkChanged {
+        sink: String,
+        power_state: PowerState,
+    },
+    /// Turning a sink on or off failed.
+    SinkCommandFailed { sink: String, error: String },
+    /// Retrying to turn a sink on or off was given up until the next source transition.
+    SinkGaveUp { sink: String, attempts: u32 },
+    /// A sink with `confirm-off` is about to be turned off, unless it is overridden in time.
+    SinkOffPending { sink: String, in_sec: u64 },
+    /// Load shedding started or ended.
+    LoadShedding { active: bool },
+    /// A report period ended, see `report` in the general settings.
+    PowerReport { report: PeriodReport },
+}
+
+/// The kind of an [`Event`], without its details.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum EventKind {
+    Started,
+    Stopping,
+    SourceChanged,
+    SourceFailed,
+    SourceRecovered,
+    SourceUnknown,
+    SinkChanged,
+    SinkCommandFailed,
+    SinkGaveUp,
+    SinkOffPending,
+    LoadShedding,
+    PowerReport,
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::Started => EventKind::Started,
+            Event::Stopping => EventKind::Stopping,
+            Event::SourceChanged { .. } => EventKind::SourceChanged,
+            Event::SourceFailed { .. } => EventKind::SourceFailed,
+            Event::SourceRecovered { .. } => EventKind::SourceRecovered,
+            Event::SourceUnknown { .. } => EventKind::SourceUnknown,
+            Event::SinkChanged { .. } => EventKind::SinkChanged,
+            Event::SinkCommandFailed { .. } => EventKind::SinkCommandFailed,
+            Event::SinkGaveUp { .. } => EventKind::SinkGaveUp,
+            Event::SinkOffPending { .. } => EventKind::SinkOffPending,
+            Event::LoadShedding { .. } => EventKind::LoadShedding,
+            Event::PowerReport { .. } => EventKind::PowerReport,
+        }
+    }
+}
+
+impl EventKind {
+    /// Whether notifiers are notified about events of this kind if they don't configure
+    /// otherwise. Regular power transitions and single failed polls are too frequent for this.
+    pub fn notify_by_default(self) -> bool {
+        !matches!(
+            self,
+            EventKind::SourceChanged
+                | EventKind::SourceFailed
+                | EventKind::SourceRecovered
+                | EventKind::SinkChanged
+        )
+    }
+}
+
+impl Display for Event {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Event::Started => write!(f, "Started."),
+            Event::Stopping => write!(f, "Shutting down."),
+            Event::SourceChanged {
+                source,
+                power_state,
+            } => write!(f, "Source {source} is now {power_state}."),
+            Event::SourceFailed { source, error } => {
+                write!(f, "Failed polling source {source}: {error}")
+            }
+            Event::SourceRecovered { source } => write!(f, "Source {source} recovered."),
+            Event::SourceUnknown {
+                source,
+                failures,
+                error,
+            } => write!(
+                f,
+                "Source {source} failed {failures} times in a row, its state is unknown: {error}"
+            ),
+            Event::SinkChanged { sink, power_state } => {
+                write!(f, "Sink {sink} was turned {power_state}.")
+            }
+            Event::SinkCommandFailed { sink, error } => {
+                write!(f, "Failed setting power state of sink {sink}: {error}")
+            }
+            Event::SinkGaveUp { sink, attempts } => write!(
+                f,
+                "Gave up setting power state of sink {sink} after {attempts} attempts."
+            ),
+            Event::SinkOffPending { sink, in_sec } => write!(
+                f,
+                "Turning off sink {sink} in {in_sec} sec. To keep it on, run \
+                `personal-power-ctrl override \"{sink}\" on`."
+            ),
+            Event::LoadShedding { active: true } => {
+                write!(f, "Load shedding started, turning off low priority sinks.")
+            }
+            Event::LoadShedding { active: false } => write!(f, "Load shedding ended."),
+            Event::PowerReport { report } => write!(f, "{report}"),
+        }
+    }
+}
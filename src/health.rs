@@ -0,0 +1,30 @@
+#![cfg(any(feature = "http", test))]
+
+use crate::error::ErrorKind;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+/// Aggregated health of all sources and sinks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Whether all components are healthy.
+    pub healthy: bool,
+    pub components: Vec<ComponentHealth>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComponentHealth {
+    /// `source` or `sink`.
+    pub category: String,
+    pub name: String,
+    pub healthy: bool,
+    /// Whether the source keeps timing out and seems to be stuck. Always false for sinks.
+    pub degraded: bool,
+    /// Failed polls or commands in a row.
+    pub consecutive_errors: u32,
+    /// Kind of the error of the last poll or command, if it failed.
+    #[serde(default)]
+    pub error_kind: Option<ErrorKind>,
+    /// The last successful poll or command.
+    pub last_success: Option<SystemTime>,
+}
@@ -0,0 +1,131 @@
+#![cfg(feature = "http")]
+
+use crate::health::HealthReport;
+#[cfg(feature = "source-webhook")]
+use crate::source::PushError;
+use crate::state::State;
+use crate::statistics::OnTimeReport;
+use axum::extract;
+use axum::http::StatusCode;
+use axum::routing::get;
+#[cfg(feature = "source-webhook")]
+use axum::routing::post;
+#[cfg(feature = "source-webhook")]
+use axum::Form;
+use axum::{Json, Router, Server};
+#[cfg(feature = "source-webhook")]
+use serde::Deserialize;
+use std::fmt::Write;
+use std::future::pending;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+/// Serve the HTTP API on the given address. Never completes.
+pub async fn serve(addr: SocketAddr, state: Arc<State>) {
+    let app = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/metrics", get(metrics));
+    #[cfg(feature = "source-webhook")]
+    let app = app.route("/webhook/:source", post(webhook));
+    let app = app.with_state(state);
+    let server = match Server::try_bind(&addr) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed binding HTTP server to {}: {}", addr, e);
+            return pending().await;
+        }
+    };
+    info!("Listening for HTTP requests on {}.", addr);
+    if let Err(e) = server.serve(app.into_make_service()).await {
+        error!("HTTP server failed: {}", e);
+    }
+    pending().await
+}
+
+/// The health of all components. Fails with 503 if any component is unhealthy.
+async fn healthz(
+    extract::State(state): extract::State<Arc<State>>,
+) -> (StatusCode, Json<HealthReport>) {
+    let health = state.health();
+    let status = if health.healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(health))
+}
+
+#[cfg(feature = "source-webhook")]
+#[derive(Deserialize)]
+struct Push {
+    active: bool,
+    secret: String,
+}
+
+/// Push the state of a webhook source, as a form with `active` and `secret`.
+#[cfg(feature = "source-webhook")]
+async fn webhook(
+    extract::State(state): extract::State<Arc<State>>,
+    extract::Path(source): extract::Path<String>,
+    Form(push): Form<Push>,
+) -> StatusCode {
+    match state.push_to_source(&source, &push.secret, push.active) {
+        Ok(()) => StatusCode::NO_CONTENT,
+        Err(PushError::NotFound | PushError::NotAccepted) => StatusCode::NOT_FOUND,
+        Err(PushError::Unauthorized) => StatusCode::FORBIDDEN,
+    }
+}
+
+/// The statistics in the Prometheus text format.
+async fn metrics(extract::State(state): extract::State<Arc<State>>) -> String {
+    let statistics = state.statistics();
+    let mut out = String::new();
+    out.push_str("# HELP ppc_on_seconds_total Time a source or sink was on.\n");
+    out.push_str("# TYPE ppc_on_seconds_total counter\n");
+    let all = [("source", &statistics.sources), ("sink", &statistics.sinks)];
+    for (category, reports) in all {
+        for report in reports {
+            writeln!(
+                out,
+                "ppc_on_seconds_total{{category=\"{category}\",name=\"{}\"}} {}",
+                escape_label(&report.name),
+                report.total_sec
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("# HELP ppc_source_degraded Whether a source keeps timing out.\n");
+    out.push_str("# TYPE ppc_source_degraded gauge\n");
+    for component in state.health().components {
+        if component.category == "source" {
+            writeln!(
+                out,
+                "ppc_source_degraded{{name=\"{}\"}} {}",
+                escape_label(&component.name),
+                u8::from(component.degraded)
+            )
+            .unwrap();
+        }
+    }
+    out.push_str("# HELP ppc_energy_watt_hours_total Estimated energy use of a sink.\n");
+    out.push_str("# TYPE ppc_energy_watt_hours_total counter\n");
+    for OnTimeReport { name, total_wh, .. } in &statistics.sinks {
+        if let Some(wh) = total_wh {
+            writeln!(
+                out,
+                "ppc_energy_watt_hours_total{{name=\"{}\"}} {wh}",
+                escape_label(name)
+            )
+            .unwrap();
+        }
+    }
+    out
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
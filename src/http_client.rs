@@ -0,0 +1,54 @@
+#![cfg(feature = "reqwest")]
+
+use crate::settings::HttpClientSettings;
+use reqwest::{Certificate, ClientBuilder, Identity, Proxy, Url};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+static SETTINGS: Mutex<Option<HttpClientSettings>> = Mutex::new(None);
+
+/// Use these settings for all clients built from now on.
+pub fn configure(settings: &HttpClientSettings) {
+    *SETTINGS.lock().unwrap() = Some(settings.clone());
+}
+
+/// A builder for a client sending requests to the URL, with the `[general.http]` settings
+/// applied. Modules may adjust it further before building the client, which they should keep
+/// to reuse its connections.
+pub fn builder(url: &Url) -> Result<ClientBuilder, Box<dyn Error + Send + Sync>> {
+    let settings = SETTINGS.lock().unwrap().clone().unwrap_or_default();
+    let user_agent = settings.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+    let mut builder = reqwest::Client::builder().user_agent(user_agent);
+    if let Some(proxy) = &settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    for path in settings.root_certificates.iter() {
+        builder = builder.add_root_certificate(Certificate::from_pem(&read(path)?)?);
+    }
+    match (&settings.client_certificate, &settings.client_key) {
+        (Some(cert), Some(key)) => {
+            builder = builder.identity(Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)?);
+        }
+        (None, None) => {}
+        _ => return Err("client-certificate and client-key must be set together".into()),
+    }
+    let host = url.host_str().unwrap_or_default();
+    if settings.accept_invalid_certs.iter().any(|h| h == host) {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(sec) = settings.timeout_sec {
+        builder = builder.timeout(Duration::from_secs(sec));
+    }
+    if let Some(sec) = settings.connect_timeout_sec {
+        builder = builder.connect_timeout(Duration::from_secs(sec));
+    }
+    Ok(builder)
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    std::fs::read(path).map_err(|e| format!("failed reading {}: {e}", path.display()).into())
+}
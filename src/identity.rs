@@ -1,4 +1,6 @@
-use crate::settings::{SinkBaseSettings, SourceBaseSettings};
+use crate::settings::{
+    NotifierBaseSettings, RouteSettings, SinkBaseSettings, SourceBaseSettings, ZoneSettings,
+};
 use crate::sink::Sink;
 use crate::source::Source;
 use std::borrow::Cow;
@@ -79,6 +81,33 @@ impl Named for SourceBaseSettings {
     }
 }
 
+impl Named for NotifierBaseSettings {
+    fn category(&self) -> &'static str {
+        "notify"
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Named for ZoneSettings {
+    fn category(&self) -> &'static str {
+        "zone"
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Named for RouteSettings {
+    fn category(&self) -> &'static str {
+        "route"
+    }
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
 impl Named for IsSink {
     #[inline]
     fn category(&self) -> &'static str {
@@ -0,0 +1,49 @@
+#![cfg(any(feature = "discover", feature = "sink-hs100"))]
+
+#[cfg(feature = "sink-hs100")]
+use serde_json::Value;
+#[cfg(feature = "sink-hs100")]
+use std::error::Error;
+#[cfg(feature = "sink-hs100")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "sink-hs100")]
+use tokio::net::TcpStream;
+
+/// Port of the local protocol of TP-Link Kasa devices, over both TCP and UDP.
+pub const PORT: u16 = 9999;
+/// Largest response that is read.
+#[cfg(feature = "sink-hs100")]
+const MAX_RESPONSE_LEN: usize = 64 * 1024;
+
+/// The "autokey" cipher of TP-Link Kasa devices, encrypting if `encrypt` is true.
+pub fn xor(data: &[u8], encrypt: bool) -> Vec<u8> {
+    let mut key = 171;
+    data.iter()
+        .map(|&byte| {
+            let out = byte ^ key;
+            key = if encrypt { out } else { byte };
+            out
+        })
+        .collect()
+}
+
+/// Send a request to the device over TCP and return its response. `host` may include the
+/// port.
+#[cfg(feature = "sink-hs100")]
+pub async fn request(host: &str, request: &Value) -> Result<Value, Box<dyn Error + Send + Sync>> {
+    let mut stream = if host.contains(':') {
+        TcpStream::connect(host).await?
+    } else {
+        TcpStream::connect((host, PORT)).await?
+    };
+    let request = xor(&serde_json::to_vec(request)?, true);
+    stream.write_u32(request.len() as u32).await?;
+    stream.write_all(&request).await?;
+    let len = stream.read_u32().await? as usize;
+    if len > MAX_RESPONSE_LEN {
+        return Err(format!("response of {len} bytes is too large").into());
+    }
+    let mut response = vec![0; len];
+    stream.read_exact(&mut response).await?;
+    Ok(serde_json::from_slice(&xor(&response, false))?)
+}
@@ -0,0 +1,153 @@
+#![cfg(feature = "klap")]
+
+use crate::error;
+use crate::http_client;
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use reqwest::header::{COOKIE, SET_COOKIE};
+use reqwest::{Response, StatusCode, Url};
+use serde_json::Value;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use tokio::sync::Mutex;
+use tracing::debug;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+type Encryptor = cbc::Encryptor<aes::Aes128>;
+type Decryptor = cbc::Decryptor<aes::Aes128>;
+
+const SEED_LEN: usize = 16;
+
+/// A client for KLAP, the authenticated local protocol of newer TP-Link Tapo and Kasa devices.
+/// Requests are JSON objects, encrypted with a key derived from the credentials of the
+/// TP-Link account the device is registered with.
+pub struct Client {
+    base_url: Url,
+    client: reqwest::Client,
+    auth_hash: [u8; 32],
+    /// Requests are sent one after another, since each one needs the next sequence number.
+    session: Mutex<Option<Session>>,
+}
+
+/// The keys and cookie of a successful handshake.
+struct Session {
+    cookie: String,
+    key: [u8; 16],
+    iv: [u8; 12],
+    signature_key: [u8; 28],
+    seq: i32,
+}
+
+impl Client {
+    /// A client for the device at `host`, which may include the port. `user` is the email
+    /// address of the TP-Link account.
+    pub fn new(host: &str, user: &str, pass: &str) -> Result<Self> {
+        let base_url = Url::parse(&format!("http://{host}/app/"))?;
+        let client = http_client::builder(&base_url)?.build()?;
+        let auth_hash = sha256(&[&Sha1::digest(user), &Sha1::digest(pass)]);
+        Ok(Self {
+            base_url,
+            client,
+            auth_hash,
+            session: Mutex::new(None),
+        })
+    }
+
+    /// Send the request and return the response. A new session is started if there is none
+    /// yet, or if the device rejects the current one, which expires after a day.
+    pub async fn request(&self, request: &Value) -> Result<Value> {
+        let mut session = self.session.lock().await;
+        if let Some(current) = session.as_mut() {
+            match self.send(current, request).await? {
+                Some(response) => return Ok(response),
+                None => debug!("KLAP session expired"),
+            }
+        }
+        let new_session = session.insert(self.handshake().await?);
+        self.send(new_session, request)
+            .await?
+            .ok_or_else(|| "request rejected right after the handshake".into())
+    }
+
+    async fn handshake(&self) -> Result<Session> {
+        let local_seed: [u8; SEED_LEN] = std::array::from_fn(|_| fastrand::u8(..));
+        let response = self
+            .post("handshake1", None, local_seed.to_vec())
+            .await?
+            .error_for_status()?;
+        let cookie = response
+            .headers()
+            .get(SET_COOKIE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(';').next())
+            .ok_or("handshake without session cookie")?
+            .to_string();
+        let body = response.bytes().await?;
+        if body.len() != SEED_LEN + 32 {
+            return Err(format!("unexpected handshake response of {} bytes", body.len()).into());
+        }
+        let (remote_seed, server_hash) = body.split_at(SEED_LEN);
+        if server_hash != sha256(&[&local_seed, remote_seed, &self.auth_hash]) {
+            return Err(error::Error::auth(
+                "the device does not accept the credentials, they must be the ones of \
+                the TP-Link account it is registered with",
+            )
+            .into());
+        }
+        let client_hash = sha256(&[remote_seed, &local_seed, &self.auth_hash]);
+        self.post("handshake2", Some(&cookie), client_hash.to_vec())
+            .await?
+            .error_for_status()?;
+
+        let derive = |label: &[u8]| sha256(&[label, &local_seed, remote_seed, &self.auth_hash]);
+        let iv = derive(b"iv");
+        Ok(Session {
+            cookie,
+            key: derive(b"lsk")[..16].try_into().unwrap(),
+            iv: iv[..12].try_into().unwrap(),
+            signature_key: derive(b"ldk")[..28].try_into().unwrap(),
+            seq: i32::from_be_bytes(iv[28..].try_into().unwrap()),
+        })
+    }
+
+    /// Send the request in the session. Returns `None` if the device rejects the session.
+    async fn send(&self, session: &mut Session, request: &Value) -> Result<Option<Value>> {
+        session.seq = session.seq.wrapping_add(1);
+        let seq = session.seq.to_be_bytes();
+        let iv: [u8; 16] = [&session.iv[..], &seq].concat().try_into().unwrap();
+        let encrypted = Encryptor::new(&session.key.into(), &iv.into())
+            .encrypt_padded_vec_mut::<Pkcs7>(&serde_json::to_vec(request)?);
+        let signature = sha256(&[&session.signature_key, &seq, &encrypted]);
+        let body = [&signature[..], &encrypted].concat();
+
+        let path = format!("request?seq={}", session.seq);
+        let response = self.post(&path, Some(&session.cookie), body).await?;
+        if response.status() == StatusCode::FORBIDDEN {
+            return Ok(None);
+        }
+        let body = response.error_for_status()?.bytes().await?;
+        let encrypted = body.get(32..).ok_or("truncated response")?;
+        let decrypted = Decryptor::new(&session.key.into(), &iv.into())
+            .decrypt_padded_vec_mut::<Pkcs7>(encrypted)
+            .map_err(|_| "failed decrypting the response")?;
+        Ok(Some(serde_json::from_slice(&decrypted)?))
+    }
+
+    async fn post(&self, path: &str, cookie: Option<&str>, body: Vec<u8>) -> Result<Response> {
+        let url = self.base_url.join(path)?;
+        let mut request = self.client.post(url).body(body);
+        if let Some(cookie) = cookie {
+            request = request.header(COOKIE, cookie);
+        }
+        Ok(request.send().await?)
+    }
+}
+
+fn sha256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
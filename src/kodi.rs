@@ -0,0 +1,47 @@
+#![cfg(any(feature = "source-kodi", feature = "sink-kodi-rpc-cec"))]
+
+use crate::http_client;
+use crate::settings::PassSettings;
+use kodi_jsonrpc_client::KodiClient;
+use reqwest::Url;
+use serde::Deserialize;
+use std::error::Error;
+use std::time::Duration;
+
+/// How to reach the JSON-RPC API of a Kodi instance.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct ConnectionSettings {
+    pub jsonrpc: String,
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    /// Accept invalid TLS certificates, for HTTPS with a self-signed certificate.
+    #[serde(default)]
+    pub insecure: bool,
+    pub connect_timeout_sec: Option<u64>,
+}
+
+impl ConnectionSettings {
+    /// Create the client, which is meant to be kept to reuse its connections.
+    pub fn client(&self) -> Result<KodiClient, Box<dyn Error + Send + Sync>> {
+        let mut url = Url::parse(&self.jsonrpc)?;
+        let mut builder = http_client::builder(&url)?;
+        if self.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        if let Some(sec) = self.connect_timeout_sec {
+            builder = builder.connect_timeout(Duration::from_secs(sec));
+        }
+        if let Some(user) = &self.user {
+            url.set_username(user)
+                .map_err(|_| "failed setting user on kodi rpc")?;
+        }
+        if let Some(pass) = self.pass.resolve()? {
+            url.set_password(Some(&pass))
+                .map_err(|_| "failed setting pass on kodi rpc")?;
+        }
+        Ok(KodiClient::new(builder.build()?, url))
+    }
+}
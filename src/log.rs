@@ -1,29 +1,65 @@
+use crate::error::ErrorKind;
 use std::any::Any;
 use std::env;
 use std::error::Error;
 use std::io::{stdout, IsTerminal};
 use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
 use tracing_subscriber::filter::Targets;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
-use tracing_subscriber::{fmt, Layer};
+use tracing_subscriber::{fmt, reload, Registry};
 
 #[must_use = "this may hold resources used for logging purposes until dropped."]
-pub struct LogHandle(());
+pub struct LogHandle {
+    /// Targets read from `RUST_LOG` at startup.
+    env_targets: Targets,
+    /// Handle to swap out the active filter.
+    reload_handle: reload::Handle<Targets, Registry>,
+}
+
+impl LogHandle {
+    /// Apply the log targets from the configuration on top of the ones from `RUST_LOG`.
+    ///
+    /// Targets from the configuration take precedence over the ones from the environment if they
+    /// name the same target. This can be called again at any time to replace the previously
+    /// applied configuration targets.
+    pub fn apply_config(&self, config_targets: Option<&str>) -> Result<(), Box<dyn Error>> {
+        let targets = match config_targets {
+            None => self.env_targets.clone(),
+            Some(v) => {
+                let config_targets = Targets::from_str(v)?;
+                let mut targets = self.env_targets.clone().with_targets(&config_targets);
+                if let Some(level) = config_targets.default_level() {
+                    targets = targets.with_default(level);
+                }
+                targets
+            }
+        };
+        self.reload_handle.reload(targets).map_err(Into::into)
+    }
+}
 
 pub fn setup() -> Result<LogHandle, Box<dyn Error>> {
-    let targets = match env::var_os("RUST_LOG") {
+    let env_targets = match env::var_os("RUST_LOG") {
         None => Targets::default(),
         Some(v) => Targets::from_str(v.to_string_lossy().as_ref())?,
     };
 
+    let (targets, reload_handle) = reload::Layer::new(env_targets.clone());
     let console = fmt::layer().pretty().with_ansi(stdout().is_terminal());
 
     tracing_subscriber::registry()
-        .with(targets.and_then(console))
+        .with(targets)
+        .with(console)
         .try_init()?;
 
-    Ok(LogHandle(()))
+    Ok(LogHandle {
+        env_targets,
+        reload_handle,
+    })
 }
 
 pub fn pwrst_log(x: bool) -> &'static str {
@@ -42,3 +78,67 @@ pub fn panic_to_string(panic: Box<dyn Any + Send>) -> String {
         },
     }
 }
+
+/// How often an error that keeps repeating is logged again, as a summary.
+const REPEAT_SUMMARY_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Deduplicates the errors of a source or sink, so that one that keeps failing the same way
+/// doesn't flood the log. The first error of a kind is logged, then a summary at most every
+/// [`REPEAT_SUMMARY_INTERVAL`] while it repeats.
+#[derive(Default)]
+pub struct RepeatedErrors(Mutex<Option<Repeats>>);
+
+struct Repeats {
+    kind: ErrorKind,
+    /// Errors since the last time one was logged.
+    unlogged: u32,
+    last_logged: Instant,
+}
+
+/// How to log an error, see [`RepeatedErrors::record`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorLog {
+    /// The first error of its kind in a row, log it.
+    First,
+    /// The error repeated this often during the duration since it was last logged, log a
+    /// summary.
+    Summary(u32, Duration),
+    /// The error repeated and was logged recently.
+    Suppressed,
+}
+
+impl RepeatedErrors {
+    /// Record an error of the kind and return how to log it.
+    pub fn record(&self, kind: ErrorKind) -> ErrorLog {
+        let now = Instant::now();
+        let mut repeats = self.0.lock().unwrap();
+        match repeats.as_mut() {
+            Some(repeats) if repeats.kind == kind => {
+                repeats.unlogged += 1;
+                let since = now - repeats.last_logged;
+                if since < REPEAT_SUMMARY_INTERVAL {
+                    return ErrorLog::Suppressed;
+                }
+                let count = std::mem::take(&mut repeats.unlogged);
+                repeats.last_logged = now;
+                ErrorLog::Summary(count, since)
+            }
+            _ => {
+                *repeats = Some(Repeats {
+                    kind,
+                    unlogged: 0,
+                    last_logged: now,
+                });
+                ErrorLog::First
+            }
+        }
+    }
+
+    /// Forget the errors after a success.
+    pub fn clear(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests;
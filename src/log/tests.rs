@@ -0,0 +1,31 @@
+use super::{ErrorLog, RepeatedErrors, REPEAT_SUMMARY_INTERVAL};
+use crate::error::ErrorKind;
+use std::time::Duration;
+use tokio::time::advance;
+
+#[tokio::test(start_paused = true)]
+async fn summarizes_repeated_errors() {
+    let errors = RepeatedErrors::default();
+    assert_eq!(errors.record(ErrorKind::Network), ErrorLog::First);
+    for _ in 0..9 {
+        advance(Duration::from_secs(60)).await;
+        assert_eq!(errors.record(ErrorKind::Network), ErrorLog::Suppressed);
+    }
+    advance(Duration::from_secs(60)).await;
+    assert_eq!(
+        errors.record(ErrorKind::Network),
+        ErrorLog::Summary(10, REPEAT_SUMMARY_INTERVAL)
+    );
+    advance(Duration::from_secs(60)).await;
+    assert_eq!(errors.record(ErrorKind::Network), ErrorLog::Suppressed);
+}
+
+#[tokio::test(start_paused = true)]
+async fn logs_new_kinds_and_after_success() {
+    let errors = RepeatedErrors::default();
+    assert_eq!(errors.record(ErrorKind::Network), ErrorLog::First);
+    assert_eq!(errors.record(ErrorKind::Auth), ErrorLog::First);
+    assert_eq!(errors.record(ErrorKind::Auth), ErrorLog::Suppressed);
+    errors.clear();
+    assert_eq!(errors.record(ErrorKind::Auth), ErrorLog::First);
+}
@@ -3,51 +3,238 @@ extern crate async_trait;
 #[macro_use]
 extern crate atomic_enum;
 
+use crate::cli::{Cli, Command};
+use crate::event::Event;
+use crate::log::LogHandle;
+use crate::notifier::Notifiers;
 use crate::settings::Settings;
 use crate::sink::create_sinks;
 use crate::source::create_sources;
 use crate::state::State;
 use async_ctrlc::CtrlC;
-use tracing::{error, info};
+use clap::Parser;
+use std::future::Future;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
 
+mod adb;
 mod async_util;
+mod cec;
+mod cli;
+mod control;
+mod dbus;
+mod error;
+mod event;
+mod health;
+mod http;
+mod http_client;
 mod identity;
+mod kasa;
+mod klap;
+mod kodi;
 mod log;
+mod modbus;
+mod mqtt;
+mod neighbor;
+mod notifier;
+mod registry;
+mod service;
 mod settings;
 mod sink;
 mod source;
+mod ssh;
 mod state;
+mod statistics;
+mod telegram;
+mod testing;
 
-async fn run(config: Settings) {
-    let mut state = State::new(config.general);
+async fn init(config: &Settings) -> State {
+    let mut state = State::new(config.general.clone(), &config.zone, &config.route);
     create_sinks(&config.sink, &mut state)
         .await
         .expect("Failed to init sinks.");
     create_sources(&config.source, &mut state)
         .await
         .expect("Failed to init sources.");
+    state
+}
+
+async fn run(config_path: &Path, config: &Settings, state: Arc<State>) {
     // This will never complete.
-    state.run().await;
+    #[cfg(all(feature = "dbus", target_os = "linux"))]
+    let dbus = async {
+        match config.general.dbus {
+            Some(bus) => dbus::serve(bus, state.clone()).await,
+            None => std::future::pending().await,
+        }
+    };
+    #[cfg(not(all(feature = "dbus", target_os = "linux")))]
+    let dbus = std::future::pending::<()>();
+    #[cfg(feature = "http")]
+    let http = async {
+        match config.general.http_listen {
+            Some(addr) => http::serve(addr, state.clone()).await,
+            None => std::future::pending().await,
+        }
+    };
+    #[cfg(not(feature = "http"))]
+    let http = std::future::pending::<()>();
+    #[cfg(feature = "telegram")]
+    let telegram = async {
+        match &config.general.telegram {
+            Some(settings) => telegram::serve(settings, state.clone()).await,
+            None => std::future::pending().await,
+        }
+    };
+    #[cfg(not(feature = "telegram"))]
+    let telegram = std::future::pending::<()>();
+    tokio::select! {
+        _ = state.clone().run() => {},
+        _ = control::serve(
+            &config.general.control_socket,
+            config.general.control_socket_mode,
+            config_path,
+            state.clone(),
+        ) => {},
+        _ = dbus => {},
+        _ = http => {},
+        _ = telegram => {}
+    }
     unreachable!("App loop somehow completed.");
 }
 
-#[tokio::main]
-async fn main() {
-    let _log = log::setup().expect("failed setting up logging");
+/// Re-read the log settings from the config whenever a `SIGHUP` is received.
+#[cfg(unix)]
+async fn reload_log_on_hangup(config_path: &Path, log: &LogHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("Failed registering SIGHUP handler, log settings can not be reloaded: {e}");
+            return std::future::pending().await;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        info!("Reloading log settings...");
+        match settings::read(config_path)
+            .map(|config| log.apply_config(config.general.log.as_deref()))
+        {
+            Ok(Ok(())) => info!("Reloaded log settings."),
+            Ok(Err(e)) => error!("Failed applying log settings: {e}"),
+            Err(e) => error!("Failed reading config: {e}"),
+        }
+    }
+    std::future::pending().await
+}
+
+#[cfg(not(unix))]
+async fn reload_log_on_hangup(_config_path: &Path, _log: &LogHandle) {
+    std::future::pending().await
+}
+
+/// Completes once the daemon is asked to stop with Ctrl+C. The handler is registered right away.
+#[cfg(not(windows))]
+fn shutdown_signal() -> impl Future<Output = ()> {
+    CtrlC::new().expect("failed creating Ctrl+C handler")
+}
+
+/// Completes once the daemon is asked to stop with Ctrl+C, by closing its console or by the
+/// system shutting down. The handlers are registered right away.
+#[cfg(windows)]
+fn shutdown_signal() -> impl Future<Output = ()> {
+    use tokio::signal::windows::{ctrl_close, ctrl_shutdown};
+
     let ctrlc = CtrlC::new().expect("failed creating Ctrl+C handler");
+    let mut close = ctrl_close().expect("failed creating console close handler");
+    let mut shutdown = ctrl_shutdown().expect("failed creating shutdown handler");
+    async move {
+        tokio::select! {
+            _ = ctrlc => {},
+            _ = close.recv() => {},
+            _ = shutdown.recv() => {}
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let log = log::setup().expect("failed setting up logging");
+    match cli.command.unwrap_or_default() {
+        Command::Run => {
+            daemon(&cli.config, log, shutdown_signal()).await;
+            ExitCode::SUCCESS
+        }
+        #[cfg(all(feature = "windows-service", windows))]
+        Command::Service => service::run(&cli.config, log).await,
+        Command::CheckConfig => cli::check_config::run(&cli.config).await,
+        Command::Status => cli::status::run(&cli.config).await,
+        Command::Capabilities => {
+            cli::capabilities::run();
+            ExitCode::SUCCESS
+        }
+        Command::Stats => cli::stats::run(&cli.config).await,
+        #[cfg(feature = "monitor")]
+        Command::Monitor => cli::monitor::run(&cli.config).await,
+        Command::Override { name, action } => {
+            cli::set_override::run(&cli.config, &name, action).await
+        }
+        #[cfg(feature = "schema")]
+        Command::Schema => cli::schema::run(),
+        Command::Reset { name } => cli::set_override::reset(&cli.config, &name).await,
+        Command::Shed { action } => cli::set_override::shed(&cli.config, action).await,
+        Command::Enable { kind, name } => {
+            cli::set_override::enable(&cli.config, kind, &name, true).await
+        }
+        Command::Disable { kind, name } => {
+            cli::set_override::enable(&cli.config, kind, &name, false).await
+        }
+        #[cfg(feature = "discover")]
+        Command::Discover { wait_sec } => cli::discover::run(wait_sec).await,
+        Command::TestSink { name, action } => cli::test::run_sink(&cli.config, &name, action).await,
+        Command::TestSource { name } => cli::test::run_source(&cli.config, &name).await,
+    }
+}
+
+/// Run the daemon until `shutdown` completes, then run the shutdown actions of the sinks.
+async fn daemon(config_path: &Path, log: LogHandle, shutdown: impl Future<Output = ()>) {
     info!("Started.");
-    let config = match settings::read() {
+    let config = match settings::read(config_path) {
         Ok(v) => v,
         Err(e) => {
             error!("Failed reading config: {e}");
             panic!("Failed reading config: {e}");
         }
     };
+    if let Err(e) = log.apply_config(config.general.log.as_deref()) {
+        error!("Failed applying log settings: {e}");
+        panic!("Failed applying log settings: {e}");
+    }
+
+    let notifiers = Notifiers::create(&config.notifier).expect("Failed to init notifiers.");
+    let state = Arc::new(init(&config).await);
+    let events = state.subscribe();
+    notifiers.dispatch(&Event::Started).await;
 
+    // Kept until the end, since dropping it aborts sink commands in progress.
+    let app = run(config_path, &config, state.clone());
+    tokio::pin!(app);
     tokio::select! {
-        _ = ctrlc => {},
-        _ = run(config) => {}
+        _ = shutdown => {},
+        _ = reload_log_on_hangup(config_path, &log) => {},
+        _ = notifiers.run(events) => {},
+        _ = &mut app => {}
     }
 
+    info!("Shutting down...");
+    state
+        .drain(Duration::from_secs(config.general.shutdown_grace_sec))
+        .await;
+    notifiers.dispatch(&Event::Stopping).await;
+    state.shutdown().await;
     info!("Quitting.");
 }
@@ -0,0 +1,118 @@
+#![cfg(feature = "modbus")]
+
+#[cfg(feature = "source-pdu-outlet")]
+use serde::Deserialize;
+use std::error::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+pub const DEFAULT_PORT: u16 = 502;
+
+const READ_COILS: u8 = 0x01;
+#[cfg(feature = "source-pdu-outlet")]
+const READ_HOLDING_REGISTERS: u8 = 0x03;
+#[cfg(feature = "source-pdu-outlet")]
+const READ_INPUT_REGISTERS: u8 = 0x04;
+#[cfg(feature = "sink-modbus")]
+const WRITE_SINGLE_COIL: u8 = 0x05;
+
+/// The table a register is read from.
+#[cfg(feature = "source-pdu-outlet")]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum RegisterKind {
+    #[default]
+    Input,
+    Holding,
+}
+
+/// A Modbus TCP connection to a single unit.
+pub struct Connection {
+    stream: TcpStream,
+    unit_id: u8,
+    transaction_id: u16,
+}
+
+impl Connection {
+    pub async fn connect(host: &str, port: u16, unit_id: u8) -> Result<Self> {
+        Ok(Self {
+            stream: TcpStream::connect((host, port)).await?,
+            unit_id,
+            transaction_id: 0,
+        })
+    }
+
+    pub async fn read_coil(&mut self, address: u16) -> Result<bool> {
+        let mut request = vec![READ_COILS];
+        request.extend_from_slice(&address.to_be_bytes());
+        request.extend_from_slice(&1u16.to_be_bytes());
+        let response = self.call(&request).await?;
+        // Byte count, then the coils as bits.
+        match response[..] {
+            [1, coils] => Ok(coils & 1 != 0),
+            _ => Err("invalid read coils response".into()),
+        }
+    }
+
+    #[cfg(feature = "source-pdu-outlet")]
+    pub async fn read_register(&mut self, kind: RegisterKind, address: u16) -> Result<u16> {
+        let mut request = vec![match kind {
+            RegisterKind::Input => READ_INPUT_REGISTERS,
+            RegisterKind::Holding => READ_HOLDING_REGISTERS,
+        }];
+        request.extend_from_slice(&address.to_be_bytes());
+        request.extend_from_slice(&1u16.to_be_bytes());
+        let response = self.call(&request).await?;
+        // Byte count, then the registers.
+        match response[..] {
+            [2, high, low] => Ok(u16::from_be_bytes([high, low])),
+            _ => Err("invalid read registers response".into()),
+        }
+    }
+
+    #[cfg(feature = "sink-modbus")]
+    pub async fn write_coil(&mut self, address: u16, value: bool) -> Result<()> {
+        let mut request = vec![WRITE_SINGLE_COIL];
+        request.extend_from_slice(&address.to_be_bytes());
+        request.extend_from_slice(if value { &[0xFF, 0x00] } else { &[0x00, 0x00] });
+        // The response echoes the request.
+        if self.call(&request).await? != request[1..] {
+            return Err("invalid write coil response".into());
+        }
+        Ok(())
+    }
+
+    /// Send a request PDU and return the data of the response PDU, without the function code.
+    async fn call(&mut self, pdu: &[u8]) -> Result<Vec<u8>> {
+        self.transaction_id = self.transaction_id.wrapping_add(1);
+        let mut frame = Vec::with_capacity(7 + pdu.len());
+        frame.extend_from_slice(&self.transaction_id.to_be_bytes());
+        frame.extend_from_slice(&0u16.to_be_bytes());
+        frame.extend_from_slice(&(pdu.len() as u16 + 1).to_be_bytes());
+        frame.push(self.unit_id);
+        frame.extend_from_slice(pdu);
+        self.stream.write_all(&frame).await?;
+
+        let mut header = [0; 7];
+        self.stream.read_exact(&mut header).await?;
+        let transaction_id = u16::from_be_bytes([header[0], header[1]]);
+        let len = u16::from_be_bytes([header[4], header[5]]) as usize;
+        if transaction_id != self.transaction_id || len < 2 {
+            return Err("invalid Modbus response".into());
+        }
+        let mut response = vec![0; len - 1];
+        self.stream.read_exact(&mut response).await?;
+        let function = response.remove(0);
+        if function == pdu[0] | 0x80 {
+            let code = response.first().copied().unwrap_or_default();
+            return Err(format!("Modbus exception {code:#04x}").into());
+        }
+        if function != pdu[0] {
+            return Err(format!("unexpected Modbus function {function:#04x} in response").into());
+        }
+        Ok(response)
+    }
+}
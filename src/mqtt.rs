@@ -0,0 +1,154 @@
+#![cfg(feature = "mqtt")]
+
+use crate::settings::PassSettings;
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Connection settings of an MQTT broker.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct BrokerSettings {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+}
+
+fn default_port() -> u16 {
+    1883
+}
+
+/// A message received on a subscribed topic.
+#[derive(Clone, Debug)]
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// A connection to an MQTT broker, shared by all sources and sinks using the same broker and
+/// user. It reconnects by itself and restores the subscriptions.
+pub struct MqttClient {
+    client: AsyncClient,
+    subscriptions: Mutex<HashSet<String>>,
+    messages: broadcast::Sender<Message>,
+}
+
+impl MqttClient {
+    /// Connect to the broker, or return the existing connection to it. Must be called within
+    /// the runtime.
+    pub fn connect(settings: &BrokerSettings) -> Result<Arc<Self>, Box<dyn Error + Send + Sync>> {
+        static CLIENTS: OnceLock<Mutex<HashMap<String, Weak<MqttClient>>>> = OnceLock::new();
+        let key = format!(
+            "{}@{}:{}",
+            settings.user.as_deref().unwrap_or_default(),
+            settings.host,
+            settings.port
+        );
+        let mut clients = CLIENTS.get_or_init(Default::default).lock().unwrap();
+        if let Some(client) = clients.get(&key).and_then(Weak::upgrade) {
+            return Ok(client);
+        }
+        let client = Self::connect_new(settings)?;
+        clients.insert(key, Arc::downgrade(&client));
+        Ok(client)
+    }
+
+    fn connect_new(settings: &BrokerSettings) -> Result<Arc<Self>, Box<dyn Error + Send + Sync>> {
+        let client_id = format!("personal-power-ctrl-{:08x}", fastrand::u32(..));
+        let mut options = MqttOptions::new(client_id, &settings.host, settings.port);
+        options.set_keep_alive(KEEP_ALIVE);
+        match (&settings.user, settings.pass.resolve()?) {
+            (Some(user), pass) => {
+                options.set_credentials(user, pass.unwrap_or_default());
+            }
+            (None, Some(_)) => return Err("a user is required with a password".into()),
+            (None, None) => {}
+        }
+        let (client, mut event_loop) = AsyncClient::new(options, 64);
+        let this = Arc::new(Self {
+            client,
+            subscriptions: Mutex::new(HashSet::new()),
+            messages: broadcast::channel(64).0,
+        });
+        let host = settings.host.clone();
+        let weak = Arc::downgrade(&this);
+        tokio::spawn(async move {
+            loop {
+                let event = event_loop.poll().await;
+                // Stop once nothing uses the connection anymore.
+                let Some(this) = weak.upgrade() else {
+                    return;
+                };
+                match event {
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        info!("Connected to MQTT broker {host}.");
+                        this.resubscribe().await;
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        this.messages
+                            .send(Message {
+                                topic: publish.topic,
+                                payload: publish.payload.to_vec(),
+                            })
+                            .ok();
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        warn!("MQTT connection to {host} failed: {e}. Reconnecting...");
+                        drop(this);
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        });
+        Ok(this)
+    }
+
+    async fn resubscribe(&self) {
+        let topics = self.subscriptions.lock().unwrap().clone();
+        for topic in topics {
+            if let Err(e) = self.client.subscribe(&topic, QoS::AtLeastOnce).await {
+                warn!("Failed resubscribing to {topic}: {e}");
+            }
+        }
+    }
+
+    /// Subscribe to the topic, and receive all messages from then on. The receiver gets the
+    /// messages of all topics of the connection.
+    pub async fn subscribe(
+        &self,
+        topic: &str,
+    ) -> Result<broadcast::Receiver<Message>, Box<dyn Error + Send + Sync>> {
+        let receiver = self.messages.subscribe();
+        if self.subscriptions.lock().unwrap().insert(topic.to_string()) {
+            debug!("Subscribing to {topic}");
+            self.client.subscribe(topic, QoS::AtLeastOnce).await?;
+        }
+        Ok(receiver)
+    }
+
+    #[cfg(feature = "sink-zigbee2mqtt")]
+    pub async fn publish(
+        &self,
+        topic: &str,
+        payload: impl Into<Vec<u8>>,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
+        debug!("Publishing to {topic}");
+        self.client
+            .publish(topic, QoS::AtLeastOnce, false, payload)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use std::io;
+
+/// Flag of `/proc/net/arp` entries that are complete (the hardware address is known).
+#[cfg(target_os = "linux")]
+const ATF_COM: u32 = 0x2;
+
+/// Checks whether an IP or MAC address currently has a complete entry in the neighbor table.
+/// MAC addresses are compared case-insensitively.
+#[cfg(target_os = "linux")]
+pub fn contains(addr: &str) -> io::Result<bool> {
+    let table = std::fs::read_to_string("/proc/net/arp")?;
+    // Format: IP address, HW type, Flags, HW address, Mask, Device. First line is a header.
+    Ok(table.lines().skip(1).any(|line| {
+        let cols: Vec<&str> = line.split_whitespace().collect();
+        if cols.len() < 4 {
+            return false;
+        }
+        let complete = u32::from_str_radix(cols[2].trim_start_matches("0x"), 16)
+            .map(|flags| flags & ATF_COM != 0)
+            .unwrap_or(false);
+        complete && (cols[0] == addr || cols[3].eq_ignore_ascii_case(addr))
+    }))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn contains(_addr: &str) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "reading the neighbor table is only supported on Linux",
+    ))
+}
@@ -0,0 +1,162 @@
+use crate::event::Event;
+use crate::identity::Named;
+use crate::log::panic_to_string;
+#[cfg(any(
+    feature = "notifier-ntfy",
+    feature = "notifier-smtp",
+    feature = "notifier-webhook"
+))]
+use crate::settings::NotifierSettings;
+use crate::settings::{MapOfNotifierSettings, NotifierBaseSettings};
+use futures::future::join_all;
+use futures::FutureExt;
+use std::error::Error;
+use std::iter::empty;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::time::timeout;
+use tracing::{error, info, warn};
+
+#[cfg(feature = "notifier-ntfy")]
+pub mod ntfy;
+#[cfg(feature = "notifier-smtp")]
+pub mod smtp;
+#[cfg(feature = "notifier-webhook")]
+pub mod webhook;
+
+pub type NotifyResult = Result<(), Box<dyn Error + Send + Sync>>;
+pub type CreateNotifierResult = Result<Box<dyn Notifier>, crate::error::Error>;
+
+#[async_trait]
+/// Something that tells the user about noteworthy events, such as failures.
+pub trait Notifier: Send + Sync {
+    /// Base settings.
+    fn base_settings(&self) -> &NotifierBaseSettings;
+    /// Whether the notifier should be notified about the event. By default, this is decided by
+    /// the `events` of the base settings.
+    fn wants(&self, event: &Event) -> bool {
+        self.base_settings().wants(event)
+    }
+    /// Send a notification about the event.
+    async fn notify(&self, event: &Event) -> NotifyResult;
+}
+
+/// All configured notifiers.
+pub struct Notifiers(Vec<Box<dyn Notifier>>);
+
+impl Notifiers {
+    pub fn create(notifier_config: &MapOfNotifierSettings) -> Result<Self, Box<dyn Error>> {
+        let mut notifiers = Vec::new();
+        for (base, result) in try_create_all(notifier_config) {
+            match result {
+                Ok(notifier) => {
+                    info!("{} Loaded.", base.identity());
+                    notifiers.push(notifier);
+                }
+                Err(e) => {
+                    error!("{} Failed creating notifier: {}", base.identity(), &e);
+                    return Err(e.of(base.identity().clone_owned()).into());
+                }
+            }
+        }
+        Ok(Self(notifiers))
+    }
+
+    /// Notify all notifiers interested in the event, concurrently.
+    pub async fn dispatch(&self, event: &Event) {
+        join_all(self.0.iter().filter(|notifier| notifier.wants(event)).map(
+            |notifier| async move {
+                let base = notifier.base_settings();
+                match timeout(
+                    Duration::from_secs(base.timeout_sec as u64),
+                    AssertUnwindSafe(notifier.notify(event)).catch_unwind(),
+                )
+                .await
+                {
+                    Ok(Ok(Ok(()))) => {}
+                    Ok(Ok(Err(e))) => {
+                        warn!("{} Failed sending notification: {}", base.identity(), e)
+                    }
+                    Ok(Err(panic)) => error!(
+                        "{} Panic while sending notification: {}",
+                        base.identity(),
+                        panic_to_string(panic)
+                    ),
+                    Err(_) => warn!("{} Timeout while sending notification.", base.identity()),
+                }
+            },
+        ))
+        .await;
+    }
+
+    /// Dispatch all events received. Never completes.
+    pub async fn run(&self, mut events: Receiver<Event>) -> ! {
+        loop {
+            match events.recv().await {
+                Ok(event) => self.dispatch(&event).await,
+                Err(RecvError::Lagged(n)) => warn!("Notifiers missed {} events.", n),
+                Err(RecvError::Closed) => std::future::pending().await,
+            }
+        }
+    }
+}
+
+/// Try to create all enabled notifiers, returning the base settings of each notifier alongside
+/// the result of creating it.
+#[cfg_attr(
+    not(any(
+        feature = "notifier-ntfy",
+        feature = "notifier-smtp",
+        feature = "notifier-webhook"
+    )),
+    allow(unused_variables)
+)]
+pub fn try_create_all(
+    notifier_config: &MapOfNotifierSettings,
+) -> impl Iterator<Item = (&NotifierBaseSettings, CreateNotifierResult)> {
+    let all = empty();
+    #[cfg(feature = "notifier-ntfy")]
+    let all = all.chain(create_of_type(&notifier_config.ntfy));
+    #[cfg(feature = "notifier-smtp")]
+    let all = all.chain(create_of_type(&notifier_config.smtp));
+    #[cfg(feature = "notifier-webhook")]
+    let all = all.chain(create_of_type(&notifier_config.webhook));
+
+    all
+}
+
+#[cfg(any(
+    feature = "notifier-ntfy",
+    feature = "notifier-smtp",
+    feature = "notifier-webhook"
+))]
+fn create_of_type<'a, N>(
+    notifier_configs: &'a [N],
+) -> impl Iterator<Item = (&'a NotifierBaseSettings, CreateNotifierResult)> + 'a
+where
+    N: NotifierSettings + 'a,
+    N::Impl: 'static,
+{
+    notifier_configs
+        .iter()
+        .filter(|cfg| cfg.base().enable)
+        .map(|cfg| {
+            (
+                cfg.base(),
+                cfg.create_notifier()
+                    .map(|x| Box::new(x) as Box<dyn Notifier>),
+            )
+        })
+}
+
+impl NotifierBaseSettings {
+    /// Whether the notifier should be notified about the event.
+    pub fn wants(&self, event: &Event) -> bool {
+        match &self.events {
+            Some(events) => events.contains(&event.kind()),
+            None => event.kind().notify_by_default(),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "notifier-ntfy")]
+
+use crate::event::Event;
+use crate::http_client;
+use crate::notifier::{Notifier, NotifyResult};
+use crate::settings::{NotifierBaseSettings, NotifierSettings, PassSettings};
+use serde::Deserialize;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "NtfyNotifierSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// URL of the ntfy server.
+    #[serde(default = "default_server")]
+    pub server: String,
+    pub topic: String,
+    /// Message priority, from 1 (min) to 5 (max).
+    pub priority: Option<u8>,
+    /// User for access controlled topics. To use an access token instead, leave this unset and
+    /// set the token as password.
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    #[serde(flatten)]
+    base: NotifierBaseSettings,
+}
+
+fn default_server() -> String {
+    "https://ntfy.sh".to_string()
+}
+
+impl NotifierSettings for Settings {
+    type Impl = NtfyNotifier;
+
+    fn base(&self) -> &NotifierBaseSettings {
+        &self.base
+    }
+
+    fn create_notifier(&self) -> Result<Self::Impl, crate::error::Error> {
+        NtfyNotifier::new(self.clone())
+    }
+}
+
+pub struct NtfyNotifier {
+    settings: Settings,
+    url: reqwest::Url,
+    pass: Option<String>,
+    client: reqwest::Client,
+}
+
+impl NtfyNotifier {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let url = reqwest::Url::parse(&settings.server)
+            .and_then(|server| server.join(&settings.topic))
+            .map_err(crate::error::Error::other)?;
+        let pass = settings.pass.resolve()?;
+        let client = http_client::builder(&url)?.build()?;
+        Ok(Self {
+            settings,
+            url,
+            pass,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for NtfyNotifier {
+    fn base_settings(&self) -> &NotifierBaseSettings {
+        self.settings.base()
+    }
+
+    async fn notify(&self, event: &Event) -> NotifyResult {
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header("Title", "personal-power-ctrl")
+            .body(event.to_string());
+        if let Some(priority) = self.settings.priority {
+            request = request.header("Priority", priority.to_string());
+        }
+        if self.settings.user.is_some() || self.pass.is_some() {
+            request = request.basic_auth(
+                self.settings.user.as_deref().unwrap_or_default(),
+                self.pass.as_deref(),
+            );
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
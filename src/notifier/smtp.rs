@@ -0,0 +1,232 @@
+#![cfg(feature = "notifier-smtp")]
+
+use crate::event::Event;
+use crate::notifier::{Notifier, NotifyResult};
+use crate::settings::{NotifierBaseSettings, NotifierSettings, PassSettings};
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "SmtpNotifierSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    pub host: String,
+    /// Defaults to the standard port of the encryption mode.
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub encryption: Encryption,
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    pub from: String,
+    pub to: Vec<String>,
+    /// Number of failed commands in a row after which a sink is included in the digest.
+    #[serde(default = "default_sink_failures")]
+    pub sink_failures: u32,
+    /// Time in seconds a source has to keep failing to be included in the digest.
+    #[serde(default = "default_source_failing_sec")]
+    pub source_failing_sec: u64,
+    /// `events` of the base settings are not used, this notifier only sends digests of
+    /// persistent failures and power reports.
+    #[serde(flatten)]
+    base: NotifierBaseSettings,
+}
+
+fn default_sink_failures() -> u32 {
+    3
+}
+
+fn default_source_failing_sec() -> u64 {
+    600
+}
+
+/// How the connection to the SMTP server is encrypted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Encryption {
+    /// TLS from the start.
+    #[default]
+    Tls,
+    /// Upgrade to TLS with `STARTTLS`.
+    StartTls,
+    /// Unencrypted. Only use this for servers on the local machine.
+    None,
+}
+
+impl NotifierSettings for Settings {
+    type Impl = SmtpNotifier;
+
+    fn base(&self) -> &NotifierBaseSettings {
+        &self.base
+    }
+
+    fn create_notifier(&self) -> Result<Self::Impl, crate::error::Error> {
+        SmtpNotifier::new(self.clone())
+    }
+}
+
+/// Persistent failures seen so far.
+#[derive(Default)]
+struct Tracking {
+    /// Consecutive failures and last error by sink.
+    sinks: BTreeMap<String, (u32, String)>,
+    /// Start of the failures and last error by source.
+    sources: BTreeMap<String, (Instant, String)>,
+    /// Sinks and sources that were already included in a digest, and haven't recovered since.
+    reported: HashSet<String>,
+}
+
+pub struct SmtpNotifier {
+    settings: Settings,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    tracking: Mutex<Tracking>,
+}
+
+impl SmtpNotifier {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let from = settings.from.parse().map_err(crate::error::Error::other)?;
+        let to = settings
+            .to
+            .iter()
+            .map(|to| to.parse())
+            .collect::<Result<_, _>>()
+            .map_err(crate::error::Error::other)?;
+        let mut transport = match settings.encryption {
+            Encryption::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)
+                .map_err(crate::error::Error::other)?,
+            Encryption::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)
+                    .map_err(crate::error::Error::other)?
+            }
+            Encryption::None => {
+                AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host)
+            }
+        };
+        if let Some(port) = settings.port {
+            transport = transport.port(port);
+        }
+        if let Some(user) = &settings.user {
+            let pass = settings.pass.resolve()?.unwrap_or_default();
+            transport = transport.credentials(Credentials::new(user.clone(), pass));
+        }
+        Ok(Self {
+            transport: transport.build(),
+            settings,
+            from,
+            to,
+            tracking: Mutex::new(Tracking::default()),
+        })
+    }
+
+    /// Update the tracked failures with the event. Returns the digest to send, if a failure
+    /// newly became persistent.
+    fn track(&self, event: &Event) -> Option<String> {
+        let mut tracking = self.tracking.lock().unwrap();
+        let tracking = &mut *tracking;
+        match event {
+            Event::SinkCommandFailed { sink, error } => {
+                let (failures, last_error) = tracking
+                    .sinks
+                    .entry(sink.clone())
+                    .or_insert((0, String::new()));
+                *failures += 1;
+                *last_error = error.clone();
+                if *failures < self.settings.sink_failures
+                    || !tracking.reported.insert(format!("sink {sink}"))
+                {
+                    return None;
+                }
+            }
+            Event::SinkChanged { sink, .. } => {
+                tracking.sinks.remove(sink);
+                tracking.reported.remove(&format!("sink {sink}"));
+                return None;
+            }
+            Event::SourceFailed { source, error } => {
+                let (since, last_error) = tracking
+                    .sources
+                    .entry(source.clone())
+                    .or_insert((Instant::now(), String::new()));
+                *last_error = error.clone();
+                if since.elapsed() < Duration::from_secs(self.settings.source_failing_sec)
+                    || !tracking.reported.insert(format!("source {source}"))
+                {
+                    return None;
+                }
+            }
+            Event::SourceRecovered { source } => {
+                tracking.sources.remove(source);
+                tracking.reported.remove(&format!("source {source}"));
+                return None;
+            }
+            _ => return None,
+        }
+
+        let mut digest = String::from("The following problems persist:\n\n");
+        for (sink, (failures, error)) in &tracking.sinks {
+            if *failures >= self.settings.sink_failures {
+                digest.push_str(&format!(
+                    "- Sink {sink} failed {failures} times in a row: {error}\n"
+                ));
+            }
+        }
+        let min_duration = Duration::from_secs(self.settings.source_failing_sec);
+        for (source, (since, error)) in &tracking.sources {
+            if since.elapsed() >= min_duration {
+                digest.push_str(&format!(
+                    "- Source {source} has been failing for {} min: {error}\n",
+                    since.elapsed().as_secs() / 60
+                ));
+            }
+        }
+        Some(digest)
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    fn base_settings(&self) -> &NotifierBaseSettings {
+        self.settings.base()
+    }
+
+    fn wants(&self, event: &Event) -> bool {
+        matches!(
+            event,
+            Event::SinkCommandFailed { .. }
+                | Event::SinkChanged { .. }
+                | Event::SourceFailed { .. }
+                | Event::SourceRecovered { .. }
+                | Event::PowerReport { .. }
+        )
+    }
+
+    async fn notify(&self, event: &Event) -> NotifyResult {
+        let (subject, body) = match event {
+            Event::PowerReport { report } => ("power report", report.to_string()),
+            _ => match self.track(event) {
+                Some(digest) => ("persistent failures", digest),
+                None => return Ok(()),
+            },
+        };
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("personal-power-ctrl: {subject}"));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        self.transport.send(message.body(body)?).await?;
+        Ok(())
+    }
+}
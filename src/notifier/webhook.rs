@@ -0,0 +1,88 @@
+#![cfg(feature = "notifier-webhook")]
+
+use crate::event::Event;
+use crate::http_client;
+use crate::notifier::{Notifier, NotifyResult};
+use crate::settings::{NotifierBaseSettings, NotifierSettings};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "WebhookNotifierSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// URL the event is posted to as JSON.
+    pub url: String,
+    /// Additional HTTP headers to send, e.g. for authorization.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(flatten)]
+    base: NotifierBaseSettings,
+}
+
+impl NotifierSettings for Settings {
+    type Impl = WebhookNotifier;
+
+    fn base(&self) -> &NotifierBaseSettings {
+        &self.base
+    }
+
+    fn create_notifier(&self) -> Result<Self::Impl, crate::error::Error> {
+        WebhookNotifier::new(self.clone())
+    }
+}
+
+/// The JSON body posted to the webhook: the event with its fields, and a human-readable
+/// message.
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a Event,
+    message: String,
+}
+
+pub struct WebhookNotifier {
+    settings: Settings,
+    url: reqwest::Url,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let url = reqwest::Url::parse(&settings.url).map_err(crate::error::Error::other)?;
+        let client = http_client::builder(&url)?.build()?;
+        Ok(Self {
+            settings,
+            url,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn base_settings(&self) -> &NotifierBaseSettings {
+        self.settings.base()
+    }
+
+    async fn notify(&self, event: &Event) -> NotifyResult {
+        let body = serde_json::to_vec(&Payload {
+            event,
+            message: event.to_string(),
+        })?;
+        let mut request = self
+            .client
+            .post(self.url.clone())
+            .header("Content-Type", "application/json")
+            .body(body);
+        for (name, value) in &self.settings.headers {
+            request = request.header(name, value);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Registry of the sink and source types of the enabled features, keyed by the name of their
+//! config table, e.g. `tapo` for `[[sink.tapo]]`.
+
+use config::{ConfigError, Map, Value};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use std::fmt::{Debug, Formatter};
+
+/// A type of sink or source, as registered by the module implementing it.
+pub struct Registration<T: ?Sized> {
+    /// Name of its config table.
+    pub name: &'static str,
+    /// The cargo feature it is compiled in with.
+    pub feature: &'static str,
+    /// Parse the array of its config table.
+    pub parse: fn(Value) -> Result<Vec<Box<T>>, ConfigError>,
+    /// JSON Schema of the array of its config table.
+    #[cfg(feature = "schema")]
+    pub schema: fn(&mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema,
+    /// Names of its own settings, without the base settings shared by all types.
+    pub fields: &'static [&'static str],
+}
+
+/// Register the settings type under the name of its config table, for the feature, with the
+/// names of its own settings.
+#[allow(unused_macros)]
+macro_rules! registration {
+    ($name:literal, $feature:literal, $settings:ty, [$($field:literal),* $(,)?]) => {
+        $crate::registry::Registration {
+            name: $name,
+            feature: $feature,
+            parse: |value| {
+                Ok(value
+                    .try_deserialize::<Vec<$settings>>()?
+                    .into_iter()
+                    .map(|settings| Box::new(settings) as _)
+                    .collect())
+            },
+            #[cfg(feature = "schema")]
+            schema: |gen| gen.subschema_for::<Box<[$settings]>>(),
+            fields: &[$($field),*],
+        }
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use registration;
+
+/// Settings of any type of a kind of component, with the types registered for it.
+pub trait Registered: 'static {
+    /// Name of the config section holding the tables of the types, e.g. `sink`.
+    const SECTION: &'static str;
+    /// Names of the base settings shared by all types.
+    const BASE_FIELDS: &'static [&'static str];
+    /// The types of the enabled features.
+    fn types() -> &'static [Registration<Self>];
+}
+
+/// The settings of all configured components of a kind, such as all sinks. They are in the
+/// order the types are registered in, and in config order within a type.
+pub struct MapOf<T: ?Sized>(Vec<Box<T>>);
+
+impl<T: ?Sized> MapOf<T> {
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.0.iter().map(|settings| &**settings)
+    }
+}
+
+impl<T: ?Sized> Default for MapOf<T> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<T: ?Sized> Clone for MapOf<T>
+where
+    Box<T>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: ?Sized + Debug> Debug for MapOf<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<'de, T: ?Sized + Registered> Deserialize<'de> for MapOf<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // The tables are kept as config values, so that they are deserialized as leniently as
+        // the rest of the config, e.g. numbers from environment variables.
+        let mut tables = Map::<String, Value>::deserialize(deserializer)?;
+        let mut all = Vec::new();
+        for registration in T::types() {
+            let Some(table) = tables.remove(registration.name) else {
+                continue;
+            };
+            let parsed = (registration.parse)(table).map_err(|e| {
+                D::Error::custom(format!("{}.{}: {e}", T::SECTION, registration.name))
+            })?;
+            all.extend(parsed);
+        }
+        if let Some(name) = tables.keys().next() {
+            let known: Vec<_> = T::types().iter().map(|r| r.name).collect();
+            return Err(D::Error::custom(format!(
+                "unknown {} type `{name}`, this build supports: {}",
+                T::SECTION,
+                known.join(", ")
+            )));
+        }
+        Ok(Self(all))
+    }
+}
+
+#[cfg(feature = "schema")]
+impl<T: ?Sized + Registered> schemars::JsonSchema for MapOf<T> {
+    fn schema_name() -> String {
+        let mut section = T::SECTION.chars();
+        let first = section.next().unwrap_or_default().to_ascii_uppercase();
+        format!("MapOf{first}{}Settings", section.as_str())
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        use schemars::schema::{InstanceType, ObjectValidation, SchemaObject};
+
+        let mut object = ObjectValidation {
+            additional_properties: Some(Box::new(false.into())),
+            ..Default::default()
+        };
+        for registration in T::types() {
+            let schema = (registration.schema)(gen);
+            object
+                .properties
+                .insert(registration.name.to_string(), schema);
+        }
+        SchemaObject {
+            instance_type: Some(InstanceType::Object.into()),
+            object: Some(Box::new(object)),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,100 @@
+#[cfg(feature = "schema")]
+use crate::registry::Registered;
+use crate::settings::MapOfSinkSettings;
+#[cfg(feature = "schema")]
+use crate::sink::AnySinkSettings;
+#[cfg(feature = "schema")]
+use crate::source::AnySourceSettings;
+use config::{Config, File, FileFormat};
+
+fn parse(toml: &str) -> Result<MapOfSinkSettings, config::ConfigError> {
+    Config::builder()
+        .add_source(File::from_str(toml, FileFormat::Toml))
+        .build()?
+        .try_deserialize()
+}
+
+#[cfg(feature = "sink-composite")]
+#[test]
+fn parses_tables_of_registered_types() {
+    let sinks = parse(
+        r#"[[composite]]
+        name = "Living room"
+        enable = true
+        timeout-sec = 5
+        sinks = ["TV", "Soundbar"]
+
+        [[composite]]
+        name = "Office"
+        enable = false
+        timeout-sec = 5
+        sinks = ["Monitor"]"#,
+    )
+    .unwrap();
+    let names: Vec<_> = sinks.iter().map(|s| s.base().name.as_str()).collect();
+    assert_eq!(names, ["Living room", "Office"]);
+    assert!(sinks.iter().all(|s| s.is_group()));
+}
+
+#[test]
+fn rejects_unknown_types() {
+    let error = parse(
+        r#"[[no-such-sink]]
+        name = "Lamp""#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("unknown sink type `no-such-sink`"));
+}
+
+/// Names of the settings in the schema of the array of a type's config table.
+#[cfg(feature = "schema")]
+fn schema_fields(schema: schemars::schema::Schema) -> Vec<String> {
+    use schemars::schema::SingleOrVec;
+
+    let items = schema.into_object().array.unwrap().items.unwrap();
+    let SingleOrVec::Single(item) = items else {
+        panic!("expected a single item schema");
+    };
+    let mut fields: Vec<_> = item
+        .into_object()
+        .object
+        .unwrap()
+        .properties
+        .into_keys()
+        .collect();
+    fields.sort();
+    fields
+}
+
+/// The field names, which `capabilities` lists in builds without schemars, match the settings.
+#[cfg(feature = "schema")]
+fn assert_fields_match_schema<T: ?Sized + Registered>() {
+    let mut gen = schemars::gen::SchemaSettings::default()
+        .with(|settings| settings.inline_subschemas = true)
+        .into_generator();
+    for registration in T::types() {
+        let mut expected: Vec<_> = T::BASE_FIELDS
+            .iter()
+            .chain(registration.fields)
+            .map(|field| field.to_string())
+            .collect();
+        expected.sort();
+        let fields = schema_fields((registration.schema)(&mut gen));
+        assert_eq!(
+            fields,
+            expected,
+            "fields of {}.{}",
+            T::SECTION,
+            registration.name
+        );
+    }
+}
+
+#[cfg(feature = "schema")]
+#[test]
+fn fields_match_schema() {
+    assert_fields_match_schema::<dyn AnySinkSettings>();
+    assert_fields_match_schema::<dyn AnySourceSettings>();
+}
@@ -0,0 +1,118 @@
+#![cfg(all(feature = "windows-service", windows))]
+
+use crate::log::LogHandle;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::runtime::Handle;
+use tokio::sync::oneshot;
+use tracing::{error, info};
+use windows_service::service::{
+    ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::{define_windows_service, service_dispatcher};
+
+const SERVICE_NAME: &str = "personal-power-ctrl";
+/// Time the service control manager is told stopping may take, to turn off the sinks.
+const STOP_WAIT_HINT: Duration = Duration::from_secs(60);
+
+/// What the daemon is started with. The service control manager calls the service entry point
+/// without any context.
+struct Context {
+    runtime: Handle,
+    config_path: PathBuf,
+    log: LogHandle,
+}
+
+static CONTEXT: Mutex<Option<Context>> = Mutex::new(None);
+
+define_windows_service!(ffi_service_main, service_main);
+
+/// Run the daemon as a Windows service, until the service control manager stops it.
+pub async fn run(config_path: &Path, log: LogHandle) -> ExitCode {
+    *CONTEXT.lock().unwrap() = Some(Context {
+        runtime: Handle::current(),
+        config_path: config_path.to_path_buf(),
+        log,
+    });
+    // Blocks until the service stopped.
+    let result =
+        tokio::task::spawn_blocking(|| service_dispatcher::start(SERVICE_NAME, ffi_service_main))
+            .await;
+    match result {
+        Ok(Ok(())) => ExitCode::SUCCESS,
+        Ok(Err(e)) => {
+            error!(
+                "Failed running as a service (not started by the service control manager?): {e}"
+            );
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            error!("Service dispatcher failed: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let Some(context) = CONTEXT.lock().unwrap().take() else {
+        return;
+    };
+    if let Err(e) = run_service(context) {
+        error!("Failed reporting the service status: {e}");
+    }
+}
+
+fn run_service(context: Context) -> windows_service::Result<()> {
+    let (stop_sender, stop_receiver) = oneshot::channel();
+    let mut stop_sender = Some(stop_sender);
+    let status_handle =
+        service_control_handler::register(SERVICE_NAME, move |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                if let Some(sender) = stop_sender.take() {
+                    sender.send(()).ok();
+                }
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        })?;
+    let status = |current_state, controls_accepted, wait_hint| ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state,
+        controls_accepted,
+        exit_code: ServiceExitCode::Win32(0),
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    };
+
+    status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+        Duration::ZERO,
+    ))?;
+    let stopped = async {
+        stop_receiver.await.ok();
+        info!("Stop requested by the service control manager.");
+        let stopping = status(
+            ServiceState::StopPending,
+            ServiceControlAccept::empty(),
+            STOP_WAIT_HINT,
+        );
+        if let Err(e) = status_handle.set_service_status(stopping) {
+            error!("Failed reporting the service status: {e}");
+        }
+    };
+    context
+        .runtime
+        .block_on(crate::daemon(&context.config_path, context.log, stopped));
+    status_handle.set_service_status(status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        Duration::ZERO,
+    ))
+}
@@ -1,29 +1,467 @@
-use crate::sink::Sink;
-use crate::source::Source;
-use config::{Config, File};
+use crate::event::EventKind;
+#[cfg(any(
+    feature = "notifier-ntfy",
+    feature = "notifier-smtp",
+    feature = "notifier-webhook"
+))]
+use crate::notifier::Notifier;
+use crate::registry::MapOf;
+use crate::sink::{AnySinkSettings, Sink};
+use crate::source::{AnySourceSettings, Source};
+use config::{Config, ConfigError, File, Map, Value, ValueKind};
 use serde::Deserialize;
 use std::env;
 use std::error::Error;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// General settings for the app.
 #[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub struct GeneralSettings {
     /// When on, the interval in seconds that should be checked whether all
     /// sources are off again or not.
     pub power_off_check_interval_sec: u64,
+    /// Seconds after start during which sinks are never turned off, so that slow sources have
+    /// a chance to report being on first.
+    #[serde(default)]
+    pub startup_grace_sec: u64,
+    /// Log targets and levels, in the same format as `RUST_LOG`. Merged with the targets from
+    /// `RUST_LOG`, taking precedence over them. Re-read on `SIGHUP`.
+    pub log: Option<String>,
+    /// Path of the unix socket the daemon listens on for control clients, such as the `status`
+    /// command. On Windows, the name of a named pipe instead.
+    #[serde(default = "default_control_socket")]
+    pub control_socket: PathBuf,
+    /// Permissions of the control socket, e.g. `0o660` to let the group of the daemon control
+    /// it. Connecting requires write permission. Ignored on Windows.
+    #[serde(default = "default_control_socket_mode")]
+    pub control_socket_mode: u32,
+    /// The D-Bus bus to provide the D-Bus service on, if any.
+    pub dbus: Option<DbusBus>,
+    /// Number of consecutive failed polls after which a source is reported as unknown to
+    /// notifiers. Sources with sleepy settings are never reported.
+    #[serde(default = "default_source_unknown_after_failures")]
+    pub source_unknown_after_failures: u32,
+    /// Address to serve the HTTP API on, such as `/healthz`, if any.
+    pub http_listen: Option<SocketAddr>,
+    #[serde(default)]
+    pub health: HealthSettings,
+    #[serde(default)]
+    pub http: HttpClientSettings,
+    /// File the on-time statistics of sources and sinks are persisted in. If not set, they are
+    /// only kept while running.
+    pub statistics_file: Option<PathBuf>,
+    /// Minimum time in seconds between turning on two sinks, so that sinks turning on at the
+    /// same time are staggered, e.g. to limit inrush current. Can be set per sink as well.
+    pub power_on_delay_sec: Option<u64>,
+    /// Seconds to wait on shutdown for sink commands in progress to complete, before the
+    /// shutdown actions of the sinks are run.
+    #[serde(default = "default_shutdown_grace_sec")]
+    pub shutdown_grace_sec: u64,
+    /// Turn off sinks of low priority while load is shed, e.g. while a UPS runs on battery.
+    pub load_shedding: Option<LoadSheddingSettings>,
+    /// Regularly report the on-time, toggles, failures and energy use of the sinks.
+    pub report: Option<ReportSettings>,
+    /// A Telegram bot to check the status and override sinks from a chat.
+    pub telegram: Option<TelegramSettings>,
+}
+
+/// The bot only talks to the chats in `chat-ids`, and tells them about events.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct TelegramSettings {
+    /// The token of the bot from @BotFather, as `pass`, `pass-file` or `pass-env`.
+    #[serde(flatten)]
+    pub token: PassSettings,
+    /// Chats the bot answers and reports to. Messages from other chats are ignored.
+    pub chat_ids: Vec<i64>,
+    /// Events to report to the chats. By default, sinks being turned on or off and the events
+    /// notifiers are told about by default.
+    pub events: Option<Vec<EventKind>>,
+    /// URL of the Bot API server.
+    #[serde(default = "default_telegram_api_url")]
+    pub api_url: String,
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+/// Reports are sent to the notifiers as `power-report` events, after the end of each period.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReportSettings {
+    pub period: ReportPeriod,
+    /// File to append the reports to as well.
+    pub file: Option<PathBuf>,
+}
+
+/// Periods are made of UTC days, like the statistics.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ReportPeriod {
+    /// Report each day on the day before.
+    Daily,
+    /// Report each Monday on the week before.
+    Weekly,
+}
+
+impl ReportPeriod {
+    pub fn days(self) -> u64 {
+        match self {
+            ReportPeriod::Daily => 1,
+            ReportPeriod::Weekly => 7,
+        }
+    }
+
+    /// Whether a period ends before the day, in days since the Unix epoch.
+    pub fn ends_before(self, day: u64) -> bool {
+        match self {
+            ReportPeriod::Daily => true,
+            // The epoch was a Thursday, so day 4 was a Monday.
+            ReportPeriod::Weekly => day % 7 == 4,
+        }
+    }
+}
+
+/// Load shedding: while it's active, sinks with a `priority` below `min-priority` are turned
+/// off regardless of their sources, and turned on again once it ends if their sources are on.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct LoadSheddingSettings {
+    /// Minimum priority of the sinks that are kept on while shedding load.
+    pub min_priority: u32,
+    /// Name of a source, such as a `ups` source, that activates load shedding while it's on.
+    /// It doesn't turn on any sinks itself. Without it, load shedding is only activated with
+    /// the `shed` command.
+    pub source: Option<String>,
+}
+
+/// Thresholds after which sources and sinks are reported as unhealthy.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct HealthSettings {
+    /// Number of failed polls in a row after which a source is unhealthy.
+    pub source_failures: u32,
+    /// Number of failed commands in a row after which a sink is unhealthy.
+    pub sink_failures: u32,
+}
+
+impl Default for HealthSettings {
+    fn default() -> Self {
+        Self {
+            source_failures: 5,
+            sink_failures: 3,
+        }
+    }
+}
+
+/// Settings for all HTTP clients, such as the ones of Kodi sources and webhook notifiers.
+#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct HttpClientSettings {
+    /// Proxy URL for all requests. Without it, the usual proxy environment variables apply.
+    pub proxy: Option<String>,
+    /// PEM files of certificate authorities to trust in addition to the system ones.
+    pub root_certificates: Box<[PathBuf]>,
+    /// PEM file of a certificate to authenticate with, requires `client-key`.
+    pub client_certificate: Option<PathBuf>,
+    /// PEM file of the PKCS#8 private key of `client-certificate`.
+    pub client_key: Option<PathBuf>,
+    /// Hosts to accept invalid TLS certificates from, such as self-signed ones.
+    pub accept_invalid_certs: Box<[String]>,
+    pub timeout_sec: Option<u64>,
+    pub connect_timeout_sec: Option<u64>,
+    pub user_agent: Option<String>,
+}
+
+/// A group of sources and sinks that is controlled independently of the others, with its own
+/// timing. Sinks of a zone are only turned on and kept on by sources of the same zone. Sources
+/// and sinks that are not in any zone form a default zone using the general settings.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ZoneSettings {
+    pub name: String,
+    /// Names of the sources in this zone. A source may be in multiple zones.
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Names of the sinks in this zone. A sink may only be in one zone.
+    #[serde(default)]
+    pub sinks: Vec<String>,
+    /// Replaces `power-off-check-interval-sec` of the general settings for this zone.
+    pub power_off_check_interval_sec: Option<u64>,
+    /// Replaces `startup-grace-sec` of the general settings for this zone.
+    pub startup_grace_sec: Option<u64>,
+}
+
+/// Maps a set of sources to a set of sinks. A sink in any route is only turned on and kept on by
+/// the sources of its routes, regardless of its zone. Its zone still provides the timing, unless
+/// replaced by the route.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct RouteSettings {
+    pub name: String,
+    /// Names of the sources of this route.
+    pub sources: Vec<String>,
+    /// Names of the sinks of this route. A sink may be in multiple routes, and is then on while
+    /// any of them is.
+    pub sinks: Vec<String>,
+    /// Whether any or all of the sources of this route need to be active to turn its sinks on.
+    #[serde(default)]
+    pub trigger_mode: TriggerMode,
+    /// Replaces `power-off-check-interval-sec` of the zone for the sinks of this route. For
+    /// sinks in multiple routes, the longest interval applies.
+    pub power_off_check_interval_sec: Option<u64>,
+}
+
+fn default_source_unknown_after_failures() -> u32 {
+    3
+}
+
+fn default_shutdown_grace_sec() -> u64 {
+    30
+}
+
+/// A D-Bus message bus.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum DbusBus {
+    Session,
+    System,
+}
+
+fn default_control_socket_mode() -> u32 {
+    0o600
+}
+
+fn default_control_socket() -> PathBuf {
+    #[cfg(windows)]
+    {
+        PathBuf::from(r"\\.\pipe\personal-power-ctrl")
+    }
+    #[cfg(not(windows))]
+    PathBuf::from("personal-power-ctrl.sock")
 }
 
 /// Interval to poll for source status updates.
 #[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PollInterval {
     pub on: u64,
     pub off: u64,
+    /// Random variation applied to each interval, so that sources with the same interval don't
+    /// all poll at the same time.
+    pub jitter: Option<Jitter>,
+}
+
+impl PollInterval {
+    /// The time to wait before the next poll, with jitter applied.
+    pub fn next(&self, is_on: bool) -> Duration {
+        let interval = if is_on { self.on } else { self.off };
+        let max_jitter = match self.jitter {
+            None => 0.0,
+            Some(Jitter::Sec(sec)) => sec as f64,
+            Some(Jitter::Percent(percent)) => interval as f64 * percent as f64 / 100.0,
+        };
+        // Uniformly distributed in [-max_jitter, max_jitter].
+        let jitter = (fastrand::f64() * 2.0 - 1.0) * max_jitter;
+        Duration::from_secs_f64((interval as f64 + jitter).max(0.0))
+    }
+}
+
+/// Maximum random variation of a poll interval, in either direction.
+#[derive(Clone, Copy, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub enum Jitter {
+    /// Fixed number of seconds.
+    Sec(u64),
+    /// Percentage of the interval.
+    Percent(u64),
+}
+
+/// Settings for devices that should not be polled aggressively while they seem to be asleep, since
+/// polling them might keep them from entering their own low-power states.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct SleepySettings {
+    /// Number of consecutive failed or timed out polls after which the device is considered to be
+    /// asleep.
+    pub after_failures: u32,
+    /// While asleep, the interval in seconds in which the device is still probed.
+    pub probe_interval_sec: u64,
+    /// An IP or MAC address to look for in the local ARP/neighbor table. If set, the device is
+    /// probed again as soon as it shows up there, without waiting for `probe_interval_sec`.
+    pub wake_hint: Option<String>,
+    /// The interval in seconds in which the neighbor table is checked for `wake_hint`.
+    #[serde(default = "default_wake_hint_interval_sec")]
+    pub wake_hint_interval_sec: u64,
+}
+
+fn default_wake_hint_interval_sec() -> u64 {
+    5
+}
+
+/// A password, given either directly, as a file to read it from or as an environment variable to
+/// read it from. At most one of these may be set. To be used with `#[serde(flatten)]` by
+/// implementing settings struct.
+#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct PassSettings {
+    pub pass: Option<String>,
+    pub pass_file: Option<PathBuf>,
+    pub pass_env: Option<String>,
+}
+
+impl PassSettings {
+    /// Get the password, reading it from the file or environment variable if needed.
+    /// A trailing newline of a password file is ignored.
+    #[cfg(any(
+        feature = "mqtt",
+        feature = "notifier-ntfy",
+        feature = "notifier-smtp",
+        feature = "sink-hs100",
+        feature = "sink-kodi-rpc-cec",
+        feature = "sink-pjlink",
+        feature = "sink-redfish",
+        feature = "sink-tapo",
+        feature = "sink-tuya",
+        feature = "source-kodi",
+        feature = "source-steamlink",
+        feature = "source-webhook",
+        feature = "ssh",
+        feature = "telegram"
+    ))]
+    pub fn resolve(&self) -> Result<Option<String>, crate::error::Error> {
+        match (&self.pass, &self.pass_file, &self.pass_env) {
+            (None, None, None) => Ok(None),
+            (Some(pass), None, None) => Ok(Some(pass.clone())),
+            (None, Some(path), None) => {
+                let pass = std::fs::read_to_string(path)
+                    .map_err(|e| format!("failed reading password file {}: {e}", path.display()))?;
+                Ok(Some(pass.trim_end_matches(['\n', '\r']).to_string()))
+            }
+            (None, None, Some(var)) => env::var(var)
+                .map(Some)
+                .map_err(|e| format!("failed reading password from ${var}: {e}").into()),
+            _ => Err("only one of pass, pass-file and pass-env may be set".into()),
+        }
+    }
+}
+
+/// What to do with a sink when the app shuts down.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ShutdownAction {
+    /// Leave the sink in whatever state it is in.
+    #[default]
+    Leave,
+    /// Turn the sink on.
+    On,
+    /// Turn the sink off.
+    Off,
+}
+
+/// Which of the sources that may trigger a sink need to be active to turn it on.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum TriggerMode {
+    /// Any single source.
+    #[default]
+    Any,
+    /// All of them.
+    All,
+}
+
+/// How a sink is treated after a command failed and its state is unknown.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum OnUnknown {
+    /// Retry the command according to the retry settings.
+    #[default]
+    Retry,
+    /// Assume the sink is on until the next source transition.
+    AssumeOn,
+    /// Assume the sink is off until the next source transition.
+    AssumeOff,
+    /// Send no more commands until the sink is reset manually, e.g. with the `reset` command.
+    ManualReset,
+}
+
+/// How failed sink commands are retried.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct RetrySettings {
+    /// Number of failed attempts after which to give up until the next source transition.
+    /// If not set, commands are retried forever.
+    pub max_attempts: Option<u32>,
+    /// Delay in seconds before the first retry.
+    pub initial_delay_sec: u64,
+    /// Factor the delay is multiplied with after each failed retry.
+    pub backoff_factor: f64,
+    /// Upper limit for the delay in seconds.
+    pub max_delay_sec: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: None,
+            initial_delay_sec: 5,
+            backoff_factor: 2.0,
+            max_delay_sec: 300,
+        }
+    }
+}
+
+impl RetrySettings {
+    /// The delay before retrying after the given number of consecutive failed attempts.
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        let factor = self
+            .backoff_factor
+            .powi(failed_attempts.saturating_sub(1) as i32);
+        Duration::from_secs_f64(
+            (self.initial_delay_sec as f64 * factor)
+                .min(self.max_delay_sec as f64)
+                .max(0.0),
+        )
+    }
 }
 
 /// Basic settings for sinks. To be used with `#[serde(flatten)]` by
 /// implementing settings struct.
 #[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct SinkBaseSettings {
@@ -49,13 +487,126 @@ pub struct SinkBaseSettings {
     /// If both are set, then only sources that match both filters will trigger. If neither are
     /// set, all sources will trigger.
     pub on_source_blacklist: Option<Vec<String>>,
+    /// Whether any or all of the sources allowed by the whitelist and blacklist need to be
+    /// active to turn this sink on.
+    #[serde(default)]
+    pub trigger_mode: TriggerMode,
+    /// A whitelist of sources that are considered when deciding whether to turn this sink off
+    /// (`name` field of source). The sink is turned off once all of the considered sources
+    /// are off.
+    ///
+    /// The whitelist and blacklist are combined in the same way as for on events. If neither are
+    /// set, all sources are considered.
+    pub off_source_whitelist: Option<Vec<String>>,
+    /// A blacklist of sources that are NOT considered when deciding whether to turn this sink
+    /// off (`name` field of source).
+    pub off_source_blacklist: Option<Vec<String>>,
     /// Timeout in seconds.
     pub timeout_sec: u32,
+    /// What to do with the sink when the app shuts down.
+    #[serde(default)]
+    pub on_shutdown: ShutdownAction,
+    /// How failed commands are retried.
+    #[serde(default)]
+    pub retry: RetrySettings,
+    /// How the sink is treated after a command failed and its state is unknown.
+    #[serde(default)]
+    pub on_unknown: OnUnknown,
+    /// Minimum time in seconds between two changes of the power state, to protect relays or
+    /// lamps from source flapping. Changes requested in the meantime are delayed, only the
+    /// latest one is applied.
+    pub min_seconds_between_toggles: Option<u64>,
+    /// Minimum time in seconds the sink stays off once turned off, to protect projector lamps or
+    /// compressors from restarting right away. Turning on in the meantime is delayed until then.
+    pub min_off_seconds: Option<u64>,
+    /// Put the sink into standby once all sources have been off for this many seconds, before
+    /// it is turned off fully after the power off check interval. Sinks that don't support
+    /// standby are only turned off.
+    pub standby_after_sec: Option<u64>,
+    /// Minimum time in seconds since any other sink was turned on before turning on this sink.
+    /// Overrides the general `power-on-delay-sec`.
+    pub power_on_delay_sec: Option<u64>,
+    /// Power draw in watts while on, to estimate its energy use.
+    pub watts: Option<f64>,
+    /// Run before the sink is turned on.
+    pub pre_on: Option<HookSettings>,
+    /// Run after the sink was turned on.
+    pub post_on: Option<HookSettings>,
+    /// Run before the sink is turned off, e.g. to pause a scrub before cutting the power.
+    pub pre_off: Option<HookSettings>,
+    /// Run after the sink was turned off.
+    pub post_off: Option<HookSettings>,
+    /// Drive the sink like a momentary contact, such as a relay wired to a power button.
+    pub pulse: Option<PulseSettings>,
+    /// Announce turning the sink off to the notifiers and wait before doing it, so that it can
+    /// still be kept on with an override.
+    pub confirm_off: Option<ConfirmOffSettings>,
+    /// Sinks with a priority below the `min-priority` of `[general.load-shedding]` are turned
+    /// off while load is shed.
+    #[serde(default)]
+    pub priority: u32,
+}
+
+/// A last chance before a sink is turned off, see `confirm-off`.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ConfirmOffSettings {
+    /// How long to wait after the announcement before turning the sink off.
+    pub window_sec: u64,
+}
+
+/// Momentary operation of a sink: turning it on closes the contact (turns the device on) and
+/// opens it again after the duration.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct PulseSettings {
+    /// How long the contact is closed for turning on.
+    pub duration_ms: u64,
+    /// What turning off does.
+    #[serde(default)]
+    pub off: PulseOff,
+}
+
+/// What turning off a sink in pulse mode does.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum PulseOff {
+    /// The same pulse as for turning on, for buttons that toggle.
+    #[default]
+    Pulse,
+    /// A pulse of a different duration in milliseconds, e.g. a long press.
+    PulseMs(u64),
+    /// Nothing, for devices that turn off on their own.
+    NoOp,
+}
+
+/// A shell command or webhook run around turning a sink on or off. It counts towards the timeout
+/// of the sink. The command gets `PPC_SINK` and `PPC_POWER_STATE` in its environment, the
+/// webhook the same as JSON.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct HookSettings {
+    /// Run with `sh -c`, failing if it exits with a non-zero code.
+    pub command: Option<String>,
+    /// POSTed to, failing on an error status.
+    pub url: Option<String>,
+    /// Whether a failing pre hook aborts turning the sink on or off, which then counts as a
+    /// failed command. Otherwise, and for post hooks, failures are only logged.
+    #[serde(default)]
+    pub abort_on_failure: bool,
 }
 
 /// Basic settings for sources. To be used with `#[serde(flatten)]` by
 /// implementing settings struct.
 #[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct SourceBaseSettings {
@@ -67,50 +618,128 @@ pub struct SourceBaseSettings {
     pub poll_interval_sec: PollInterval,
     /// Timeout in seconds.
     pub timeout_sec: u32,
+    /// If set, slow down polling while the device seems to be asleep.
+    pub sleepy: Option<SleepySettings>,
+    /// Which power state to assume while polling fails.
+    #[serde(default)]
+    pub on_error: OnError,
+    /// What to do when polling keeps timing out.
+    #[serde(default)]
+    pub watchdog: WatchdogSettings,
+    /// Keep reporting the source as on for this many seconds after it turned off, to smooth
+    /// over short drops.
+    pub linger_sec: Option<u64>,
+}
+
+/// Escalation for sources that seem to be stuck, because polling them timed out repeatedly.
+/// Sources with sleepy settings are exempt while asleep.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(default)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct WatchdogSettings {
+    /// Number of timeouts in a row after which the source is considered degraded until it is
+    /// polled successfully again.
+    pub after_timeouts: u32,
+    /// Whether to re-create the source at that point, e.g. to recover stuck connections or
+    /// threads. If it keeps timing out, it's re-created again after as many timeouts.
+    pub recreate: bool,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            after_timeouts: 5,
+            recreate: false,
+        }
+    }
+}
+
+/// Which power state to assume for a source while polling it fails.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum OnError {
+    /// Keep the last known state.
+    #[default]
+    Keep,
+    /// Assume that the source is off.
+    AssumeOff,
+    /// Assume that the state is unknown, once polling has been failing for this many seconds.
+    AssumeUnknownAfterSec(u64),
+}
+
+/// Basic settings for notifiers. To be used with `#[serde(flatten)]` by
+/// implementing settings struct.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct NotifierBaseSettings {
+    /// Human-readable name of the notifier.
+    pub name: String,
+    /// Whether this notifier is enabled.
+    pub enable: bool,
+    /// Timeout in seconds.
+    pub timeout_sec: u32,
+    /// The kinds of events to notify about. If not set, all events except for regular power
+    /// transitions of sources and sinks are notified about.
+    pub events: Option<Vec<EventKind>>,
 }
 
 /// Settings for a sink.
 pub trait SinkSettings {
     type Impl: Sink;
     fn base(&self) -> &SinkBaseSettings;
-    fn create_sink(&self) -> Result<Self::Impl, Box<dyn Error>>;
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error>;
 }
 
 /// Settings for a source.
 pub trait SourceSettings {
     type Impl: Source;
     fn base(&self) -> &SourceBaseSettings;
-    fn create_source(&self) -> Result<Self::Impl, Box<dyn Error>>;
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error>;
 }
 
-/// Mapping of all available sinks by type.
-#[derive(Clone, Debug, Default, Deserialize)]
-#[serde(deny_unknown_fields)]
-#[serde(rename_all = "kebab-case")]
-pub struct MapOfSinkSettings {
-    #[cfg(feature = "sink-hs100")]
-    #[serde(default)]
-    pub hs100: Box<[crate::sink::hs100::Settings]>,
-    #[cfg(feature = "sink-kodi-rpc-cec")]
-    #[serde(default)]
-    pub kodi_rpc_cec: Box<[crate::sink::kodi_rpc_cec::Settings]>,
+/// Settings for a notifier.
+#[cfg(any(
+    feature = "notifier-ntfy",
+    feature = "notifier-smtp",
+    feature = "notifier-webhook"
+))]
+pub trait NotifierSettings {
+    type Impl: Notifier;
+    fn base(&self) -> &NotifierBaseSettings;
+    fn create_notifier(&self) -> Result<Self::Impl, crate::error::Error>;
 }
 
-/// Mapping of all available sources by type.
+/// Settings of all sinks, by type.
+pub type MapOfSinkSettings = MapOf<dyn AnySinkSettings>;
+
+/// Settings of all sources, by type.
+pub type MapOfSourceSettings = MapOf<dyn AnySourceSettings>;
+
+/// Mapping of all available notifiers by type.
 #[derive(Clone, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
-pub struct MapOfSourceSettings {
-    #[cfg(feature = "source-kodi")]
+pub struct MapOfNotifierSettings {
+    #[cfg(feature = "notifier-ntfy")]
+    #[serde(default)]
+    pub ntfy: Box<[crate::notifier::ntfy::Settings]>,
+    #[cfg(feature = "notifier-smtp")]
     #[serde(default)]
-    pub kodi: Box<[crate::source::kodi::Settings]>,
-    #[cfg(feature = "source-steamlink")]
+    pub smtp: Box<[crate::notifier::smtp::Settings]>,
+    #[cfg(feature = "notifier-webhook")]
     #[serde(default)]
-    pub steamlink: Box<[crate::source::steamlink::Settings]>,
+    pub webhook: Box<[crate::notifier::webhook::Settings]>,
 }
 
 /// App settings.
 #[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct Settings {
@@ -119,15 +748,146 @@ pub struct Settings {
     pub sink: MapOfSinkSettings,
     #[serde(default)]
     pub source: MapOfSourceSettings,
+    #[serde(default)]
+    pub notifier: MapOfNotifierSettings,
+    #[serde(default)]
+    pub zone: Box<[ZoneSettings]>,
+    #[serde(default)]
+    pub route: Box<[RouteSettings]>,
 }
 
-/// Read the [`config.toml`] in the current working directory as the app configuration.
-pub fn read() -> Result<Settings, Box<dyn Error>> {
-    let config_path = env::current_dir()?.join("config.toml");
+/// Prefix of environment variables that override config values.
+const ENV_PREFIX: &str = "PPC__";
+
+/// Read the config file at the given path as the app configuration.
+///
+/// All `*.toml` files in a `conf.d` directory next to the config file are merged into it, in
+/// alphabetical order. Values can then be overridden by environment variables, see
+/// [`EnvOverrides`].
+///
+/// HTTP clients created afterwards use the `[general.http]` settings of this config.
+pub fn read(path: &Path) -> Result<Settings, Box<dyn Error>> {
+    let mut files = vec![path.to_path_buf()];
+    let conf_d = path.parent().unwrap_or(Path::new("")).join("conf.d");
+    if conf_d.is_dir() {
+        let mut extra_files = conf_d
+            .read_dir()?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()?;
+        extra_files.retain(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "toml"));
+        extra_files.sort();
+        files.extend(extra_files);
+    }
 
     let config = Config::builder()
-        .add_source(File::from(config_path).required(true))
+        .add_source(MergedFiles(files))
+        .add_source(EnvOverrides)
         .build()?;
 
-    config.try_deserialize().map_err(Into::into)
+    let settings: Settings = config.try_deserialize()?;
+    validate_zones(&settings.zone)?;
+    validate_routes(&settings.route)?;
+    #[cfg(feature = "reqwest")]
+    crate::http_client::configure(&settings.general.http);
+    Ok(settings)
+}
+
+fn validate_zones(zones: &[ZoneSettings]) -> Result<(), Box<dyn Error>> {
+    for (i, zone) in zones.iter().enumerate() {
+        for other in &zones[..i] {
+            if other.name == zone.name {
+                return Err(format!("zone \"{}\" is defined twice", zone.name).into());
+            }
+            if let Some(sink) = zone.sinks.iter().find(|sink| other.sinks.contains(sink)) {
+                return Err(format!(
+                    "sink \"{sink}\" is in both zone \"{}\" and \"{}\"",
+                    other.name, zone.name
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_routes(routes: &[RouteSettings]) -> Result<(), Box<dyn Error>> {
+    for (i, route) in routes.iter().enumerate() {
+        if routes[..i].iter().any(|other| other.name == route.name) {
+            return Err(format!("route \"{}\" is defined twice", route.name).into());
+        }
+    }
+    Ok(())
+}
+
+/// Config source made of multiple files. Unlike adding the files as separate sources, arrays
+/// (such as the lists of sinks and sources) are concatenated instead of replaced.
+#[derive(Clone, Debug)]
+struct MergedFiles(Vec<PathBuf>);
+
+impl config::Source for MergedFiles {
+    fn clone_into_box(&self) -> Box<dyn config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        let mut merged = Map::new();
+        for path in &self.0 {
+            merge_tables(
+                &mut merged,
+                config::Source::collect(&File::from(path.as_path()).required(true))?,
+            );
+        }
+        Ok(merged)
+    }
+}
+
+/// Config source for overriding values with environment variables such as
+/// `PPC__GENERAL__POWER_OFF_CHECK_INTERVAL_SEC` or `PPC__SINK__HS100__0__HOST`.
+///
+/// Keys are separated by double underscores, single underscores are converted to dashes and
+/// numeric keys index into lists.
+#[derive(Clone, Debug)]
+struct EnvOverrides;
+
+impl config::Source for EnvOverrides {
+    fn clone_into_box(&self) -> Box<dyn config::Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, ConfigError> {
+        let origin = "the environment".to_string();
+        Ok(env::vars_os()
+            .filter_map(|(key, value)| {
+                let key = key.to_str()?.strip_prefix(ENV_PREFIX)?;
+                let path = key
+                    .split("__")
+                    .map(|segment| match segment.parse::<usize>() {
+                        Ok(index) => format!("[{index}]"),
+                        Err(_) => format!(".{}", segment.to_lowercase().replace('_', "-")),
+                    })
+                    .collect::<String>();
+                Some((
+                    path.trim_start_matches('.').to_string(),
+                    Value::new(Some(&origin), value.to_str()?),
+                ))
+            })
+            .collect())
+    }
+}
+
+fn merge_tables(into: &mut Map<String, Value>, from: Map<String, Value>) {
+    for (key, value) in from {
+        match into.get_mut(&key) {
+            Some(existing) => match (&mut existing.kind, value.kind) {
+                (ValueKind::Table(existing), ValueKind::Table(table)) => {
+                    merge_tables(existing, table)
+                }
+                (ValueKind::Array(existing), ValueKind::Array(array)) => existing.extend(array),
+                (_, kind) => existing.kind = kind,
+            },
+            None => {
+                into.insert(key, value);
+            }
+        }
+    }
 }
@@ -1,58 +1,361 @@
 use crate::identity::Named;
+#[allow(unused_imports)]
+use crate::registry::registration;
+use crate::registry::{Registered, Registration};
 use crate::settings::{MapOfSinkSettings, SinkBaseSettings, SinkSettings};
 use crate::state::State;
-use std::error::Error;
-use std::iter::empty;
+use std::fmt::Debug;
 use tracing::{error, info};
 
+#[cfg(feature = "sink-cec")]
+pub mod cec;
+#[cfg(feature = "sink-composite")]
+pub mod composite;
+#[cfg(feature = "sink-denon-avr")]
+pub mod denon_avr;
+#[cfg(all(feature = "sink-gpio", target_os = "linux"))]
+pub mod gpio;
+pub mod hook;
 #[cfg(feature = "sink-hs100")]
 pub mod hs100;
 #[cfg(feature = "sink-kodi-rpc-cec")]
 pub mod kodi_rpc_cec;
+#[cfg(feature = "sink-modbus")]
+pub mod modbus;
+#[cfg(feature = "sink-pjlink")]
+pub mod pjlink;
+pub mod pulse;
+#[cfg(feature = "sink-redfish")]
+pub mod redfish;
+#[cfg(feature = "sink-remote-pc")]
+pub mod remote_pc;
+#[cfg(feature = "sink-serial")]
+pub mod serial;
+#[cfg(feature = "sink-tapo")]
+pub mod tapo;
+#[cfg(feature = "sink-tuya")]
+pub mod tuya;
+#[cfg(feature = "sink-webos")]
+pub mod webos;
+mod wol;
+#[cfg(feature = "sink-zigbee2mqtt")]
+pub mod zigbee2mqtt;
+
+pub type SinkCommandResult = Result<(), crate::error::Error>;
+pub type CreateSinkResult = Result<Box<dyn Sink>, crate::error::Error>;
 
 #[async_trait]
 /// A device which power state should be controlled based on whether sources are active or not.
-pub trait Sink {
+pub trait Sink: Send + Sync {
     /// Base settings.
     fn base_settings(&self) -> &SinkBaseSettings;
     /// Turn the sink on.
-    async fn on(&self) -> Result<(), Box<dyn Error>>;
-    /// Turn the sink on.
-    async fn off(&self) -> Result<(), Box<dyn Error>>;
+    async fn on(&self) -> SinkCommandResult;
+    /// Turn the sink off.
+    async fn off(&self) -> SinkCommandResult;
+    /// Whether the sink has a standby level between on and off, see [`Sink::standby`].
+    fn supports_standby(&self) -> bool {
+        false
+    }
+    /// Put the sink into standby, a low power level that is faster to wake from than off.
+    async fn standby(&self) -> SinkCommandResult {
+        Err("standby is not supported".into())
+    }
 }
 
-pub async fn create_sinks(
-    sink_config: &MapOfSinkSettings,
-    state: &mut State,
-) -> Result<(), Box<dyn Error>> {
-    let all = empty();
+/// The sink types of the enabled features.
+const TYPES: &[Registration<dyn AnySinkSettings>] = &[
+    #[cfg(feature = "sink-cec")]
+    registration!("cec", "sink-cec", cec::Settings, ["address", "port"]),
+    #[cfg(feature = "sink-composite")]
+    registration!(
+        "composite",
+        "sink-composite",
+        composite::Settings,
+        ["sinks"]
+    ),
+    #[cfg(feature = "sink-denon-avr")]
+    registration!(
+        "denon-avr",
+        "sink-denon-avr",
+        denon_avr::Settings,
+        ["host", "input"]
+    ),
+    #[cfg(all(feature = "sink-gpio", target_os = "linux"))]
+    registration!(
+        "gpio",
+        "sink-gpio",
+        gpio::Settings,
+        ["active-low", "chip", "line"]
+    ),
     #[cfg(feature = "sink-hs100")]
-    let all = all.chain(create_of_type(&sink_config.hs100));
+    registration!(
+        "hs100",
+        "sink-hs100",
+        hs100::Settings,
+        ["child-id", "host", "pass", "pass-env", "pass-file", "user"]
+    ),
     #[cfg(feature = "sink-kodi-rpc-cec")]
-    let all = all.chain(create_of_type(&sink_config.kodi_rpc_cec));
+    registration!(
+        "kodi-rpc-cec",
+        "sink-kodi-rpc-cec",
+        kodi_rpc_cec::Settings,
+        [
+            "connect-timeout-sec",
+            "insecure",
+            "jsonrpc",
+            "pass",
+            "pass-env",
+            "pass-file",
+            "user"
+        ]
+    ),
+    #[cfg(feature = "sink-modbus")]
+    registration!(
+        "modbus",
+        "sink-modbus",
+        modbus::Settings,
+        ["coil", "host", "port", "unit-id"]
+    ),
+    #[cfg(feature = "sink-pjlink")]
+    registration!(
+        "pjlink",
+        "sink-pjlink",
+        pjlink::Settings,
+        ["host", "pass", "pass-env", "pass-file"]
+    ),
+    #[cfg(feature = "sink-redfish")]
+    registration!(
+        "redfish",
+        "sink-redfish",
+        redfish::Settings,
+        [
+            "auth",
+            "insecure",
+            "off-reset-type",
+            "pass",
+            "pass-env",
+            "pass-file",
+            "system-id",
+            "url",
+            "user"
+        ]
+    ),
+    #[cfg(feature = "sink-remote-pc")]
+    registration!(
+        "remote-pc",
+        "sink-remote-pc",
+        remote_pc::Settings,
+        [
+            "mac",
+            "off-command",
+            "os",
+            "ssh",
+            "wait-until-reachable",
+            "wol-broadcast"
+        ]
+    ),
+    #[cfg(feature = "sink-serial")]
+    registration!(
+        "serial",
+        "sink-serial",
+        serial::Settings,
+        [
+            "baud-rate",
+            "off-payload",
+            "off-response",
+            "on-payload",
+            "on-response",
+            "path"
+        ]
+    ),
+    #[cfg(feature = "sink-tapo")]
+    registration!(
+        "tapo",
+        "sink-tapo",
+        tapo::Settings,
+        ["host", "pass", "pass-env", "pass-file", "user"]
+    ),
+    #[cfg(feature = "sink-tuya")]
+    registration!(
+        "tuya",
+        "sink-tuya",
+        tuya::Settings,
+        [
+            "device-id",
+            "dps",
+            "host",
+            "pass",
+            "pass-env",
+            "pass-file",
+            "version"
+        ]
+    ),
+    #[cfg(feature = "sink-webos")]
+    registration!(
+        "webos",
+        "sink-webos",
+        webos::Settings,
+        [
+            "client-key",
+            "client-key-file",
+            "host",
+            "mac",
+            "secure",
+            "wol-broadcast"
+        ]
+    ),
+    #[cfg(feature = "sink-zigbee2mqtt")]
+    registration!(
+        "zigbee2mqtt",
+        "sink-zigbee2mqtt",
+        zigbee2mqtt::Settings,
+        ["base-topic", "confirm", "friendly-name", "mqtt"]
+    ),
+];
 
-    state.try_register_sinks(all).await
+/// Settings of a sink of any type.
+pub trait AnySinkSettings: Debug + Send + Sync {
+    fn base(&self) -> &SinkBaseSettings;
+    /// Create the sink. Groups create their members from `sink_config`.
+    fn create(&self, sink_config: &MapOfSinkSettings) -> CreateSinkResult;
+    /// Whether the sink is a group of other sinks, which can't be a member of a group.
+    fn is_group(&self) -> bool {
+        false
+    }
+    /// Names of the members of a group.
+    fn members(&self) -> &[String] {
+        &[]
+    }
+    fn clone_box(&self) -> Box<dyn AnySinkSettings>;
 }
 
-fn create_of_type<'a, S>(
-    sink_configs: &'a [S],
-) -> impl Iterator<Item = Result<Box<dyn Sink>, Box<dyn Error>>> + 'a
+impl<S> AnySinkSettings for S
 where
-    S: SinkSettings + 'a,
+    S: SinkSettings + Clone + Debug + Send + Sync + 'static,
     S::Impl: 'static,
 {
-    sink_configs
-        .iter()
-        .filter(|cfg| cfg.base().enable)
-        .map(|cfg| {
-            info!("{} Initializing...", cfg.base().identity());
-            cfg.create_sink()
-                .map(|x| Box::new(x) as Box<dyn Sink>)
-                .map_err(|e| {
-                    error!("{} Failed creating sink: {}", cfg.base().identity(), &e);
-                    e
-                })
+    fn base(&self) -> &SinkBaseSettings {
+        SinkSettings::base(self)
+    }
+
+    fn create(&self, _sink_config: &MapOfSinkSettings) -> CreateSinkResult {
+        self.create_sink().map(pulse::wrap)
+    }
+
+    fn clone_box(&self) -> Box<dyn AnySinkSettings> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn AnySinkSettings> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Registered for dyn AnySinkSettings {
+    const SECTION: &'static str = "sink";
+
+    const BASE_FIELDS: &'static [&'static str] = &[
+        "confirm-off",
+        "enable",
+        "min-off-seconds",
+        "min-seconds-between-toggles",
+        "name",
+        "off-source-blacklist",
+        "off-source-whitelist",
+        "on-shutdown",
+        "on-source-blacklist",
+        "on-source-whitelist",
+        "on-unknown",
+        "post-off",
+        "post-on",
+        "power-on-delay-sec",
+        "pre-off",
+        "pre-on",
+        "priority",
+        "pulse",
+        "retry",
+        "standby-after-sec",
+        "timeout-sec",
+        "trigger-mode",
+        "watts",
+    ];
+
+    fn types() -> &'static [Registration<Self>] {
+        TYPES
+    }
+}
+
+pub async fn create_sinks(
+    sink_config: &MapOfSinkSettings,
+    state: &mut State,
+) -> Result<(), crate::error::Error> {
+    let all = try_create_all(sink_config).map(|(base, result)| {
+        result.map_err(|e| {
+            error!("{} Failed creating sink: {}", base.identity(), &e);
+            e.of(base.identity().clone_owned())
         })
+    });
+
+    state.try_register_sinks(all).await
+}
+
+/// Try to create all enabled sinks, returning the base settings of each sink alongside the
+/// result of creating it.
+pub fn try_create_all(
+    sink_config: &MapOfSinkSettings,
+) -> impl Iterator<Item = (&SinkBaseSettings, CreateSinkResult)> {
+    create_where(sink_config, |base| base.enable)
+}
+
+/// Try to create the sink with the given name, even if it is disabled. Returns `None` if no
+/// sink with this name is configured.
+pub fn try_create_named<'a>(
+    sink_config: &'a MapOfSinkSettings,
+    name: &'a str,
+) -> Option<(&'a SinkBaseSettings, CreateSinkResult)> {
+    create_where(sink_config, move |base| base.name == name).next()
+}
+
+/// Try to create the sink with the given name as a member of a group, even if it is disabled.
+/// Groups can't be members.
+#[cfg(feature = "sink-composite")]
+fn try_create_member<'a>(
+    sink_config: &'a MapOfSinkSettings,
+    name: &'a str,
+) -> Option<(&'a SinkBaseSettings, CreateSinkResult)> {
+    create_devices_where(sink_config, move |base| base.name == name).next()
+}
+
+/// Create the sinks matching the filter, groups after all other sinks.
+fn create_where<'a>(
+    sink_config: &'a MapOfSinkSettings,
+    filter: impl Fn(&SinkBaseSettings) -> bool + Copy + 'a,
+) -> impl Iterator<Item = (&'a SinkBaseSettings, CreateSinkResult)> + 'a {
+    let groups = sink_config.iter().filter(|cfg| cfg.is_group());
+    create_devices_where(sink_config, filter).chain(create_of(sink_config, groups, filter))
+}
+
+/// Like [`create_where`], but without groups of sinks.
+fn create_devices_where<'a>(
+    sink_config: &'a MapOfSinkSettings,
+    filter: impl Fn(&SinkBaseSettings) -> bool + 'a,
+) -> impl Iterator<Item = (&'a SinkBaseSettings, CreateSinkResult)> + 'a {
+    let devices = sink_config.iter().filter(|cfg| !cfg.is_group());
+    create_of(sink_config, devices, filter)
+}
+
+fn create_of<'a>(
+    sink_config: &'a MapOfSinkSettings,
+    sinks: impl Iterator<Item = &'a dyn AnySinkSettings> + 'a,
+    filter: impl Fn(&SinkBaseSettings) -> bool + 'a,
+) -> impl Iterator<Item = (&'a SinkBaseSettings, CreateSinkResult)> + 'a {
+    sinks.filter(move |cfg| filter(cfg.base())).map(|cfg| {
+        info!("{} Initializing...", cfg.base().identity());
+        (cfg.base(), cfg.create(sink_config))
+    })
 }
 
 impl SinkBaseSettings {
@@ -76,4 +379,17 @@ impl SinkBaseSettings {
             true
         }
     }
+
+    pub fn allows_source_for_off(&self, source_name: &str) -> bool {
+        if let Some(blacklist) = &self.off_source_blacklist {
+            if blacklist.iter().any(|itm| itm == source_name) {
+                return false;
+            }
+        }
+
+        match &self.off_source_whitelist {
+            Some(whitelist) => whitelist.iter().any(|itm| itm == source_name),
+            None => true,
+        }
+    }
 }
@@ -0,0 +1,58 @@
+#![cfg(feature = "sink-cec")]
+
+use crate::cec::{CecAdapter, LogicalAddress};
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use serde::Deserialize;
+use std::sync::Arc;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "CecSinkSettings")
+)]
+pub struct Settings {
+    /// The libcec port of the CEC adapter, e.g. `RPI` or `/dev/ttyACM0`.
+    pub port: String,
+    /// The device to turn on or off.
+    #[serde(default)]
+    pub address: LogicalAddress,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+impl SinkSettings for Settings {
+    type Impl = CecSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(CecSink {
+            settings: self.clone(),
+            adapter: CecAdapter::open(&self.port)?,
+        })
+    }
+}
+
+pub struct CecSink {
+    settings: Settings,
+    adapter: Arc<CecAdapter>,
+}
+
+#[async_trait]
+impl Sink for CecSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.adapter.power_on(self.settings.address).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.adapter.standby(self.settings.address).await
+    }
+}
@@ -0,0 +1,148 @@
+#![cfg(feature = "sink-composite")]
+
+use crate::error;
+use crate::identity::Named;
+use crate::settings::{MapOfSinkSettings, SinkBaseSettings};
+use crate::sink::{
+    pulse, try_create_member, AnySinkSettings, CreateSinkResult, Sink, SinkCommandResult,
+};
+use futures::future::join_all;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::{debug, warn};
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "CompositeSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Names of the sinks in the group, which can't be groups themselves. They are usually
+    /// disabled, so that they are only controlled through the group.
+    pub sinks: Vec<String>,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+impl Settings {
+    pub fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    /// Create the group with new instances of its members, which are created even if they are
+    /// disabled.
+    pub fn create_sink(
+        &self,
+        sink_config: &MapOfSinkSettings,
+    ) -> Result<CompositeSink, error::Error> {
+        let mut members = Vec::with_capacity(self.sinks.len());
+        for name in &self.sinks {
+            let (base, member) = try_create_member(sink_config, name)
+                .ok_or_else(|| format!("no sink named \"{name}\" for the group"))?;
+            if base.enable {
+                warn!(
+                    "{} Also controlled on its own, since it is enabled.",
+                    base.identity()
+                );
+            }
+            members.push(member.map_err(|e| e.of(base.identity().clone_owned()))?);
+        }
+        Ok(CompositeSink {
+            settings: self.clone(),
+            members,
+        })
+    }
+}
+
+impl AnySinkSettings for Settings {
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create(&self, sink_config: &MapOfSinkSettings) -> CreateSinkResult {
+        self.create_sink(sink_config).map(pulse::wrap)
+    }
+
+    fn is_group(&self) -> bool {
+        true
+    }
+
+    fn members(&self) -> &[String] {
+        &self.sinks
+    }
+
+    fn clone_box(&self) -> Box<dyn AnySinkSettings> {
+        Box::new(self.clone())
+    }
+}
+
+/// A group of other sinks, turned on and off together.
+pub struct CompositeSink {
+    settings: Settings,
+    members: Vec<Box<dyn Sink>>,
+}
+
+impl CompositeSink {
+    /// Turn all members on or off at the same time, each with its own timeout. Fails if any of
+    /// them failed, naming all that did.
+    async fn set(&self, on: bool) -> SinkCommandResult {
+        let results = join_all(self.members.iter().map(|member| async move {
+            let base = member.base_settings();
+            let command = match on {
+                true => member.on(),
+                false => member.off(),
+            };
+            let result = match timeout(Duration::from_secs(base.timeout_sec as u64), command).await
+            {
+                Ok(result) => result,
+                Err(_) => Err(error::Error::Timeout),
+            };
+            (base, result)
+        }))
+        .await;
+        let mut errors = Vec::new();
+        for (base, result) in results {
+            match result {
+                Ok(()) => debug!(
+                    "{} Turned {}.",
+                    base.identity(),
+                    if on { "on" } else { "off" }
+                ),
+                Err(e) => errors.push((base, e)),
+            }
+        }
+        match errors.len() {
+            0 => Ok(()),
+            // Keeps the kind of the error, e.g. so that rejected credentials are not retried.
+            1 => {
+                let (base, e) = errors.pop().unwrap();
+                Err(e.of(base.identity().clone_owned()))
+            }
+            _ => {
+                let errors: Vec<_> = errors
+                    .iter()
+                    .map(|(base, e)| format!("{}: {}", base.name, e))
+                    .collect();
+                Err(format!("failed for {}", errors.join(", ")).into())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for CompositeSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.set(true).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.set(false).await
+    }
+}
@@ -0,0 +1,167 @@
+#![cfg(feature = "sink-denon-avr")]
+
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use serde::Deserialize;
+use std::error::Error;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout};
+use tracing::debug;
+
+const TELNET_PORT: u16 = 23;
+/// How long to wait for the response to a single command.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+/// The receiver ignores commands for a moment after powering on.
+const POWER_ON_SETTLE: Duration = Duration::from_secs(2);
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "DenonAvrSinkSettings")
+)]
+pub struct Settings {
+    /// Host name or IP address of the receiver, optionally with the port.
+    pub host: String,
+    /// If set, select this input after turning on, e.g. `GAME` or `MPLAY`.
+    pub input: Option<String>,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+impl SinkSettings for Settings {
+    type Impl = DenonAvrSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(DenonAvrSink {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// A Denon or Marantz receiver, controlled with its telnet protocol.
+pub struct DenonAvrSink {
+    settings: Settings,
+}
+
+struct Connection {
+    stream: BufReader<TcpStream>,
+}
+
+impl Connection {
+    async fn open(host: &str) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let stream = if host.contains(':') {
+            TcpStream::connect(host).await?
+        } else {
+            TcpStream::connect((host, TELNET_PORT)).await?
+        };
+        Ok(Self {
+            stream: BufReader::new(stream),
+        })
+    }
+
+    /// Send a command and return the value of the first response with the prefix of the
+    /// command, e.g. `ON` for `PW?`. The receiver also sends unrelated status updates,
+    /// which are skipped.
+    async fn command(&mut self, command: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+        debug!("Sending {command}");
+        self.stream
+            .get_mut()
+            .write_all(format!("{command}\r").as_bytes())
+            .await?;
+        let prefix = &command[..2];
+        timeout(RESPONSE_TIMEOUT, async {
+            let mut line = Vec::new();
+            loop {
+                line.clear();
+                if self.stream.read_until(b'\r', &mut line).await? == 0 {
+                    return Err("connection closed by the receiver".into());
+                }
+                let response = String::from_utf8_lossy(&line);
+                let response = response.trim();
+                debug!("Received {response}");
+                if let Some(value) = response.strip_prefix(prefix) {
+                    return Ok(value.to_string());
+                }
+            }
+        })
+        .await
+        .map_err(|_| format!("no response to {command}"))?
+    }
+
+    async fn set_power(&mut self, on: bool) -> SinkCommandResult {
+        let (command, expected) = if on {
+            ("PWON", "ON")
+        } else {
+            ("PWSTANDBY", "STANDBY")
+        };
+        self.command(command).await?;
+        let state = self.command("PW?").await?;
+        if state != expected {
+            return Err(format!("receiver reports power {state} after {command}").into());
+        }
+        Ok(())
+    }
+
+    /// Turn the main zone on or off, leaving the receiver powered.
+    async fn set_main_zone(&mut self, on: bool) -> SinkCommandResult {
+        let (command, expected) = if on { ("ZMON", "ON") } else { ("ZMOFF", "OFF") };
+        self.command(command).await?;
+        let state = self.command("ZM?").await?;
+        if state != expected {
+            return Err(format!("receiver reports main zone {state} after {command}").into());
+        }
+        Ok(())
+    }
+
+    async fn select_input(&mut self, input: &str) -> SinkCommandResult {
+        self.command(&format!("SI{input}")).await?;
+        let selected = self.command("SI?").await?;
+        if !selected.eq_ignore_ascii_case(input) {
+            return Err(
+                format!("receiver reports input {selected} after selecting {input}").into(),
+            );
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for DenonAvrSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        let mut connection = Connection::open(&self.settings.host).await?;
+        connection.set_power(true).await?;
+        // The main zone stays off after standby.
+        connection.set_main_zone(true).await?;
+        if let Some(input) = &self.settings.input {
+            sleep(POWER_ON_SETTLE).await;
+            connection.select_input(input).await?;
+        }
+        Ok(())
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        let mut connection = Connection::open(&self.settings.host).await?;
+        connection.set_power(false).await
+    }
+
+    fn supports_standby(&self) -> bool {
+        true
+    }
+
+    /// Turns off the main zone, which wakes up much faster than the whole receiver.
+    async fn standby(&self) -> SinkCommandResult {
+        let mut connection = Connection::open(&self.settings.host).await?;
+        connection.set_main_zone(false).await
+    }
+}
@@ -0,0 +1,96 @@
+#![cfg(all(feature = "sink-gpio", target_os = "linux"))]
+
+use crate::error;
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use gpio_cdev::{Chip, LineHandle, LineRequestFlags};
+use serde::Deserialize;
+use std::sync::Mutex;
+
+const CONSUMER: &str = "personal-power-ctrl";
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "GpioSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// The GPIO character device.
+    #[serde(default = "default_chip")]
+    pub chip: String,
+    /// Offset of the line on the chip, the BCM number on a Raspberry Pi.
+    pub line: u32,
+    /// Drive the line low to turn on, as most relay boards expect.
+    #[serde(default)]
+    pub active_low: bool,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_chip() -> String {
+    "/dev/gpiochip0".to_string()
+}
+
+impl SinkSettings for Settings {
+    type Impl = GpioSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, error::Error> {
+        Ok(GpioSink {
+            settings: self.clone(),
+            handle: Mutex::new(None),
+        })
+    }
+}
+
+/// A relay attached to a GPIO line of the machine running the daemon.
+pub struct GpioSink {
+    settings: Settings,
+    /// Requested on the first command, so starting the daemon does not switch the relay.
+    /// It's kept afterwards, since releasing the line may reset it.
+    handle: Mutex<Option<LineHandle>>,
+}
+
+impl GpioSink {
+    fn set(&self, on: bool) -> SinkCommandResult {
+        let value = u8::from(on);
+        let mut handle = self.handle.lock().unwrap();
+        match &*handle {
+            Some(handle) => handle.set_value(value).map_err(error::Error::other)?,
+            None => {
+                let mut flags = LineRequestFlags::OUTPUT;
+                if self.settings.active_low {
+                    flags |= LineRequestFlags::ACTIVE_LOW;
+                }
+                let mut chip = Chip::new(&self.settings.chip)
+                    .map_err(|e| format!("failed opening {}: {e}", self.settings.chip))?;
+                let line = chip
+                    .get_line(self.settings.line)
+                    .map_err(error::Error::other)?;
+                let requested = line.request(flags, value, CONSUMER);
+                *handle = Some(requested.map_err(error::Error::other)?);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for GpioSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.set(true)
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.set(false)
+    }
+}
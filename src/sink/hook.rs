@@ -0,0 +1,53 @@
+use crate::settings::HookSettings;
+use crate::sink::SinkCommandResult;
+use tokio::process::Command;
+use tracing::debug;
+
+/// Run the command and webhook of the hook for the sink being turned on or off.
+pub async fn run(hook: &HookSettings, sink_name: &str, on: bool) -> SinkCommandResult {
+    let power_state = if on { "on" } else { "off" };
+    if let Some(command) = &hook.command {
+        debug!("Running hook command \"{command}\"");
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("PPC_SINK", sink_name)
+            .env("PPC_POWER_STATE", power_state)
+            .kill_on_drop(true)
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(format!("hook command \"{command}\" failed with {status}").into());
+        }
+    }
+    if let Some(url) = &hook.url {
+        post(url, sink_name, power_state).await?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "reqwest")]
+async fn post(url: &str, sink_name: &str, power_state: &str) -> SinkCommandResult {
+    debug!("Posting to hook {url}");
+    let url = reqwest::Url::parse(url).map_err(crate::error::Error::other)?;
+    let client = crate::http_client::builder(&url)
+        .and_then(|builder| Ok(builder.build()?))
+        .map_err(|e| e.to_string())?;
+    let body = serde_json::to_vec(&serde_json::json!({
+        "sink": sink_name,
+        "power-state": power_state,
+    }))?;
+    client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(not(feature = "reqwest"))]
+async fn post(_url: &str, _sink_name: &str, _power_state: &str) -> SinkCommandResult {
+    Err("webhook hooks require a feature using HTTP, such as notifier-webhook".into())
+}
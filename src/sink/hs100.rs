@@ -1,15 +1,30 @@
 #![cfg(feature = "sink-hs100")]
 
-use crate::settings::{SinkBaseSettings, SinkSettings};
-use crate::sink::Sink;
+use crate::error;
+use crate::settings::{PassSettings, SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use crate::{kasa, klap};
 use serde::Deserialize;
+use serde_json::json;
 use std::borrow::Cow;
-use std::convert::Infallible;
-use std::error::Error;
 
 #[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Hs100SinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
 pub struct Settings {
     pub host: String,
+    /// ID of a single outlet of a power strip, such as the HS300 or KP303, as printed by
+    /// `discover`. Without it, the whole device is switched.
+    pub child_id: Option<String>,
+    /// Email address of the TP-Link account the device is registered with. Required by newer
+    /// firmware, which only accepts authenticated requests (KLAP).
+    pub user: Option<String>,
+    #[serde(flatten)]
+    pub pass: PassSettings,
     #[serde(flatten)]
     base: SinkBaseSettings,
 }
@@ -21,18 +36,54 @@ impl SinkSettings for Settings {
         &self.base
     }
 
-    fn create_sink(&self) -> Result<Self::Impl, Box<dyn Error>> {
-        Hs100Sink::new(self.clone()).map_err(Into::into)
+    fn create_sink(&self) -> Result<Self::Impl, error::Error> {
+        Hs100Sink::new(self.clone())
     }
 }
 
 pub struct Hs100Sink {
     settings: Settings,
+    klap: Option<klap::Client>,
 }
 
 impl Hs100Sink {
-    fn new(settings: Settings) -> Result<Self, Infallible> {
-        Ok(Self { settings })
+    fn new(settings: Settings) -> Result<Self, error::Error> {
+        let klap = match &settings.user {
+            Some(user) => {
+                let pass = settings
+                    .pass
+                    .resolve()?
+                    .ok_or("a password is required with user")?;
+                Some(klap::Client::new(&settings.host, user, &pass)?)
+            }
+            None => None,
+        };
+        Ok(Self { settings, klap })
+    }
+
+    /// Switch the relay with a request of our own, for single outlets and KLAP, which the API
+    /// crate does not support.
+    async fn set(&self, on: bool) -> SinkCommandResult {
+        let mut request = json!({
+            "system": { "set_relay_state": { "state": u8::from(on) } },
+        });
+        if let Some(child_id) = &self.settings.child_id {
+            request["context"] = json!({ "child_ids": [child_id] });
+        }
+        let response = match &self.klap {
+            Some(klap) => klap.request(&request).await?,
+            None => kasa::request(&self.settings.host, &request).await?,
+        };
+        let result = &response["system"]["set_relay_state"];
+        match result["err_code"].as_i64() {
+            Some(0) => Ok(()),
+            Some(code) => Err(format!(
+                "error {code}: {}",
+                result["err_msg"].as_str().unwrap_or("unknown")
+            )
+            .into()),
+            None => Err(format!("unexpected response: {response}").into()),
+        }
     }
 }
 
@@ -42,13 +93,19 @@ impl Sink for Hs100Sink {
         self.settings.base()
     }
 
-    async fn on(&self) -> Result<(), Box<dyn Error>> {
+    async fn on(&self) -> SinkCommandResult {
+        if self.settings.child_id.is_some() || self.klap.is_some() {
+            return self.set(true).await;
+        }
         let plug = hs100api::SmartPlug::new(Cow::Borrowed(&self.settings.host));
-        plug.on().await.map(|_| ()).map_err(Into::into)
+        plug.on().await.map(|_| ()).map_err(error::Error::network)
     }
 
-    async fn off(&self) -> Result<(), Box<dyn Error>> {
+    async fn off(&self) -> SinkCommandResult {
+        if self.settings.child_id.is_some() || self.klap.is_some() {
+            return self.set(false).await;
+        }
         let plug = hs100api::SmartPlug::new(Cow::Borrowed(&self.settings.host));
-        plug.off().await.map(|_| ()).map_err(Into::into)
+        plug.off().await.map(|_| ()).map_err(error::Error::network)
     }
 }
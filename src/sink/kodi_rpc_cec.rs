@@ -1,18 +1,26 @@
 #![cfg(feature = "sink-kodi-rpc-cec")]
 
+use crate::identity::Named;
+use crate::kodi::ConnectionSettings;
 use crate::settings::{SinkBaseSettings, SinkSettings};
-use crate::sink::kodi_rpc_cec::kodi_cmd::{AddonsExecute, CecCommand};
-use crate::sink::Sink;
+use crate::sink::kodi_rpc_cec::kodi_cmd::{AddonsExecute, AddonsGetAddonDetails, CecCommand};
+use crate::sink::{Sink, SinkCommandResult};
 use kodi_jsonrpc_client::KodiClient;
 use serde::Deserialize;
-use std::convert::Infallible;
-use std::error::Error;
+use std::sync::Arc;
+use tracing::error;
+
+const ADDON_ID: &str = "script.json-cec";
 
 #[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "KodiRpcCecSinkSettings")
+)]
 pub struct Settings {
-    pub jsonrpc: String,
-    pub user: Option<String>,
-    pub pass: Option<String>,
+    #[serde(flatten)]
+    pub connection: ConnectionSettings,
     #[serde(flatten)]
     base: SinkBaseSettings,
 }
@@ -24,38 +32,54 @@ impl SinkSettings for Settings {
         &self.base
     }
 
-    fn create_sink(&self) -> Result<Self::Impl, Box<dyn Error>> {
-        KodiRpcCecSink::new(self.clone()).map_err(Into::into)
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        KodiRpcCecSink::new(self.clone())
     }
 }
 
 pub struct KodiRpcCecSink {
     settings: Settings,
+    client: Arc<KodiClient>,
 }
 
 impl KodiRpcCecSink {
-    fn new(settings: Settings) -> Result<Self, Infallible> {
-        Ok(Self { settings })
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let client = Arc::new(settings.connection.client()?);
+        // Report a missing addon right away instead of on the first command. Kodi may not be
+        // running yet, so this is not fatal.
+        let identity = settings.base().identity().to_string();
+        let startup_client = client.clone();
+        tokio::spawn(async move {
+            if let Err(e) = check_addon(&startup_client).await {
+                error!("{identity} {e}");
+            }
+        });
+        Ok(Self { settings, client })
     }
 
-    async fn send(&self, command: CecCommand) -> Result<(), Box<dyn Error>> {
-        let mut url = reqwest::Url::parse(&self.settings.jsonrpc)?;
-        if let Some(user) = &self.settings.user {
-            url.set_username(user)
-                .map_err(|_| "failed setting user on kodi rpc")?;
-        }
-        if let Some(pass) = &self.settings.pass {
-            url.set_password(Some(pass))
-                .map_err(|_| "failed setting pass on kodi rpc")?;
+    async fn send(&self, command: CecCommand) -> SinkCommandResult {
+        // Executing a missing or disabled addon is reported as success, so check first.
+        check_addon(&self.client).await?;
+        let response = self
+            .client
+            .send_method(AddonsExecute::json_cec(command))
+            .await?;
+        if response != "OK" {
+            return Err(format!("executing {ADDON_ID} failed: {response}").into());
         }
-        let client = KodiClient::new(reqwest::Client::new(), url);
+        Ok(())
+    }
+}
 
-        client
-            .send_method(AddonsExecute::json_cec(command))
-            .await
-            .map(|_| ())
-            .map_err(Into::into)
+async fn check_addon(client: &KodiClient) -> SinkCommandResult {
+    let details = client
+        .send_method(AddonsGetAddonDetails::enabled(ADDON_ID))
+        .await
+        .map_err(|e| format!("failed getting details of {ADDON_ID}, is it installed? {e}"))?;
+    if !details.addon.enabled {
+        return Err(format!("the addon {ADDON_ID} is disabled").into());
     }
+    Ok(())
 }
 
 #[async_trait]
@@ -64,11 +88,11 @@ impl Sink for KodiRpcCecSink {
         self.settings.base()
     }
 
-    async fn on(&self) -> Result<(), Box<dyn Error>> {
+    async fn on(&self) -> SinkCommandResult {
         self.send(CecCommand::Activate).await
     }
 
-    async fn off(&self) -> Result<(), Box<dyn Error>> {
+    async fn off(&self) -> SinkCommandResult {
         self.send(CecCommand::Standby).await
     }
 }
@@ -97,6 +121,8 @@ mod kodi_cmd {
     pub struct AddonsExecute {
         addonid: &'static str,
         params: AddonsExecuteParams,
+        /// Respond once the script finished, so that commands do not overlap.
+        wait: bool,
     }
 
     impl AddonsExecute {
@@ -106,8 +132,9 @@ mod kodi_cmd {
             params.insert("command".to_string(), command.as_str().into());
 
             Self {
-                addonid: "script.json-cec",
+                addonid: super::ADDON_ID,
                 params,
+                wait: true,
             }
         }
     }
@@ -116,4 +143,34 @@ mod kodi_cmd {
         const NAME: &'static str = "Addons.ExecuteAddon";
         type Response = serde_json::Value;
     }
+
+    #[derive(Debug, serde::Serialize)]
+    pub struct AddonsGetAddonDetails {
+        addonid: &'static str,
+        properties: &'static [&'static str],
+    }
+
+    impl AddonsGetAddonDetails {
+        pub fn enabled(addonid: &'static str) -> Self {
+            Self {
+                addonid,
+                properties: &["enabled"],
+            }
+        }
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    pub struct AddonDetails {
+        pub addon: Addon,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    pub struct Addon {
+        pub enabled: bool,
+    }
+
+    impl KodiMethod for AddonsGetAddonDetails {
+        const NAME: &'static str = "Addons.GetAddonDetails";
+        type Response = AddonDetails;
+    }
 }
@@ -0,0 +1,84 @@
+#![cfg(feature = "sink-modbus")]
+
+use crate::modbus::{Connection, DEFAULT_PORT};
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use serde::Deserialize;
+use tracing::debug;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ModbusSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the device.
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+    /// Address of the coil switching the relay, starting at 0.
+    pub coil: u16,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+impl SinkSettings for Settings {
+    type Impl = ModbusSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(ModbusSink {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// A relay or PDU outlet, switched by a coil over Modbus TCP.
+pub struct ModbusSink {
+    settings: Settings,
+}
+
+impl ModbusSink {
+    async fn set(&self, on: bool) -> SinkCommandResult {
+        let settings = &self.settings;
+        let mut connection =
+            Connection::connect(&settings.host, settings.port, settings.unit_id).await?;
+        connection.write_coil(settings.coil, on).await?;
+        let state = connection.read_coil(settings.coil).await?;
+        debug!("Coil {} reads back {state}", settings.coil);
+        if state != on {
+            return Err(format!("coil {} did not change", settings.coil).into());
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for ModbusSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.set(true).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.set(false).await
+    }
+}
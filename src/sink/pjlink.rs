@@ -0,0 +1,205 @@
+#![cfg(feature = "sink-pjlink")]
+
+use crate::error;
+use crate::settings::{PassSettings, SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use md5::{Digest, Md5};
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt::Write;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::{sleep, timeout};
+use tracing::debug;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+const PORT: u16 = 4352;
+/// How long to wait for the response to a single command.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often the power status is queried while the projector is warming up or cooling down.
+const TRANSITION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "PjlinkSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the projector, optionally with the port.
+    pub host: String,
+    /// The PJLink password, if authentication is enabled on the projector.
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+impl SinkSettings for Settings {
+    type Impl = PjlinkSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> std::result::Result<Self::Impl, error::Error> {
+        Ok(PjlinkSink {
+            settings: self.clone(),
+            pass: self.pass.resolve()?,
+        })
+    }
+}
+
+/// A projector or display controlled with PJLink class 1, which most projectors support.
+pub struct PjlinkSink {
+    settings: Settings,
+    pass: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PowerStatus {
+    Off,
+    On,
+    CoolingDown,
+    WarmingUp,
+}
+
+struct Connection {
+    stream: BufReader<TcpStream>,
+    /// Prefix of the next command, to authenticate the connection with the first one.
+    auth: Option<String>,
+}
+
+impl Connection {
+    async fn open(host: &str, pass: Option<&str>) -> Result<Self> {
+        let stream = if host.contains(':') {
+            TcpStream::connect(host).await?
+        } else {
+            TcpStream::connect((host, PORT)).await?
+        };
+        let mut connection = Self {
+            stream: BufReader::new(stream),
+            auth: None,
+        };
+        let greeting = connection.read_line().await?;
+        if greeting == "PJLINK 0" {
+            return Ok(connection);
+        }
+        let Some(random) = greeting.strip_prefix("PJLINK 1 ") else {
+            return Err(format!("unexpected greeting {greeting}").into());
+        };
+        let pass = pass.ok_or_else(|| error::Error::auth("the projector requires a password"))?;
+        let digest = Md5::digest(format!("{random}{pass}"));
+        connection.auth = Some(digest.iter().fold(String::new(), |mut hex, byte| {
+            write!(hex, "{byte:02x}").unwrap();
+            hex
+        }));
+        Ok(connection)
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = Vec::new();
+        timeout(RESPONSE_TIMEOUT, self.stream.read_until(b'\r', &mut line))
+            .await
+            .map_err(|_| "no response from the projector")??;
+        if line.is_empty() {
+            return Err("connection closed by the projector".into());
+        }
+        let line = String::from_utf8_lossy(&line).trim().to_string();
+        debug!("Received {line}");
+        Ok(line)
+    }
+
+    /// Send a class 1 command, e.g. `POWR` with `?`, and return the value of the response.
+    async fn command(&mut self, command: &str, parameter: &str) -> Result<String> {
+        debug!("Sending {command} {parameter}");
+        let auth = self.auth.take().unwrap_or_default();
+        self.stream
+            .get_mut()
+            .write_all(format!("{auth}%1{command} {parameter}\r").as_bytes())
+            .await?;
+        let response = self.read_line().await?;
+        if response == "PJLINK ERRA" {
+            return Err(error::Error::auth("the projector does not accept the password").into());
+        }
+        let value = response
+            .strip_prefix(&format!("%1{command}="))
+            .ok_or_else(|| format!("unexpected response {response}"))?;
+        match value {
+            "ERR1" => Err(format!("{command} is not supported").into()),
+            "ERR2" => Err(format!("{command} {parameter}: invalid parameter").into()),
+            "ERR3" => Err(format!("{command} is unavailable at the moment").into()),
+            "ERR4" => Err("projector failure".into()),
+            _ => Ok(value.to_string()),
+        }
+    }
+
+    async fn power_status(&mut self) -> Result<PowerStatus> {
+        match self.command("POWR", "?").await?.as_str() {
+            "0" => Ok(PowerStatus::Off),
+            "1" => Ok(PowerStatus::On),
+            "2" => Ok(PowerStatus::CoolingDown),
+            "3" => Ok(PowerStatus::WarmingUp),
+            status => Err(format!("unknown power status {status}").into()),
+        }
+    }
+
+    /// Power the projector on or off. It does not accept commands while warming up or cooling
+    /// down, so this first waits for such a transition to finish.
+    async fn set_power(&mut self, on: bool) -> SinkCommandResult {
+        let (target, transition_to_target, transition_away) = if on {
+            (
+                PowerStatus::On,
+                PowerStatus::WarmingUp,
+                PowerStatus::CoolingDown,
+            )
+        } else {
+            (
+                PowerStatus::Off,
+                PowerStatus::CoolingDown,
+                PowerStatus::WarmingUp,
+            )
+        };
+        loop {
+            let status = self.power_status().await?;
+            if status == target || status == transition_to_target {
+                return Ok(());
+            }
+            if status != transition_away {
+                break;
+            }
+            debug!("Waiting for {status:?} to finish");
+            sleep(TRANSITION_POLL_INTERVAL).await;
+        }
+        let result = self.command("POWR", if on { "1" } else { "0" }).await?;
+        if result != "OK" {
+            return Err(format!("unexpected result {result}").into());
+        }
+        Ok(())
+    }
+}
+
+impl PjlinkSink {
+    async fn set_power(&self, on: bool) -> SinkCommandResult {
+        let mut connection = Connection::open(&self.settings.host, self.pass.as_deref()).await?;
+        connection.set_power(on).await
+    }
+}
+
+#[async_trait]
+impl Sink for PjlinkSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.set_power(true).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.set_power(false).await
+    }
+}
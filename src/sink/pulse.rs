@@ -0,0 +1,62 @@
+use crate::settings::{PulseOff, PulseSettings, SinkBaseSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::debug;
+
+/// Box the sink, wrapped in a [`PulseSink`] if it is set to pulse mode.
+pub fn wrap<S: Sink + 'static>(sink: S) -> Box<dyn Sink> {
+    match sink.base_settings().pulse.clone() {
+        Some(pulse) => Box::new(PulseSink {
+            inner: Arc::new(sink),
+            pulse,
+        }),
+        None => Box::new(sink),
+    }
+}
+
+/// A sink driven like a momentary contact: each command closes it by turning the inner sink on
+/// and opens it again by turning it off.
+pub struct PulseSink<S> {
+    inner: Arc<S>,
+    pulse: PulseSettings,
+}
+
+impl<S: Sink + 'static> PulseSink<S> {
+    async fn pulse(&self, duration: Duration) -> SinkCommandResult {
+        let inner = self.inner.clone();
+        // Pulsed in its own task, so that a timeout of the command can't leave the contact
+        // closed.
+        tokio::spawn(async move {
+            inner.on().await?;
+            debug!("Contact closed, opening in {} ms", duration.as_millis());
+            sleep(duration).await;
+            inner.off().await
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl<S: Sink + 'static> Sink for PulseSink<S> {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.inner.base_settings()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.pulse(Duration::from_millis(self.pulse.duration_ms))
+            .await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        match self.pulse.off {
+            PulseOff::Pulse => {
+                self.pulse(Duration::from_millis(self.pulse.duration_ms))
+                    .await
+            }
+            PulseOff::PulseMs(ms) => self.pulse(Duration::from_millis(ms)).await,
+            PulseOff::NoOp => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+#![cfg(feature = "sink-redfish")]
+
+use crate::error;
+use crate::http_client;
+use crate::settings::{PassSettings, SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use reqwest::{Client, RequestBuilder, Url};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use tracing::{debug, warn};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Auth {
+    /// HTTP basic auth on every request.
+    #[default]
+    Basic,
+    /// A session created for each command and deleted afterwards.
+    Session,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum OffResetType {
+    #[default]
+    GracefulShutdown,
+    ForceOff,
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "RedfishSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Base URL of the BMC, e.g. `https://bmc.local`.
+    pub url: String,
+    /// ID of the system in `/redfish/v1/Systems`. Defaults to the first system.
+    pub system_id: Option<String>,
+    pub user: String,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    #[serde(default)]
+    pub auth: Auth,
+    /// Accept invalid TLS certificates, such as the self-signed ones most BMCs ship with.
+    #[serde(default)]
+    pub insecure: bool,
+    /// The reset type used to turn the system off.
+    #[serde(default)]
+    pub off_reset_type: OffResetType,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+impl SinkSettings for Settings {
+    type Impl = RedfishSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> std::result::Result<Self::Impl, error::Error> {
+        RedfishSink::new(self.clone())
+    }
+}
+
+/// A server, powered on and off through the Redfish API of its BMC.
+pub struct RedfishSink {
+    settings: Settings,
+    base_url: Url,
+    pass: String,
+    client: Client,
+}
+
+/// Authentication for the requests of a single command.
+enum Credentials {
+    Basic,
+    Session { token: String, location: Url },
+}
+
+impl RedfishSink {
+    fn new(settings: Settings) -> std::result::Result<Self, error::Error> {
+        let base_url = Url::parse(&settings.url).map_err(error::Error::other)?;
+        let pass = settings
+            .pass
+            .resolve()?
+            .ok_or("a password is required for Redfish")?;
+        let mut builder = http_client::builder(&base_url)?;
+        if settings.insecure {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        let client = builder.build()?;
+        Ok(Self {
+            settings,
+            base_url,
+            pass,
+            client,
+        })
+    }
+
+    async fn login(&self) -> Result<Credentials> {
+        if self.settings.auth == Auth::Basic {
+            return Ok(Credentials::Basic);
+        }
+        let body = json!({"UserName": self.settings.user, "Password": self.pass});
+        let response = self
+            .client
+            .post(self.base_url.join("/redfish/v1/SessionService/Sessions")?)
+            .header("Content-Type", "application/json")
+            .body(body.to_string())
+            .send()
+            .await?
+            .error_for_status()?;
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .ok_or_else(|| format!("BMC did not return the {name} header of the session"))
+        };
+        Ok(Credentials::Session {
+            token: header("X-Auth-Token")?.to_string(),
+            location: self.base_url.join(header("Location")?)?,
+        })
+    }
+
+    async fn logout(&self, credentials: Credentials) {
+        if let Credentials::Session { location, .. } = &credentials {
+            let result = self.authorized(self.client.delete(location.clone()), &credentials);
+            if let Err(e) = result.send().await.and_then(|r| r.error_for_status()) {
+                warn!("Failed deleting Redfish session: {e}");
+            }
+        }
+    }
+
+    fn authorized(&self, request: RequestBuilder, credentials: &Credentials) -> RequestBuilder {
+        match credentials {
+            Credentials::Basic => request.basic_auth(&self.settings.user, Some(&self.pass)),
+            Credentials::Session { token, .. } => request.header("X-Auth-Token", token),
+        }
+    }
+
+    async fn get(&self, path: &str, credentials: &Credentials) -> Result<Value> {
+        let request = self.client.get(self.base_url.join(path)?);
+        let response = self.authorized(request, credentials).send().await?;
+        Ok(serde_json::from_str(
+            &response.error_for_status()?.text().await?,
+        )?)
+    }
+
+    async fn system_path(&self, credentials: &Credentials) -> Result<String> {
+        if let Some(id) = &self.settings.system_id {
+            return Ok(format!("/redfish/v1/Systems/{id}"));
+        }
+        let systems = self.get("/redfish/v1/Systems", credentials).await?;
+        systems["Members"][0]["@odata.id"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "BMC reports no systems".into())
+    }
+
+    async fn reset(&self, on: bool, credentials: &Credentials) -> SinkCommandResult {
+        let system_path = self.system_path(credentials).await?;
+        let system = self.get(&system_path, credentials).await?;
+        let power_state = system["PowerState"].as_str().unwrap_or_default();
+        debug!("Power state: {power_state}");
+        // Most BMCs refuse resets that would not change anything.
+        if power_state == if on { "On" } else { "Off" } {
+            return Ok(());
+        }
+        let reset_type = match (on, self.settings.off_reset_type) {
+            (true, _) => "On",
+            (false, OffResetType::GracefulShutdown) => "GracefulShutdown",
+            (false, OffResetType::ForceOff) => "ForceOff",
+        };
+        let target = system["Actions"]["#ComputerSystem.Reset"]["target"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{system_path}/Actions/ComputerSystem.Reset"));
+        let request = self
+            .client
+            .post(
+                self.base_url
+                    .join(&target)
+                    .map_err(error::Error::protocol)?,
+            )
+            .header("Content-Type", "application/json")
+            .body(json!({ "ResetType": reset_type }).to_string());
+        self.authorized(request, credentials)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn command(&self, on: bool) -> SinkCommandResult {
+        let credentials = self.login().await?;
+        let result = self.reset(on, &credentials).await;
+        self.logout(credentials).await;
+        result
+    }
+}
+
+#[async_trait]
+impl Sink for RedfishSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.command(true).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.command(false).await
+    }
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "sink-remote-pc")]
+
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{wol, Sink, SinkCommandResult};
+use crate::ssh::{self, SshSettings};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+const REACHABLE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Os {
+    #[default]
+    Linux,
+    /// Requires PsShutdown from the Sysinternals suite in the `PATH`.
+    Windows,
+}
+
+impl Os {
+    fn suspend_command(self) -> &'static str {
+        match self {
+            Os::Linux => "systemctl suspend",
+            Os::Windows => "psshutdown -d -t 0 -accepteula",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "RemotePcSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// MAC address of the PC, to wake it with Wake-on-LAN.
+    pub mac: String,
+    /// Where to send the Wake-on-LAN packet to.
+    #[serde(default = "default_wol_broadcast")]
+    pub wol_broadcast: String,
+    pub ssh: SshSettings,
+    #[serde(default)]
+    pub os: Os,
+    /// Run this via SSH to turn off, instead of suspending.
+    pub off_command: Option<String>,
+    /// After waking, wait until the SSH port accepts connections.
+    #[serde(default = "default_wait_until_reachable")]
+    pub wait_until_reachable: bool,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_wol_broadcast() -> String {
+    wol::DEFAULT_BROADCAST.to_string()
+}
+
+fn default_wait_until_reachable() -> bool {
+    true
+}
+
+impl SinkSettings for Settings {
+    type Impl = RemotePcSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(RemotePcSink {
+            magic_packet: wol::magic_packet(&self.mac)?,
+            ssh_pass: self.ssh.resolve_pass()?,
+            settings: self.clone(),
+        })
+    }
+}
+
+/// A PC, such as an HTPC, woken with Wake-on-LAN and suspended via SSH.
+pub struct RemotePcSink {
+    settings: Settings,
+    magic_packet: Vec<u8>,
+    ssh_pass: String,
+}
+
+#[async_trait]
+impl Sink for RemotePcSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        wol::send(&self.magic_packet, &self.settings.wol_broadcast).await?;
+        if !self.settings.wait_until_reachable {
+            return Ok(());
+        }
+        // Gives up with the timeout of the sink.
+        while let Err(e) = TcpStream::connect(&self.settings.ssh.host).await {
+            debug!("Not reachable yet: {e}");
+            tokio::time::sleep(REACHABLE_POLL_INTERVAL).await;
+        }
+        Ok(())
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        let command = match &self.settings.off_command {
+            Some(command) => command.clone(),
+            None => self.settings.os.suspend_command().to_string(),
+        };
+        // Also ends the SSH session if the command times out.
+        let cancel = CancellationToken::new();
+        let _cancel_guard = cancel.clone().drop_guard();
+        ssh::exec(&self.settings.ssh, &self.ssh_pass, &command, &cancel).await?;
+        Ok(())
+    }
+}
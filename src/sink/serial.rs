@@ -0,0 +1,141 @@
+#![cfg(feature = "sink-serial")]
+
+use crate::error;
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use serde::Deserialize;
+use std::error::Error;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_serial::SerialPortBuilderExt;
+use tracing::debug;
+
+/// Bytes to send or expect, either as text, e.g. `"%1POWR 1\r"`, or as `{ hex = "BE EF 03" }`.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(untagged)]
+pub enum Payload {
+    Text(String),
+    Hex { hex: String },
+}
+
+impl Payload {
+    fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+        match self {
+            Payload::Text(text) => Ok(text.as_bytes().to_vec()),
+            Payload::Hex { hex } => {
+                let digits: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+                if !digits.len().is_multiple_of(2) {
+                    return Err(format!("odd number of hex digits in \"{hex}\"").into());
+                }
+                (0..digits.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| format!("invalid hex payload \"{hex}\"").into())
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "SerialSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// The serial device, e.g. `/dev/ttyUSB0`.
+    pub path: String,
+    #[serde(default = "default_baud_rate")]
+    pub baud_rate: u32,
+    pub on_payload: Payload,
+    pub off_payload: Payload,
+    /// If set, the command only succeeds once the device answered with this.
+    pub on_response: Option<Payload>,
+    /// If set, the command only succeeds once the device answered with this.
+    pub off_response: Option<Payload>,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_baud_rate() -> u32 {
+    9600
+}
+
+impl SinkSettings for Settings {
+    type Impl = SerialSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, error::Error> {
+        let command = |payload: &Payload, response: &Option<Payload>| {
+            Ok::<_, Box<dyn Error + Send + Sync>>(Command {
+                payload: payload.to_bytes()?,
+                response: response.as_ref().map(Payload::to_bytes).transpose()?,
+            })
+        };
+        Ok(SerialSink {
+            on: command(&self.on_payload, &self.on_response)?,
+            off: command(&self.off_payload, &self.off_response)?,
+            settings: self.clone(),
+        })
+    }
+}
+
+struct Command {
+    payload: Vec<u8>,
+    response: Option<Vec<u8>>,
+}
+
+/// A device controlled with commands over a serial port, such as a projector.
+pub struct SerialSink {
+    settings: Settings,
+    on: Command,
+    off: Command,
+}
+
+impl SerialSink {
+    async fn send(&self, command: &Command) -> SinkCommandResult {
+        let mut port = tokio_serial::new(&self.settings.path, self.settings.baud_rate)
+            .open_native_async()
+            .map_err(error::Error::network)?;
+        port.write_all(&command.payload).await?;
+        port.flush().await?;
+        let Some(expected) = &command.response else {
+            return Ok(());
+        };
+        // Gives up with the timeout of the sink.
+        let mut received = Vec::new();
+        let mut buf = [0; 256];
+        while !received
+            .windows(expected.len())
+            .any(|window| window == expected)
+        {
+            let len = port.read(&mut buf).await?;
+            if len == 0 {
+                return Err("serial port closed".into());
+            }
+            received.extend_from_slice(&buf[..len]);
+            debug!("Received {:?}", String::from_utf8_lossy(&received));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Sink for SerialSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.send(&self.on).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.send(&self.off).await
+    }
+}
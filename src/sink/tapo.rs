@@ -0,0 +1,81 @@
+#![cfg(feature = "sink-tapo")]
+
+use crate::klap;
+use crate::settings::{PassSettings, SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "TapoSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    pub host: String,
+    /// Email address of the TP-Link account the plug is registered with.
+    pub user: String,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+impl SinkSettings for Settings {
+    type Impl = TapoSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        TapoSink::new(self.clone())
+    }
+}
+
+/// A TP-Link Tapo smart plug, such as the P100 or P110, with firmware using KLAP.
+pub struct TapoSink {
+    settings: Settings,
+    client: klap::Client,
+}
+
+impl TapoSink {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let pass = settings
+            .pass
+            .resolve()?
+            .ok_or("a password is required for Tapo")?;
+        let client = klap::Client::new(&settings.host, &settings.user, &pass)?;
+        Ok(Self { settings, client })
+    }
+
+    async fn set(&self, on: bool) -> SinkCommandResult {
+        let request = json!({
+            "method": "set_device_info",
+            "params": { "device_on": on },
+        });
+        let response = self.client.request(&request).await?;
+        match response["error_code"].as_i64() {
+            Some(0) => Ok(()),
+            Some(code) => Err(format!("error {code}").into()),
+            None => Err(format!("unexpected response: {response}").into()),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for TapoSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.set(true).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.set(false).await
+    }
+}
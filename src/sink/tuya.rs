@@ -0,0 +1,293 @@
+#![cfg(feature = "sink-tuya")]
+
+use crate::error;
+use crate::settings::{PassSettings, SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use aes::cipher::block_padding::{NoPadding, Pkcs7};
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyInit};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+use std::error::Error;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+
+const PORT: u16 = 6668;
+const PREFIX: u32 = 0x0000_55AA;
+const SUFFIX: u32 = 0x0000_AA55;
+const HEADER_LEN: usize = 16;
+/// Largest frame body that is read. Status updates are a few hundred bytes.
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+const CMD_SESS_KEY_NEG_START: u32 = 0x03;
+const CMD_SESS_KEY_NEG_RESP: u32 = 0x04;
+const CMD_SESS_KEY_NEG_FINISH: u32 = 0x05;
+const CMD_CONTROL: u32 = 0x07;
+const CMD_CONTROL_NEW: u32 = 0x0D;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+type Encryptor = ecb::Encryptor<aes::Aes128>;
+type Decryptor = ecb::Decryptor<aes::Aes128>;
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Version {
+    #[serde(rename = "3.3")]
+    V33,
+    #[serde(rename = "3.4")]
+    V34,
+}
+
+impl Version {
+    /// Header in front of the encrypted payload of commands.
+    fn header(self) -> [u8; 15] {
+        let mut header = [0; 15];
+        header[..3].copy_from_slice(match self {
+            Version::V33 => b"3.3",
+            Version::V34 => b"3.4",
+        });
+        header
+    }
+
+    /// Length of the checksum at the end of frames: a CRC32 or a HMAC-SHA256.
+    fn trailer_len(self) -> usize {
+        match self {
+            Version::V33 => 4,
+            Version::V34 => 32,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "TuyaSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the device.
+    pub host: String,
+    pub device_id: String,
+    /// The local key of the device, 16 characters, as `pass`, `pass-file` or `pass-env`.
+    #[serde(flatten)]
+    pub local_key: PassSettings,
+    pub version: Version,
+    /// The data point of the switch. Plugs with multiple sockets have one per socket.
+    #[serde(default = "default_dps")]
+    pub dps: u32,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_dps() -> u32 {
+    1
+}
+
+impl SinkSettings for Settings {
+    type Impl = TuyaSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> std::result::Result<Self::Impl, error::Error> {
+        let local_key = self
+            .local_key
+            .resolve()?
+            .ok_or("the local key is required")?;
+        let local_key: [u8; 16] = local_key
+            .as_bytes()
+            .try_into()
+            .map_err(|_| "the local key must be 16 characters long")?;
+        Ok(TuyaSink {
+            settings: self.clone(),
+            local_key,
+            seq: AtomicU32::new(1),
+        })
+    }
+}
+
+/// A Tuya or Smart Life device, switched with the local protocol without the cloud.
+pub struct TuyaSink {
+    settings: Settings,
+    local_key: [u8; 16],
+    seq: AtomicU32,
+}
+
+/// A connection to the device, with the key used for its messages.
+struct Connection<'a> {
+    sink: &'a TuyaSink,
+    stream: TcpStream,
+    key: [u8; 16],
+}
+
+impl<'a> Connection<'a> {
+    async fn open(sink: &'a TuyaSink) -> Result<Connection<'a>> {
+        let stream = TcpStream::connect((sink.settings.host.as_str(), PORT)).await?;
+        let mut connection = Self {
+            sink,
+            stream,
+            key: sink.local_key,
+        };
+        if sink.settings.version == Version::V34 {
+            connection.negotiate_session_key().await?;
+        }
+        Ok(connection)
+    }
+
+    /// Protocol 3.4 encrypts everything after the handshake with a key derived from a nonce
+    /// of each side.
+    async fn negotiate_session_key(&mut self) -> Result<()> {
+        let local_nonce: [u8; 16] = std::array::from_fn(|_| fastrand::u8(..));
+        let payload = encrypt::<Pkcs7>(&self.key, &local_nonce);
+        self.send(CMD_SESS_KEY_NEG_START, &payload).await?;
+        let (cmd, payload) = self.receive().await?;
+        if cmd != CMD_SESS_KEY_NEG_RESP {
+            return Err(format!("unexpected command {cmd:#x} during key negotiation").into());
+        }
+        let payload = decrypt(&self.key, &payload)?;
+        if payload.len() < 48 || payload[16..48] != hmac(&self.key, &local_nonce) {
+            let message = "device failed the key negotiation, is the local key correct?";
+            return Err(error::Error::auth(message).into());
+        }
+        let remote_nonce = &payload[..16];
+        let payload = encrypt::<Pkcs7>(&self.key, &hmac(&self.key, remote_nonce));
+        self.send(CMD_SESS_KEY_NEG_FINISH, &payload).await?;
+        let xor: Vec<u8> = local_nonce
+            .iter()
+            .zip(remote_nonce)
+            .map(|(a, b)| a ^ b)
+            .collect();
+        self.key = encrypt::<NoPadding>(&self.key, &xor)
+            .try_into()
+            .expect("AES blocks are 16 bytes");
+        Ok(())
+    }
+
+    async fn send(&mut self, cmd: u32, payload: &[u8]) -> Result<()> {
+        let version = self.sink.settings.version;
+        let trailer_len = version.trailer_len();
+        let mut frame = Vec::with_capacity(HEADER_LEN + payload.len() + trailer_len + 4);
+        frame.extend_from_slice(&PREFIX.to_be_bytes());
+        frame.extend_from_slice(&self.sink.seq.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+        frame.extend_from_slice(&cmd.to_be_bytes());
+        frame.extend_from_slice(&((payload.len() + trailer_len + 4) as u32).to_be_bytes());
+        frame.extend_from_slice(payload);
+        match version {
+            Version::V33 => frame.extend_from_slice(&crc32fast::hash(&frame).to_be_bytes()),
+            Version::V34 => frame.extend_from_slice(&hmac(&self.key, &frame)),
+        }
+        frame.extend_from_slice(&SUFFIX.to_be_bytes());
+        self.stream.write_all(&frame).await?;
+        Ok(())
+    }
+
+    /// Receive the next frame, returning its command and payload without the return code.
+    async fn receive(&mut self) -> Result<(u32, Vec<u8>)> {
+        let mut header = [0; HEADER_LEN];
+        self.stream.read_exact(&mut header).await?;
+        let field = |i: usize| u32::from_be_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+        if field(0) != PREFIX {
+            return Err("invalid frame from device".into());
+        }
+        let cmd = field(2);
+        let len = field(3) as usize;
+        if len > MAX_BODY_LEN {
+            let message = format!("frame of {len} bytes from device is too large");
+            return Err(error::Error::protocol(message).into());
+        }
+        let mut body = vec![0; len];
+        self.stream.read_exact(&mut body).await?;
+        let trailer_len = self.sink.settings.version.trailer_len();
+        let payload_len = body
+            .len()
+            .checked_sub(trailer_len + 4)
+            .ok_or("frame from device too short")?;
+        body.truncate(payload_len);
+        // Frames from the device start with a return code, which is non-zero on errors.
+        if body.len() >= 4 && body[..3] == [0, 0, 0] {
+            let return_code = body[3];
+            body.drain(..4);
+            if return_code != 0 {
+                return Err(format!("device returned error code {return_code}").into());
+            }
+        }
+        debug!("Received command {cmd:#x} with {} bytes", body.len());
+        Ok((cmd, body))
+    }
+
+    async fn set_power(&mut self, on: bool) -> Result<()> {
+        let settings = &self.sink.settings;
+        let t = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let dps = json!({ settings.dps.to_string(): on });
+        let (cmd, payload) = match settings.version {
+            Version::V33 => {
+                let data = json!({
+                    "devId": settings.device_id,
+                    "uid": settings.device_id,
+                    "t": t.to_string(),
+                    "dps": dps,
+                });
+                let encrypted = encrypt::<Pkcs7>(&self.key, data.to_string().as_bytes());
+                (
+                    CMD_CONTROL,
+                    [&settings.version.header()[..], &encrypted].concat(),
+                )
+            }
+            Version::V34 => {
+                let data = json!({"protocol": 5, "t": t, "data": {"dps": dps}});
+                let plain = [&settings.version.header()[..], data.to_string().as_bytes()].concat();
+                (CMD_CONTROL_NEW, encrypt::<Pkcs7>(&self.key, &plain))
+            }
+        };
+        self.send(cmd, &payload).await?;
+        // The device acknowledges with an empty frame of the same command, and may send
+        // status updates in between.
+        loop {
+            let (received, _) = self.receive().await?;
+            if received == cmd {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn encrypt<P: aes::cipher::block_padding::Padding<aes::cipher::typenum::U16>>(
+    key: &[u8; 16],
+    data: &[u8],
+) -> Vec<u8> {
+    Encryptor::new(key.into()).encrypt_padded_vec_mut::<P>(data)
+}
+
+fn decrypt(key: &[u8; 16], data: &[u8]) -> Result<Vec<u8>> {
+    Decryptor::new(key.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| "failed decrypting message from device".into())
+}
+
+fn hmac(key: &[u8; 16], data: &[u8]) -> Vec<u8> {
+    let mut mac = <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait]
+impl Sink for TuyaSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        Ok(Connection::open(self).await?.set_power(true).await?)
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        Ok(Connection::open(self).await?.set_power(false).await?)
+    }
+}
@@ -0,0 +1,206 @@
+#![cfg(feature = "sink-webos")]
+
+use crate::error;
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{wol, Sink, SinkCommandResult};
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+const REGISTER_ID: &str = "register_0";
+const TURN_OFF_ID: &str = "turn_off_1";
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "WebosSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the TV.
+    pub host: String,
+    /// MAC address of the TV, to wake it with Wake-on-LAN.
+    pub mac: String,
+    /// Where to send the Wake-on-LAN packet to.
+    #[serde(default = "default_wol_broadcast")]
+    pub wol_broadcast: String,
+    /// Use the encrypted WebSocket on port 3001, which newer firmware requires. The TV's
+    /// self-signed certificate is accepted.
+    #[serde(default)]
+    pub secure: bool,
+    /// The key the TV handed out when pairing. If neither this nor `client-key-file` is set,
+    /// the TV asks to pair on the first command and the key is logged.
+    pub client_key: Option<String>,
+    /// File to read the key from, and to store it in after pairing.
+    pub client_key_file: Option<PathBuf>,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_wol_broadcast() -> String {
+    wol::DEFAULT_BROADCAST.to_string()
+}
+
+impl SinkSettings for Settings {
+    type Impl = WebOsSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, error::Error> {
+        WebOsSink::new(self.clone())
+    }
+}
+
+/// An LG TV running webOS, turned on with Wake-on-LAN and off with its SSAP WebSocket API.
+pub struct WebOsSink {
+    settings: Settings,
+    magic_packet: Vec<u8>,
+    client_key: Mutex<Option<String>>,
+}
+
+impl WebOsSink {
+    fn new(settings: Settings) -> Result<Self, error::Error> {
+        let magic_packet = wol::magic_packet(&settings.mac)?;
+        let client_key = match (&settings.client_key, &settings.client_key_file) {
+            (Some(key), _) => Some(key.clone()),
+            (None, Some(path)) if path.exists() => Some(
+                std::fs::read_to_string(path)
+                    .map_err(|e| format!("failed reading client key file {}: {e}", path.display()))?
+                    .trim()
+                    .to_string(),
+            ),
+            _ => None,
+        };
+        Ok(Self {
+            settings,
+            magic_packet,
+            client_key: Mutex::new(client_key),
+        })
+    }
+
+    async fn connect(&self) -> Result<Socket, Box<dyn Error + Send + Sync>> {
+        let (url, connector) = if self.settings.secure {
+            let tls = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true)
+                .build()?;
+            (
+                format!("wss://{}:3001", self.settings.host),
+                Some(Connector::NativeTls(tls)),
+            )
+        } else {
+            (format!("ws://{}:3000", self.settings.host), None)
+        };
+        let (socket, _) =
+            tokio_tungstenite::connect_async_tls_with_config(url, None, false, connector).await?;
+        Ok(socket)
+    }
+
+    /// Wait for the next message with the ID and of one of the types, skipping others.
+    async fn receive(
+        socket: &mut Socket,
+        id: &str,
+        types: &[&str],
+    ) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        while let Some(message) = socket.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            debug!("Received {text}");
+            let message: Value = serde_json::from_str(&text)?;
+            if message["id"] != id {
+                continue;
+            }
+            match message["type"].as_str() {
+                Some("error") => {
+                    return Err(format!("TV returned an error: {}", message["error"]).into())
+                }
+                Some(t) if types.contains(&t) => return Ok(message),
+                _ => continue,
+            }
+        }
+        Err("connection closed by the TV".into())
+    }
+
+    async fn register(&self, socket: &mut Socket) -> SinkCommandResult {
+        let client_key = self.client_key.lock().unwrap().clone();
+        let mut payload = json!({
+            "forcePairing": false,
+            "pairingType": "PROMPT",
+            "manifest": {
+                "manifestVersion": 1,
+                "permissions": ["CONTROL_POWER"],
+            },
+        });
+        if let Some(key) = &client_key {
+            payload["client-key"] = json!(key);
+        } else {
+            info!("Pairing, accept the prompt on the TV.");
+        }
+        let register = json!({"type": "register", "id": REGISTER_ID, "payload": payload});
+        socket
+            .send(Message::Text(register.to_string()))
+            .await
+            .map_err(error::Error::network)?;
+        let registered = Self::receive(socket, REGISTER_ID, &["registered"]).await?;
+        let new_key = registered["payload"]["client-key"].as_str();
+        if let Some(new_key) = new_key.filter(|k| client_key.as_deref() != Some(*k)) {
+            self.store_client_key(new_key);
+        }
+        Ok(())
+    }
+
+    fn store_client_key(&self, key: &str) {
+        *self.client_key.lock().unwrap() = Some(key.to_string());
+        match &self.settings.client_key_file {
+            Some(path) => match std::fs::write(path, key) {
+                Ok(()) => info!("Paired, stored the client key in {}.", path.display()),
+                Err(e) => warn!(
+                    "Paired, but failed storing the client key in {}: {e}. Client key: {key}",
+                    path.display()
+                ),
+            },
+            None => info!("Paired, set client-key = \"{key}\" in the config to keep it."),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for WebOsSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        wol::send(&self.magic_packet, &self.settings.wol_broadcast).await?;
+        Ok(())
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        let mut socket = self.connect().await?;
+        self.register(&mut socket).await?;
+        let turn_off =
+            json!({"type": "request", "id": TURN_OFF_ID, "uri": "ssap://system/turnOff"});
+        socket
+            .send(Message::Text(turn_off.to_string()))
+            .await
+            .map_err(error::Error::network)?;
+        let response = Self::receive(&mut socket, TURN_OFF_ID, &["response"]).await?;
+        socket.close(None).await.ok();
+        if response["payload"]["returnValue"] != true {
+            return Err(format!("TV did not turn off: {}", response["payload"]).into());
+        }
+        Ok(())
+    }
+}
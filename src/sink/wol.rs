@@ -0,0 +1,30 @@
+#![cfg(any(feature = "sink-remote-pc", feature = "sink-webos"))]
+
+use std::error::Error;
+use std::io;
+use tokio::net::UdpSocket;
+
+/// Default target of Wake-on-LAN magic packets.
+pub const DEFAULT_BROADCAST: &str = "255.255.255.255:9";
+
+/// Build the magic packet waking the device with this MAC address.
+pub fn magic_packet(mac: &str) -> Result<Vec<u8>, Box<dyn Error + Send + Sync>> {
+    let mac = mac
+        .split([':', '-'])
+        .map(|octet| u8::from_str_radix(octet, 16))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()
+        .filter(|mac| mac.len() == 6)
+        .ok_or_else(|| format!("invalid MAC address \"{mac}\""))?;
+    Ok([0xFF; 6]
+        .into_iter()
+        .chain(mac.iter().copied().cycle().take(6 * 16))
+        .collect())
+}
+
+pub async fn send(magic_packet: &[u8], broadcast: &str) -> io::Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.set_broadcast(true)?;
+    socket.send_to(magic_packet, broadcast).await?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+#![cfg(feature = "sink-zigbee2mqtt")]
+
+use crate::mqtt::{BrokerSettings, MqttClient};
+use crate::settings::{SinkBaseSettings, SinkSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::debug;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "Zigbee2mqttSinkSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    pub mqtt: BrokerSettings,
+    /// Base topic Zigbee2MQTT is configured with.
+    #[serde(default = "default_base_topic")]
+    pub base_topic: String,
+    /// Friendly name of the device in Zigbee2MQTT.
+    pub friendly_name: String,
+    /// Wait until the device reports the new state on its state topic.
+    #[serde(default)]
+    pub confirm: bool,
+    #[serde(flatten)]
+    base: SinkBaseSettings,
+}
+
+fn default_base_topic() -> String {
+    "zigbee2mqtt".to_string()
+}
+
+impl SinkSettings for Settings {
+    type Impl = Zigbee2MqttSink;
+
+    fn base(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    fn create_sink(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(Zigbee2MqttSink {
+            state_topic: format!("{}/{}", self.base_topic, self.friendly_name),
+            client: MqttClient::connect(&self.mqtt)?,
+            settings: self.clone(),
+        })
+    }
+}
+
+/// A device paired with Zigbee2MQTT, such as a smart plug.
+pub struct Zigbee2MqttSink {
+    settings: Settings,
+    state_topic: String,
+    client: Arc<MqttClient>,
+}
+
+impl Zigbee2MqttSink {
+    async fn set_state(&self, on: bool) -> SinkCommandResult {
+        let state = if on { "ON" } else { "OFF" };
+        // Subscribe before publishing, to not miss the confirmation.
+        let mut messages = if self.settings.confirm {
+            Some(self.client.subscribe(&self.state_topic).await?)
+        } else {
+            None
+        };
+        let payload = json!({ "state": state }).to_string();
+        self.client
+            .publish(&format!("{}/set", self.state_topic), payload)
+            .await?;
+        let Some(messages) = &mut messages else {
+            return Ok(());
+        };
+        // Gives up with the timeout of the sink.
+        loop {
+            let message = match messages.recv().await {
+                Ok(v) => v,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return Err("MQTT connection closed".into()),
+            };
+            if message.topic != self.state_topic {
+                continue;
+            }
+            let reported: Value = serde_json::from_slice(&message.payload)?;
+            debug!("Reported state: {}", reported["state"]);
+            if reported["state"] == state {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for Zigbee2MqttSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        self.settings.base()
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.set_state(true).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.set_state(false).await
+    }
+}
@@ -1,56 +1,426 @@
 use crate::identity::Named;
+#[allow(unused_imports)]
+use crate::registry::registration;
+use crate::registry::{Registered, Registration};
 use crate::settings::{MapOfSourceSettings, SourceBaseSettings, SourceSettings};
 use crate::state::State;
-use std::error::Error;
-use std::iter::empty;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::future::pending;
+use std::sync::{Arc, RwLock};
+use tokio_util::sync::CancellationToken;
 use tracing::{error, info};
 
+#[cfg(feature = "source-androidtv")]
+pub mod androidtv;
+#[cfg(feature = "source-appletv")]
+pub mod appletv;
+#[cfg(all(feature = "source-bluetooth", target_os = "linux"))]
+pub mod bluetooth;
+#[cfg(feature = "source-cec")]
+pub mod cec;
+#[cfg(feature = "source-composite")]
+pub mod composite;
+#[cfg(feature = "source-cpu-load")]
+pub mod cpu_load;
+#[cfg(all(feature = "source-file", target_os = "linux"))]
+pub mod file;
+#[cfg(feature = "source-frigate")]
+pub mod frigate;
+#[cfg(feature = "source-game-server")]
+pub mod game_server;
+#[cfg(feature = "source-gpu")]
+pub mod gpu;
 #[cfg(feature = "source-kodi")]
 pub mod kodi;
+#[cfg(all(feature = "source-logind", target_os = "linux"))]
+pub mod logind;
+#[cfg(feature = "source-net-presence")]
+pub mod net_presence;
+#[cfg(feature = "source-pdu-outlet")]
+pub mod pdu_outlet;
+#[cfg(feature = "source-playstation")]
+pub mod playstation;
+#[cfg(all(feature = "source-process", target_os = "linux"))]
+pub mod process;
+#[cfg(feature = "source-schedule")]
+pub mod schedule;
+#[cfg(feature = "source-solar")]
+pub mod solar;
 #[cfg(feature = "source-steamlink")]
 pub mod steamlink;
+mod threshold;
+mod udp_probe;
+#[cfg(feature = "source-ups")]
+pub mod ups;
+#[cfg(feature = "source-webhook")]
+pub mod webhook;
+#[cfg(feature = "source-xbox")]
+pub mod xbox;
 
-pub type SourceIsActiveResult = Result<bool, Box<dyn Error>>;
+pub type SourceIsActiveResult = Result<bool, crate::error::Error>;
+pub type CreateSourceResult = Result<Box<dyn Source>, crate::error::Error>;
+
+/// Why a state pushed to a source was rejected.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PushError {
+    /// There is no source with the name.
+    NotFound,
+    /// The source is polled and does not accept pushes.
+    NotAccepted,
+    /// The secret was wrong.
+    Unauthorized,
+}
 
 #[async_trait]
 /// A device which power state should be monitored on whether it is active or not.
-pub trait Source {
+pub trait Source: Send + Sync {
     /// Base settings.
     fn base_settings(&self) -> &SourceBaseSettings;
-    /// Check if the source is active.
-    async fn is_active(&self) -> SourceIsActiveResult;
+    /// Check if the source is active. The future is dropped if the poll times out, but `cancel`
+    /// is only cancelled once the poll is over, so work that would continue in the background,
+    /// such as blocking calls, can observe it to abort.
+    async fn is_active(&self, cancel: &CancellationToken) -> SourceIsActiveResult;
+    /// Wait until the source may have changed, so that it is checked before its next poll is
+    /// due. Never completes for sources that can only be polled.
+    async fn wait_for_change(&self) {
+        pending().await
+    }
+    /// Receive a state pushed via the HTTP API, for sources that can't be polled.
+    fn push(&self, _secret: &str, _active: bool) -> Result<(), PushError> {
+        Err(PushError::NotAccepted)
+    }
+    /// Replace the source with a freshly created instance, to recover from it being stuck.
+    fn recreate(&self) -> Result<(), crate::error::Error> {
+        Err("the source can not be re-created".into())
+    }
+    /// Names of the sources this source combines. Such a source is not polled, but evaluated
+    /// with [`Source::combine`] whenever one of them changes.
+    fn combines(&self) -> &[String] {
+        &[]
+    }
+    /// Whether the source is on given the states of the sources it combines, `None` while that
+    /// is unknown.
+    fn combine(&self, _active: &HashMap<&str, Option<bool>>) -> Option<bool> {
+        None
+    }
 }
 
-pub async fn create_sources(
-    source_config: &MapOfSourceSettings,
-    state: &mut State,
-) -> Result<(), Box<dyn Error>> {
-    let all = empty();
+/// A source that keeps its settings, so that it can be re-created from them.
+struct RecreatableSource<S: SourceSettings> {
+    settings: S,
+    source: RwLock<Arc<S::Impl>>,
+}
+
+#[async_trait]
+impl<S> Source for RecreatableSource<S>
+where
+    S: SourceSettings + Send + Sync,
+    S::Impl: 'static,
+{
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, cancel: &CancellationToken) -> SourceIsActiveResult {
+        let source = self.source.read().unwrap().clone();
+        source.is_active(cancel).await
+    }
+
+    async fn wait_for_change(&self) {
+        let source = self.source.read().unwrap().clone();
+        source.wait_for_change().await
+    }
+
+    fn push(&self, secret: &str, active: bool) -> Result<(), PushError> {
+        self.source.read().unwrap().push(secret, active)
+    }
+
+    fn recreate(&self) -> Result<(), crate::error::Error> {
+        let source = self.settings.create_source()?;
+        *self.source.write().unwrap() = Arc::new(source);
+        Ok(())
+    }
+}
+
+/// The source types of the enabled features.
+const TYPES: &[Registration<dyn AnySourceSettings>] = &[
+    #[cfg(feature = "source-androidtv")]
+    registration!(
+        "androidtv",
+        "source-androidtv",
+        androidtv::Settings,
+        ["apps", "host", "key"]
+    ),
+    #[cfg(feature = "source-appletv")]
+    registration!(
+        "appletv",
+        "source-appletv",
+        appletv::Settings,
+        [
+            "airplay-credentials",
+            "atvscript",
+            "companion-credentials",
+            "host",
+            "mrp-credentials",
+            "paused-is-active"
+        ]
+    ),
+    #[cfg(all(feature = "source-bluetooth", target_os = "linux"))]
+    registration!(
+        "bluetooth",
+        "source-bluetooth",
+        bluetooth::Settings,
+        ["adapter", "address", "l2ping"]
+    ),
+    #[cfg(feature = "source-cec")]
+    registration!("cec", "source-cec", cec::Settings, ["address", "port"]),
+    #[cfg(feature = "source-composite")]
+    registration!(
+        "composite",
+        "source-composite",
+        composite::Settings,
+        ["expression"]
+    ),
+    #[cfg(feature = "source-cpu-load")]
+    registration!(
+        "cpu-load",
+        "source-cpu-load",
+        cpu_load::Settings,
+        ["metric", "off-below", "on-above", "ssh"]
+    ),
+    #[cfg(all(feature = "source-file", target_os = "linux"))]
+    registration!("file", "source-file", file::Settings, ["path"]),
+    #[cfg(feature = "source-frigate")]
+    registration!(
+        "frigate",
+        "source-frigate",
+        frigate::Settings,
+        [
+            "cameras",
+            "labels",
+            "mqtt",
+            "recent-sec",
+            "topic-prefix",
+            "url",
+            "zones"
+        ]
+    ),
+    #[cfg(feature = "source-game-server")]
+    registration!(
+        "game-server",
+        "source-game-server",
+        game_server::Settings,
+        ["host", "min-players", "port", "protocol"]
+    ),
+    #[cfg(feature = "source-gpu")]
+    registration!(
+        "gpu",
+        "source-gpu",
+        gpu::Settings,
+        ["backend", "device", "off-below", "on-above"]
+    ),
     #[cfg(feature = "source-kodi")]
-    let all = all.chain(create_of_type(&source_config.kodi));
+    registration!(
+        "kodi",
+        "source-kodi",
+        kodi::Settings,
+        [
+            "connect-timeout-sec",
+            "idle-threshold-sec",
+            "insecure",
+            "jsonrpc",
+            "no-screensaver-is-active",
+            "pass",
+            "pass-env",
+            "pass-file",
+            "paused-is-active",
+            "user"
+        ]
+    ),
+    #[cfg(all(feature = "source-logind", target_os = "linux"))]
+    registration!(
+        "logind",
+        "source-logind",
+        logind::Settings,
+        ["graphical-only"]
+    ),
+    #[cfg(feature = "source-net-presence")]
+    registration!(
+        "net-presence",
+        "source-net-presence",
+        net_presence::Settings,
+        ["address", "grace-sec", "probe"]
+    ),
+    #[cfg(feature = "source-pdu-outlet")]
+    registration!(
+        "pdu-outlet",
+        "source-pdu-outlet",
+        pdu_outlet::Settings,
+        ["coil", "current", "host", "port", "unit-id"]
+    ),
+    #[cfg(feature = "source-playstation")]
+    registration!(
+        "playstation",
+        "source-playstation",
+        playstation::Settings,
+        ["host", "model"]
+    ),
+    #[cfg(all(feature = "source-process", target_os = "linux"))]
+    registration!(
+        "process",
+        "source-process",
+        process::Settings,
+        ["cmdline", "process-name"]
+    ),
+    #[cfg(feature = "source-schedule")]
+    registration!(
+        "schedule",
+        "source-schedule",
+        schedule::Settings,
+        ["cron", "timezone", "windows"]
+    ),
+    #[cfg(feature = "source-solar")]
+    registration!(
+        "solar",
+        "source-solar",
+        solar::Settings,
+        [
+            "dawn-offset-min",
+            "dusk-offset-min",
+            "latitude",
+            "longitude",
+            "twilight"
+        ]
+    ),
     #[cfg(feature = "source-steamlink")]
-    let all = all.chain(create_of_type(&source_config.steamlink));
+    registration!(
+        "steamlink",
+        "source-steamlink",
+        steamlink::Settings,
+        ["host", "pass", "pass-env", "pass-file", "user"]
+    ),
+    #[cfg(feature = "source-ups")]
+    registration!(
+        "ups",
+        "source-ups",
+        ups::Settings,
+        ["active-on", "host", "port", "protocol", "ups"]
+    ),
+    #[cfg(feature = "source-webhook")]
+    registration!(
+        "webhook",
+        "source-webhook",
+        webhook::Settings,
+        ["expire-after-sec", "pass", "pass-env", "pass-file"]
+    ),
+    #[cfg(feature = "source-xbox")]
+    registration!("xbox", "source-xbox", xbox::Settings, ["host"]),
+];
 
-    state.try_register_sources(all).await
+/// Settings of a source of any type.
+pub trait AnySourceSettings: Debug + Send + Sync {
+    fn base(&self) -> &SourceBaseSettings;
+    fn create(&self) -> CreateSourceResult;
+    /// Whether the source combines other sources, which can't be combined themselves.
+    fn is_group(&self) -> bool {
+        false
+    }
+    /// Names of the sources a composite source combines.
+    fn members(&self) -> Vec<&str> {
+        Vec::new()
+    }
+    fn clone_box(&self) -> Box<dyn AnySourceSettings>;
 }
 
-fn create_of_type<'a, S>(
-    source_configs: &'a [S],
-) -> impl Iterator<Item = Result<Box<dyn Source>, Box<dyn Error>>> + 'a
+impl<S> AnySourceSettings for S
 where
-    S: SourceSettings + 'a,
+    S: SourceSettings + Clone + Debug + Send + Sync + 'static,
     S::Impl: 'static,
 {
-    source_configs
+    fn base(&self) -> &SourceBaseSettings {
+        SourceSettings::base(self)
+    }
+
+    fn create(&self) -> CreateSourceResult {
+        let source = self.create_source()?;
+        Ok(Box::new(RecreatableSource {
+            settings: self.clone(),
+            source: RwLock::new(Arc::new(source)),
+        }))
+    }
+
+    fn clone_box(&self) -> Box<dyn AnySourceSettings> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn AnySourceSettings> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Registered for dyn AnySourceSettings {
+    const SECTION: &'static str = "source";
+
+    const BASE_FIELDS: &'static [&'static str] = &[
+        "enable",
+        "linger-sec",
+        "name",
+        "on-error",
+        "poll-interval-sec",
+        "sleepy",
+        "timeout-sec",
+        "watchdog",
+    ];
+
+    fn types() -> &'static [Registration<Self>] {
+        TYPES
+    }
+}
+
+pub async fn create_sources(
+    source_config: &MapOfSourceSettings,
+    state: &mut State,
+) -> Result<(), crate::error::Error> {
+    let all = try_create_all(source_config).map(|(base, result)| {
+        result.map_err(|e| {
+            error!("{} Failed creating source: {}", base.identity(), &e);
+            e.of(base.identity().clone_owned())
+        })
+    });
+
+    state.try_register_sources(all).await
+}
+
+/// Try to create all enabled sources, returning the base settings of each source alongside the
+/// result of creating it.
+pub fn try_create_all(
+    source_config: &MapOfSourceSettings,
+) -> impl Iterator<Item = (&SourceBaseSettings, CreateSourceResult)> {
+    create_where(source_config, |base| base.enable)
+}
+
+/// Try to create the source with the given name, even if it is disabled. Returns `None` if no
+/// source with this name is configured.
+pub fn try_create_named<'a>(
+    source_config: &'a MapOfSourceSettings,
+    name: &'a str,
+) -> Option<(&'a SourceBaseSettings, CreateSourceResult)> {
+    create_where(source_config, move |base| base.name == name).next()
+}
+
+/// Create the sources matching the filter.
+fn create_where<'a>(
+    source_config: &'a MapOfSourceSettings,
+    filter: impl Fn(&SourceBaseSettings) -> bool + 'a,
+) -> impl Iterator<Item = (&'a SourceBaseSettings, CreateSourceResult)> + 'a {
+    source_config
         .iter()
-        .filter(|cfg| cfg.base().enable)
+        .filter(move |cfg| filter(cfg.base()))
         .map(|cfg| {
             info!("{} Initializing...", cfg.base().identity());
-            cfg.create_source()
-                .map(|x| Box::new(x) as Box<dyn Source>)
-                .map_err(|e| {
-                    error!("{} Failed creating source: {}", cfg.base().identity(), &e);
-                    e
-                })
+            (cfg.base(), cfg.create())
         })
 }
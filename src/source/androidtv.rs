@@ -0,0 +1,95 @@
+#![cfg(feature = "source-androidtv")]
+
+use crate::adb::{Connection, Key};
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::path::PathBuf;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// Prints the wakefulness and the activities in the foreground.
+const STATE_COMMAND: &str =
+    "dumpsys power | grep mWakefulness=; dumpsys activity activities | grep ResumedActivity";
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "AndroidTvSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the device with network debugging enabled, optionally with
+    /// the port.
+    pub host: String,
+    /// Private key generated with `adb keygen`, with the public key next to it. It has to be
+    /// allowed on the device once.
+    pub key: PathBuf,
+    /// If set, only active while one of these apps is in the foreground, by package name such
+    /// as `com.netflix.ninja`.
+    pub apps: Option<Vec<String>>,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = AndroidTvSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(AndroidTvSource {
+            key: Key::read(&self.key)?,
+            settings: self.clone(),
+        })
+    }
+}
+
+/// An Android TV device such as a Shield, active while it is awake, checked over ADB.
+pub struct AndroidTvSource {
+    settings: Settings,
+    key: Key,
+}
+
+/// The package of the activity in a line like
+/// `mResumedActivity: ActivityRecord{1a2b3c u0 com.netflix.ninja/.MainActivity t42}`.
+fn package_of(line: &str) -> Option<&str> {
+    line.split_whitespace()
+        .find_map(|word| word.split_once('/'))
+        .map(|(package, _)| package)
+}
+
+#[async_trait]
+impl Source for AndroidTvSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let mut connection = Connection::connect(&self.settings.host, &self.key).await?;
+        let output = connection.shell(STATE_COMMAND).await?;
+        let wakefulness = output
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("mWakefulness="))
+            .ok_or("no wakefulness in the output of dumpsys")?;
+        debug!("Wakefulness: {wakefulness}");
+        if wakefulness != "Awake" {
+            return Ok(false);
+        }
+        let Some(apps) = &self.settings.apps else {
+            return Ok(true);
+        };
+        let foreground: Vec<_> = output
+            .lines()
+            .filter(|line| line.contains("ResumedActivity"))
+            .filter_map(package_of)
+            .collect();
+        debug!("Apps in the foreground: {foreground:?}");
+        Ok(foreground
+            .iter()
+            .any(|package| apps.iter().any(|app| app == package)))
+    }
+}
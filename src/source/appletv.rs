@@ -0,0 +1,109 @@
+#![cfg(feature = "source-appletv")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "AppleTvSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// IP address of the Apple TV.
+    pub host: String,
+    /// Credentials from pairing with `atvremote --protocol mrp pair`.
+    pub mrp_credentials: Option<String>,
+    /// Credentials from pairing with `atvremote --protocol companion pair`.
+    pub companion_credentials: Option<String>,
+    /// Credentials from pairing with `atvremote --protocol airplay pair`.
+    pub airplay_credentials: Option<String>,
+    /// Whether paused playback counts as active.
+    #[serde(default)]
+    pub paused_is_active: bool,
+    /// The `atvscript` executable of pyatv.
+    #[serde(default = "default_atvscript")]
+    pub atvscript: String,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+fn default_atvscript() -> String {
+    "atvscript".to_string()
+}
+
+impl SourceSettings for Settings {
+    type Impl = AppleTvSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(AppleTvSource {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// An Apple TV, active during playback including AirPlay, checked with pyatv's `atvscript`,
+/// which speaks its local protocols.
+pub struct AppleTvSource {
+    settings: Settings,
+}
+
+/// The relevant part of the output of `atvscript playing`.
+#[derive(Deserialize)]
+struct Playing {
+    result: String,
+    error: Option<String>,
+    device_state: Option<String>,
+}
+
+#[async_trait]
+impl Source for AppleTvSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let settings = &self.settings;
+        let mut command = Command::new(&settings.atvscript);
+        command.args(["--scan-hosts", &settings.host]);
+        let credentials = [
+            ("--mrp-credentials", &settings.mrp_credentials),
+            ("--companion-credentials", &settings.companion_credentials),
+            ("--airplay-credentials", &settings.airplay_credentials),
+        ];
+        for (flag, value) in credentials {
+            if let Some(value) = value {
+                command.args([flag, value]);
+            }
+        }
+        let output = command
+            .arg("playing")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .output()
+            .await
+            .map_err(|e| format!("failed running {}: {e}", settings.atvscript))?;
+        let playing: Playing = serde_json::from_slice(&output.stdout)?;
+        if playing.result != "success" {
+            return Err(playing.error.unwrap_or(playing.result).into());
+        }
+        let state = playing.device_state.unwrap_or_default();
+        debug!("Device state: {state}");
+        Ok(match state.as_str() {
+            "playing" | "loading" | "seeking" | "fast_forward" | "rewind" => true,
+            "paused" => settings.paused_is_active,
+            _ => false,
+        })
+    }
+}
@@ -0,0 +1,128 @@
+#![cfg(all(feature = "source-bluetooth", target_os = "linux"))]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::process::Stdio;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+use zbus::{Connection, Proxy};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "BluetoothSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// MAC address of the device.
+    pub address: String,
+    /// The Bluetooth adapter the device is paired with.
+    #[serde(default = "default_adapter")]
+    pub adapter: String,
+    /// Whether to also count the device as active if it answers an `l2ping` while not connected.
+    /// Requires the `l2ping` binary and usually root privileges.
+    #[serde(default)]
+    pub l2ping: bool,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+fn default_adapter() -> String {
+    "hci0".to_string()
+}
+
+impl SourceSettings for Settings {
+    type Impl = BluetoothSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        BluetoothSource::new(self.clone())
+    }
+}
+
+/// Active while the device is connected according to BlueZ, or optionally answers an `l2ping`.
+pub struct BluetoothSource {
+    settings: Settings,
+    device_path: String,
+    connection: OnceCell<Connection>,
+}
+
+impl BluetoothSource {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let octets = settings.address.split(':').collect::<Vec<_>>();
+        if octets.len() != 6
+            || octets
+                .iter()
+                .any(|o| o.len() != 2 || u8::from_str_radix(o, 16).is_err())
+        {
+            return Err(format!("invalid Bluetooth address \"{}\"", settings.address).into());
+        }
+        let device_path = format!(
+            "/org/bluez/{}/dev_{}",
+            settings.adapter,
+            octets.join("_").to_uppercase()
+        );
+        Ok(Self {
+            settings,
+            device_path,
+            connection: OnceCell::new(),
+        })
+    }
+
+    async fn is_connected(&self) -> zbus::Result<bool> {
+        let connection = self.connection.get_or_try_init(Connection::system).await?;
+        let proxy = Proxy::new(
+            connection,
+            "org.bluez",
+            self.device_path.as_str(),
+            "org.bluez.Device1",
+        )
+        .await?;
+        proxy.get_property("Connected").await
+    }
+
+    async fn answers_l2ping(&self) -> std::io::Result<bool> {
+        // Leave time for the poll timeout to not trigger if the device does not answer.
+        let wait = (self.settings.base.timeout_sec / 2).max(1);
+        let status = Command::new("l2ping")
+            .args(["-c", "1", "-t", &wait.to_string(), &self.settings.address])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .status()
+            .await?;
+        Ok(status.success())
+    }
+}
+
+#[async_trait]
+impl Source for BluetoothSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let connected = match self.is_connected().await {
+            Ok(v) => v,
+            // BlueZ only knows devices that were paired or seen in a scan.
+            Err(zbus::Error::MethodError(name, _, _))
+                if name.as_str() == "org.freedesktop.DBus.Error.UnknownObject" =>
+            {
+                debug!("Device unknown to BlueZ.");
+                false
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if connected || !self.settings.l2ping {
+            return Ok(connected);
+        }
+        Ok(self.answers_l2ping().await?)
+    }
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "source-cec")]
+
+use crate::cec::{CecAdapter, LogicalAddress};
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use cec_rs::CecPowerStatus;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "CecSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// The libcec port of the CEC adapter, e.g. `RPI` or `/dev/ttyACM0`.
+    pub port: String,
+    /// The device to ask for its power status.
+    #[serde(default)]
+    pub address: LogicalAddress,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = CecSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(CecSource {
+            settings: self.clone(),
+            adapter: CecAdapter::open(&self.port)?,
+        })
+    }
+}
+
+/// Active while the device on the CEC bus reports being on, or turning on.
+pub struct CecSource {
+    settings: Settings,
+    adapter: Arc<CecAdapter>,
+}
+
+#[async_trait]
+impl Source for CecSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let status = self.adapter.power_status(self.settings.address).await?;
+        debug!("Power status: {status:?}");
+        match status {
+            CecPowerStatus::On | CecPowerStatus::InTransitionStandbyToOn => Ok(true),
+            CecPowerStatus::Standby | CecPowerStatus::InTransitionOnToStandby => Ok(false),
+            CecPowerStatus::Unknown => Err("the device did not report its power status".into()),
+        }
+    }
+}
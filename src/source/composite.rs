@@ -0,0 +1,157 @@
+#![cfg(feature = "source-composite")]
+
+use crate::settings::SourceBaseSettings;
+use crate::source::{AnySourceSettings, CreateSourceResult, Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "CompositeSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// When the source is on, in terms of other sources, e.g.
+    /// `{ all = [{ source = "Kodi" }, { not = { source = "Daylight" } }] }`. The sources must be
+    /// enabled, and can't be composite themselves.
+    pub expression: Expression,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+/// A combination of the states of other sources.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Expression {
+    /// On while the source with the name is on.
+    Source(String),
+    /// On while all of the expressions are on.
+    All(Vec<Expression>),
+    /// On while any of the expressions is on.
+    Any(Vec<Expression>),
+    /// On while the expression is off.
+    Not(Box<Expression>),
+}
+
+impl Expression {
+    /// Names of the sources in the expression, each once.
+    fn sources(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_sources(&mut names);
+        names
+    }
+
+    fn collect_sources<'a>(&'a self, names: &mut Vec<&'a str>) {
+        match self {
+            Expression::Source(name) if !names.contains(&name.as_str()) => names.push(name),
+            Expression::Source(_) => {}
+            Expression::All(all) | Expression::Any(all) => {
+                all.iter().for_each(|e| e.collect_sources(names))
+            }
+            Expression::Not(expression) => expression.collect_sources(names),
+        }
+    }
+
+    /// Whether the expression is on, `None` if that depends on sources whose state is unknown.
+    fn evaluate(&self, active: &HashMap<&str, Option<bool>>) -> Option<bool> {
+        match self {
+            Expression::Source(name) => active.get(name.as_str()).copied().flatten(),
+            // A single expression that is off decides, even if others are unknown.
+            Expression::All(all) => Self::decide(all, active, false),
+            Expression::Any(any) => Self::decide(any, active, true),
+            Expression::Not(expression) => expression.evaluate(active).map(|active| !active),
+        }
+    }
+
+    /// `decisive` if any of the expressions is, otherwise the opposite, unless any of them is
+    /// unknown.
+    fn decide(
+        expressions: &[Expression],
+        active: &HashMap<&str, Option<bool>>,
+        decisive: bool,
+    ) -> Option<bool> {
+        let mut result = Some(!decisive);
+        for expression in expressions {
+            match expression.evaluate(active) {
+                Some(value) if value == decisive => return Some(decisive),
+                Some(_) => {}
+                None => result = None,
+            }
+        }
+        result
+    }
+}
+
+impl Settings {
+    pub fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    pub fn create_source(&self) -> Result<CompositeSource, crate::error::Error> {
+        let sources: Vec<_> = self
+            .expression
+            .sources()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        if sources.is_empty() {
+            return Err("the expression needs at least one source".into());
+        }
+        Ok(CompositeSource {
+            settings: self.clone(),
+            sources,
+        })
+    }
+}
+
+impl AnySourceSettings for Settings {
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create(&self) -> CreateSourceResult {
+        Ok(Box::new(self.create_source()?))
+    }
+
+    fn is_group(&self) -> bool {
+        true
+    }
+
+    fn members(&self) -> Vec<&str> {
+        self.expression.sources()
+    }
+
+    fn clone_box(&self) -> Box<dyn AnySourceSettings> {
+        Box::new(self.clone())
+    }
+}
+
+/// On according to an expression over the states of other sources. It is not polled, but
+/// evaluated from the states the other sources were last polled with.
+pub struct CompositeSource {
+    settings: Settings,
+    sources: Vec<String>,
+}
+
+#[async_trait]
+impl Source for CompositeSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        Err("a composite source is evaluated from the states of its sources while running".into())
+    }
+
+    fn combines(&self) -> &[String] {
+        &self.sources
+    }
+
+    fn combine(&self, active: &HashMap<&str, Option<bool>>) -> Option<bool> {
+        self.settings.expression.evaluate(active)
+    }
+}
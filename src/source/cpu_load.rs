@@ -0,0 +1,148 @@
+#![cfg(feature = "source-cpu-load")]
+
+use crate::error;
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::threshold::{Hysteresis, ThresholdSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use crate::ssh::{self, SshSettings};
+use serde::Deserialize;
+use std::error::Error;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Metric {
+    /// The load average of the last minute.
+    #[default]
+    LoadAverage,
+    /// CPU utilization in percent since the previous poll.
+    Utilization,
+}
+
+impl Metric {
+    fn proc_file(self) -> &'static str {
+        match self {
+            Metric::LoadAverage => "/proc/loadavg",
+            Metric::Utilization => "/proc/stat",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "CpuLoadSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    #[serde(default)]
+    pub metric: Metric,
+    #[serde(flatten)]
+    pub threshold: ThresholdSettings,
+    /// If set, measure the load of this host via SSH instead of the local machine.
+    pub ssh: Option<SshSettings>,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = CpuLoadSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, error::Error> {
+        CpuLoadSource::new(self.clone())
+    }
+}
+
+/// Active while the CPU load is above the threshold.
+pub struct CpuLoadSource {
+    settings: Settings,
+    ssh_pass: Option<String>,
+    /// Total and idle CPU time of the previous utilization sample.
+    previous_sample: Mutex<Option<(u64, u64)>>,
+    hysteresis: Hysteresis,
+}
+
+impl CpuLoadSource {
+    fn new(settings: Settings) -> Result<Self, error::Error> {
+        let ssh_pass = match &settings.ssh {
+            Some(ssh) => Some(ssh.resolve_pass()?),
+            None => None,
+        };
+        Ok(Self {
+            settings,
+            ssh_pass,
+            previous_sample: Mutex::new(None),
+            hysteresis: Hysteresis::default(),
+        })
+    }
+
+    async fn read_proc_file(
+        &self,
+        cancel: &CancellationToken,
+    ) -> Result<String, Box<dyn Error + Send + Sync>> {
+        let path = self.settings.metric.proc_file();
+        match (&self.settings.ssh, &self.ssh_pass) {
+            (Some(ssh), Some(pass)) => ssh::exec(ssh, pass, &format!("cat {path}"), cancel).await,
+            _ => Ok(tokio::fs::read_to_string(path).await?),
+        }
+    }
+
+    fn utilization(&self, stat: &str) -> Result<f64, Box<dyn Error + Send + Sync>> {
+        // user nice system idle iowait irq softirq steal ...
+        let times = stat
+            .lines()
+            .next()
+            .and_then(|line| line.strip_prefix("cpu "))
+            .ok_or("no cpu line in /proc/stat")?
+            .split_whitespace()
+            .map(str::parse::<u64>)
+            .collect::<Result<Vec<_>, _>>()?;
+        if times.len() < 5 {
+            return Err("too few cpu times in /proc/stat".into());
+        }
+        let total = times.iter().sum::<u64>();
+        let idle = times[3] + times[4];
+        let (prev_total, prev_idle) = self
+            .previous_sample
+            .lock()
+            .unwrap()
+            .replace((total, idle))
+            .unwrap_or_default();
+        let total_delta = total.saturating_sub(prev_total);
+        if total_delta == 0 {
+            return Ok(0.0);
+        }
+        let idle_delta = idle.saturating_sub(prev_idle);
+        Ok(100.0 * (1.0 - idle_delta as f64 / total_delta as f64))
+    }
+}
+
+#[async_trait]
+impl Source for CpuLoadSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, cancel: &CancellationToken) -> SourceIsActiveResult {
+        let content = self.read_proc_file(cancel).await?;
+        let value = match self.settings.metric {
+            Metric::LoadAverage => content
+                .split_whitespace()
+                .next()
+                .ok_or("empty /proc/loadavg")?
+                .parse()
+                .map_err(error::Error::protocol)?,
+            Metric::Utilization => self.utilization(&content)?,
+        };
+        debug!("{:?}: {value:.2}", self.settings.metric);
+        Ok(self.hysteresis.update(value, &self.settings.threshold))
+    }
+}
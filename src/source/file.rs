@@ -0,0 +1,142 @@
+#![cfg(all(feature = "source-file", target_os = "linux"))]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use futures::StreamExt;
+use inotify::{EventStream, Inotify, WatchMask};
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::future::pending;
+use std::io;
+use std::path::PathBuf;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "FileSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// File containing `on`, `off`, `1` or `0`. A missing or empty file counts as off.
+    pub path: PathBuf,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = FileSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        if self.path.file_name().is_none() {
+            return Err(format!("{} is not a file", self.path.display()).into());
+        }
+        Ok(FileSource {
+            settings: self.clone(),
+            watch: Mutex::new(Watch::NotStarted),
+        })
+    }
+}
+
+/// Active while a file says so, for scripts and manual control. Changes to the file are picked
+/// up right away.
+pub struct FileSource {
+    settings: Settings,
+    watch: Mutex<Watch>,
+}
+
+/// Changes in the directory of the file, watched from the first wait on.
+enum Watch {
+    NotStarted,
+    Watching(EventStream<Vec<u8>>),
+    /// Watching is not possible, the file is only polled.
+    Failed,
+}
+
+impl FileSource {
+    fn watch(&self) -> io::Result<EventStream<Vec<u8>>> {
+        // The directory is watched, so that the file being created or replaced is seen too.
+        let dir = match self.settings.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => ".".as_ref(),
+        };
+        let inotify = Inotify::init()?;
+        inotify.watches().add(
+            dir,
+            WatchMask::CLOSE_WRITE
+                | WatchMask::MOVED_TO
+                | WatchMask::MOVED_FROM
+                | WatchMask::DELETE,
+        )?;
+        inotify.into_event_stream(vec![0; 1024])
+    }
+}
+
+#[async_trait]
+impl Source for FileSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let content = match tokio::fs::read_to_string(&self.settings.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e.into()),
+        };
+        match content.trim().to_ascii_lowercase().as_str() {
+            "on" | "1" => Ok(true),
+            "off" | "0" | "" => Ok(false),
+            other => Err(format!("unexpected content \"{other}\"").into()),
+        }
+    }
+
+    async fn wait_for_change(&self) {
+        let mut watch = self.watch.lock().await;
+        if let Watch::NotStarted = *watch {
+            *watch = match self.watch() {
+                Ok(events) => Watch::Watching(events),
+                Err(e) => {
+                    warn!(
+                        "Failed watching {}, only polling it: {}",
+                        self.settings.path.display(),
+                        e
+                    );
+                    Watch::Failed
+                }
+            };
+        }
+        let Watch::Watching(events) = &mut *watch else {
+            drop(watch);
+            return pending().await;
+        };
+        let file_name = self.settings.path.file_name().map(OsString::from);
+        while let Some(event) = events.next().await {
+            match event {
+                Ok(event) if event.name == file_name => {
+                    debug!("{:?} on {}", event.mask, self.settings.path.display());
+                    return;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(
+                        "Failed reading changes of {}, only polling it: {}",
+                        self.settings.path.display(),
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+        *watch = Watch::Failed;
+        drop(watch);
+        pending().await
+    }
+}
@@ -0,0 +1,254 @@
+#![cfg(feature = "source-frigate")]
+
+use crate::error;
+use crate::http_client;
+use crate::mqtt::{BrokerSettings, Message, MqttClient};
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::pending;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{broadcast, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Most recent events requested from the API.
+const MAX_EVENTS: u32 = 25;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "FrigateSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// The MQTT broker Frigate publishes to. Object counts are then received as they change.
+    pub mqtt: Option<BrokerSettings>,
+    /// Topic prefix Frigate is configured with.
+    #[serde(default = "default_topic_prefix")]
+    pub topic_prefix: String,
+    /// Base URL of Frigate, e.g. `http://frigate.local:5000`, to poll its API for events
+    /// instead of using MQTT.
+    pub url: Option<String>,
+    /// Cameras to watch.
+    pub cameras: Vec<String>,
+    /// Only count objects in these zones of the cameras.
+    #[serde(default)]
+    pub zones: Vec<String>,
+    /// Labels of the objects to count.
+    #[serde(default = "default_labels")]
+    pub labels: Vec<String>,
+    /// With the API, also count events that ended this many seconds ago, so that short ones
+    /// between two polls are not missed.
+    #[serde(default = "default_recent_sec")]
+    pub recent_sec: u64,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+fn default_topic_prefix() -> String {
+    "frigate".to_string()
+}
+
+fn default_labels() -> Vec<String> {
+    vec!["person".to_string()]
+}
+
+fn default_recent_sec() -> u64 {
+    60
+}
+
+impl SourceSettings for Settings {
+    type Impl = FrigateSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> std::result::Result<Self::Impl, error::Error> {
+        FrigateSource::new(self.clone())
+    }
+}
+
+/// Active while Frigate detects people, or other objects, on cameras or in zones of them.
+pub struct FrigateSource {
+    settings: Settings,
+    backend: Backend,
+}
+
+enum Backend {
+    Mqtt(MqttWatch),
+    Api { client: Client, events_url: Url },
+}
+
+/// The object count topics of the cameras or zones, e.g. `frigate/living_room/person`.
+struct MqttWatch {
+    client: Arc<MqttClient>,
+    topics: Vec<String>,
+    /// Subscribed on the first poll.
+    messages: Mutex<Option<broadcast::Receiver<Message>>>,
+    counts: std::sync::Mutex<HashMap<String, u64>>,
+}
+
+impl FrigateSource {
+    fn new(settings: Settings) -> std::result::Result<Self, error::Error> {
+        if settings.cameras.is_empty() {
+            return Err("at least one camera is required".into());
+        }
+        let backend = match (&settings.mqtt, &settings.url) {
+            (Some(broker), None) => {
+                // Frigate publishes counts for zones the same way as for cameras.
+                let areas = match settings.zones.is_empty() {
+                    true => &settings.cameras,
+                    false => &settings.zones,
+                };
+                let topics = areas
+                    .iter()
+                    .flat_map(|area| {
+                        let prefix = &settings.topic_prefix;
+                        settings
+                            .labels
+                            .iter()
+                            .map(move |label| format!("{prefix}/{area}/{label}"))
+                    })
+                    .collect();
+                Backend::Mqtt(MqttWatch {
+                    client: MqttClient::connect(broker)?,
+                    topics,
+                    messages: Mutex::new(None),
+                    counts: Default::default(),
+                })
+            }
+            (None, Some(url)) => {
+                let mut events_url = Url::parse(url)
+                    .and_then(|url| url.join("api/events"))
+                    .map_err(error::Error::other)?;
+                events_url
+                    .query_pairs_mut()
+                    .append_pair("cameras", &settings.cameras.join(","))
+                    .append_pair("labels", &settings.labels.join(","))
+                    .append_pair("limit", &MAX_EVENTS.to_string());
+                if !settings.zones.is_empty() {
+                    events_url
+                        .query_pairs_mut()
+                        .append_pair("zones", &settings.zones.join(","));
+                }
+                let client = http_client::builder(&events_url)?.build()?;
+                Backend::Api { client, events_url }
+            }
+            _ => return Err("exactly one of mqtt and url is required".into()),
+        };
+        Ok(Self { settings, backend })
+    }
+
+    /// Whether an event is in progress or ended within `recent-sec`.
+    async fn recent_event(&self, client: &Client, events_url: &Url) -> SourceIsActiveResult {
+        let response = client
+            .get(events_url.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        let events: Vec<Event> = serde_json::from_slice(&response.bytes().await?)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(error::Error::other)?
+            .as_secs_f64();
+        let since = now - self.settings.recent_sec as f64;
+        let recent = events.iter().find(|event| match event.end_time {
+            None => true,
+            Some(end_time) => end_time >= since,
+        });
+        if let Some(event) = recent {
+            debug!("Recent event {} on {}", event.label, event.camera);
+        }
+        Ok(recent.is_some())
+    }
+}
+
+#[derive(Deserialize)]
+struct Event {
+    camera: String,
+    label: String,
+    /// Unix time the event ended at, `None` while it's in progress.
+    end_time: Option<f64>,
+}
+
+impl MqttWatch {
+    async fn subscribe(&self) -> Result<()> {
+        let mut messages = self.messages.lock().await;
+        if messages.is_some() {
+            return Ok(());
+        }
+        let mut receiver = None;
+        for topic in &self.topics {
+            receiver = Some(self.client.subscribe(topic).await?);
+        }
+        *messages = receiver;
+        Ok(())
+    }
+
+    fn any_detected(&self) -> bool {
+        let counts = self.counts.lock().unwrap();
+        debug!("Object counts: {counts:?}");
+        counts.values().any(|&count| count > 0)
+    }
+
+    /// Receive messages until an object count changed.
+    async fn wait_for_change(&self) {
+        let mut messages = self.messages.lock().await;
+        let Some(messages) = messages.as_mut() else {
+            return pending().await;
+        };
+        loop {
+            let message = match messages.recv().await {
+                Ok(v) => v,
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return pending().await,
+            };
+            if !self.topics.contains(&message.topic) {
+                continue;
+            }
+            let payload = String::from_utf8_lossy(&message.payload);
+            let Ok(count) = payload.trim().parse::<u64>() else {
+                warn!("Unexpected object count {payload} on {}", message.topic);
+                continue;
+            };
+            let previous = self.counts.lock().unwrap().insert(message.topic, count);
+            if previous.unwrap_or_default() != count {
+                return;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Source for FrigateSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        match &self.backend {
+            Backend::Mqtt(watch) => {
+                watch.subscribe().await?;
+                Ok(watch.any_detected())
+            }
+            Backend::Api { client, events_url } => self.recent_event(client, events_url).await,
+        }
+    }
+
+    async fn wait_for_change(&self) {
+        match &self.backend {
+            Backend::Mqtt(watch) => watch.wait_for_change().await,
+            Backend::Api { .. } => pending().await,
+        }
+    }
+}
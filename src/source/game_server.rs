@@ -0,0 +1,236 @@
+#![cfg(feature = "source-game-server")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::error::Error;
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// Largest status response of a Minecraft server that is read.
+const MAX_STATUS_LEN: usize = 1 << 20;
+const A2S_HEADER: [u8; 4] = [0xFF; 4];
+const A2S_INFO_REQUEST: u8 = b'T';
+const A2S_CHALLENGE: u8 = b'A';
+const A2S_INFO_RESPONSE: u8 = b'I';
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Protocol {
+    /// The server list ping of Minecraft: Java Edition.
+    Minecraft,
+    /// The A2S_INFO query of Source engine and many other Steam game servers.
+    Source,
+}
+
+impl Protocol {
+    fn default_port(self) -> u16 {
+        match self {
+            Protocol::Minecraft => 25565,
+            Protocol::Source => 27015,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "GameServerSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the server.
+    pub host: String,
+    /// Port of the game or query, by default the usual one of the protocol.
+    pub port: Option<u16>,
+    pub protocol: Protocol,
+    /// Number of players that have to be online for the source to be on.
+    #[serde(default = "default_min_players")]
+    pub min_players: u32,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+fn default_min_players() -> u32 {
+    1
+}
+
+impl SourceSettings for Settings {
+    type Impl = GameServerSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(GameServerSource {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// Active while players are online on a game server, e.g. to keep the PC hosting it awake.
+pub struct GameServerSource {
+    settings: Settings,
+}
+
+impl GameServerSource {
+    fn port(&self) -> u16 {
+        let protocol = self.settings.protocol;
+        self.settings.port.unwrap_or(protocol.default_port())
+    }
+
+    /// Number of players online according to the server list ping, or `None` if the server is
+    /// not reachable.
+    async fn minecraft_players(&self) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+        let host = &self.settings.host;
+        let port = self.port();
+        let mut stream = match TcpStream::connect((host.as_str(), port)).await {
+            Ok(v) => v,
+            Err(e) => {
+                debug!("Server not reachable: {e}");
+                return Ok(None);
+            }
+        };
+
+        let mut handshake = vec![0x00];
+        // Protocol version -1, for servers to answer regardless of their version.
+        write_var_int(&mut handshake, -1);
+        write_var_int(&mut handshake, host.len() as i32);
+        handshake.extend_from_slice(host.as_bytes());
+        handshake.extend_from_slice(&port.to_be_bytes());
+        // Next state: status.
+        write_var_int(&mut handshake, 1);
+        let mut request = Vec::with_capacity(handshake.len() + 7);
+        write_var_int(&mut request, handshake.len() as i32);
+        request.extend_from_slice(&handshake);
+        // Status request, without fields.
+        request.extend_from_slice(&[1, 0x00]);
+        stream.write_all(&request).await?;
+
+        let _len = read_var_int(&mut stream).await?;
+        let packet_id = read_var_int(&mut stream).await?;
+        if packet_id != 0x00 {
+            return Err(format!("unexpected packet {packet_id:#x} instead of status").into());
+        }
+        let status_len = read_var_int(&mut stream).await?;
+        if !(0..=MAX_STATUS_LEN as i32).contains(&status_len) {
+            return Err(format!("invalid status length {status_len}").into());
+        }
+        let mut status = vec![0; status_len as usize];
+        stream.read_exact(&mut status).await?;
+        let status: serde_json::Value = serde_json::from_slice(&status)?;
+        let online = status["players"]["online"]
+            .as_u64()
+            .ok_or("status without players online")?;
+        Ok(Some(online))
+    }
+
+    /// Number of players online according to A2S_INFO, not counting bots, or `None` if the
+    /// server does not answer.
+    async fn source_players(&self) -> Result<Option<u64>, Box<dyn Error + Send + Sync>> {
+        // Leave time for the poll timeout to not trigger if the server does not answer.
+        let wait = Duration::from_secs(self.settings.base.timeout_sec as u64) / 2;
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket
+            .connect((self.settings.host.as_str(), self.port()))
+            .await?;
+
+        let mut request = A2S_HEADER.to_vec();
+        request.push(A2S_INFO_REQUEST);
+        request.extend_from_slice(b"Source Engine Query\0");
+        let mut buf = [0; 1400];
+        let mut challenged = false;
+        loop {
+            socket.send(&request).await?;
+            let len = match timeout(wait, socket.recv(&mut buf)).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    debug!("No response to the query.");
+                    return Ok(None);
+                }
+            };
+            let response = &buf[..len];
+            match response.get(..5) {
+                Some([0xFF, 0xFF, 0xFF, 0xFF, kind]) if *kind == A2S_CHALLENGE && !challenged => {
+                    // Newer servers require the request to be repeated with their challenge.
+                    let challenge = response.get(5..9).ok_or("truncated challenge")?;
+                    request.extend_from_slice(challenge);
+                    challenged = true;
+                }
+                Some([0xFF, 0xFF, 0xFF, 0xFF, kind]) if *kind == A2S_INFO_RESPONSE => {
+                    return Ok(Some(parse_a2s_players(&response[5..])?));
+                }
+                _ => return Err(format!("unexpected query response: {response:02x?}").into()),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Source for GameServerSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let players = match self.settings.protocol {
+            Protocol::Minecraft => self.minecraft_players().await?,
+            Protocol::Source => self.source_players().await?,
+        };
+        let Some(players) = players else {
+            return Ok(false);
+        };
+        debug!("{players} players online");
+        Ok(players >= self.settings.min_players as u64)
+    }
+}
+
+fn write_var_int(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+async fn read_var_int(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<i32> {
+    let mut value = 0u32;
+    for i in 0..5 {
+        let byte = reader.read_u8().await?;
+        value |= ((byte & 0x7F) as u32) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "VarInt too long",
+    ))
+}
+
+/// Players minus bots from the A2S_INFO response after its header.
+fn parse_a2s_players(info: &[u8]) -> Result<u64, Box<dyn Error + Send + Sync>> {
+    // Protocol version, then name, map, folder and game as null-terminated strings.
+    let mut rest = info.get(1..).ok_or("truncated info")?;
+    for _ in 0..4 {
+        let end = rest.iter().position(|b| *b == 0).ok_or("truncated info")?;
+        rest = &rest[end + 1..];
+    }
+    // Steam app ID, then players, max. players and bots.
+    match rest.get(2..5) {
+        Some(&[players, _max_players, bots]) => Ok(players.saturating_sub(bots) as u64),
+        _ => Err("truncated info".into()),
+    }
+}
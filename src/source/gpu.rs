@@ -0,0 +1,108 @@
+#![cfg(feature = "source-gpu")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::threshold::{Hysteresis, ThresholdSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use nvml_wrapper::Nvml;
+use serde::Deserialize;
+use std::error::Error;
+use std::sync::OnceLock;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// NVIDIA GPUs, via the NVML library of the driver.
+    Nvml,
+    /// AMD GPUs, via the `gpu_busy_percent` file of the amdgpu driver in sysfs.
+    AmdSysfs,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "GpuSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    pub backend: Backend,
+    /// Index of the GPU for NVML, or number of the DRM card (`/sys/class/drm/cardN`) for sysfs.
+    #[serde(default)]
+    pub device: u32,
+    /// Utilization in percent.
+    #[serde(flatten)]
+    pub threshold: ThresholdSettings,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = GpuSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(GpuSource {
+            settings: self.clone(),
+            hysteresis: Hysteresis::default(),
+        })
+    }
+}
+
+/// Active while the GPU utilization is above the threshold.
+pub struct GpuSource {
+    settings: Settings,
+    hysteresis: Hysteresis,
+}
+
+/// NVML is initialized once and shared, since loading the library is expensive.
+fn nvml() -> Result<&'static Nvml, Box<dyn Error + Send + Sync>> {
+    static NVML: OnceLock<Nvml> = OnceLock::new();
+    if let Some(nvml) = NVML.get() {
+        return Ok(nvml);
+    }
+    let nvml = Nvml::init()?;
+    Ok(NVML.get_or_init(|| nvml))
+}
+
+impl GpuSource {
+    async fn utilization(&self) -> Result<f64, Box<dyn Error + Send + Sync>> {
+        let device = self.settings.device;
+        match self.settings.backend {
+            Backend::Nvml => {
+                tokio::task::spawn_blocking(move || {
+                    let rates = nvml()?.device_by_index(device)?.utilization_rates()?;
+                    Ok(rates.gpu as f64)
+                })
+                .await?
+            }
+            Backend::AmdSysfs => {
+                let path = format!("/sys/class/drm/card{device}/device/gpu_busy_percent");
+                let content = tokio::fs::read_to_string(&path)
+                    .await
+                    .map_err(|e| format!("failed reading {path}: {e}"))?;
+                Ok(content.trim().parse()?)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Source for GpuSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let utilization = self.utilization().await?;
+        debug!("Utilization: {utilization}%");
+        Ok(self
+            .hysteresis
+            .update(utilization, &self.settings.threshold))
+    }
+}
@@ -1,22 +1,42 @@
 #![cfg(feature = "source-kodi")]
 
+use crate::kodi::ConnectionSettings;
 use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::kodi::kodi_cmd::{
+    PlayerGetActivePlayers, PlayerGetProperties, XbmcGetInfoBooleans,
+};
 use crate::source::{Source, SourceIsActiveResult};
-use kodi_jsonrpc_client::methods::PlayerGetActivePlayers;
 use kodi_jsonrpc_client::KodiClient;
 use serde::Deserialize;
-use std::convert::Infallible;
-use std::error::Error;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
 
 #[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "KodiSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
 pub struct Settings {
-    pub jsonrpc: String,
-    pub user: Option<String>,
-    pub pass: Option<String>,
+    #[serde(flatten)]
+    pub connection: ConnectionSettings,
+    /// Count paused playback as active.
+    #[serde(default = "default_paused_is_active")]
+    pub paused_is_active: bool,
+    /// Count as active while the screensaver is not showing.
+    #[serde(default)]
+    pub no_screensaver_is_active: bool,
+    /// Count as active until there was no input for this long.
+    pub idle_threshold_sec: Option<u64>,
     #[serde(flatten)]
     base: SourceBaseSettings,
 }
 
+fn default_paused_is_active() -> bool {
+    true
+}
+
 impl SourceSettings for Settings {
     type Impl = KodiSource;
 
@@ -24,18 +44,58 @@ impl SourceSettings for Settings {
         &self.base
     }
 
-    fn create_source(&self) -> Result<Self::Impl, Box<dyn Error>> {
-        KodiSource::new(self.clone()).map_err(Into::into)
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        KodiSource::new(self.clone())
     }
 }
 
 pub struct KodiSource {
     settings: Settings,
+    client: KodiClient,
 }
 
 impl KodiSource {
-    fn new(settings: Settings) -> Result<Self, Infallible> {
-        Ok(Self { settings })
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let client = settings.connection.client()?;
+        Ok(Self { settings, client })
+    }
+
+    async fn is_playing(&self) -> SourceIsActiveResult {
+        let players = self.client.send_method(PlayerGetActivePlayers {}).await?;
+        if self.settings.paused_is_active {
+            return Ok(!players.is_empty());
+        }
+        for player in players {
+            let properties = self
+                .client
+                .send_method(PlayerGetProperties::speed(player.playerid))
+                .await?;
+            debug!("Player {} has speed {}", player.playerid, properties.speed);
+            if properties.speed != 0.0 {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn is_in_use(&self) -> SourceIsActiveResult {
+        let mut booleans = Vec::new();
+        if self.settings.no_screensaver_is_active {
+            booleans.push("System.ScreenSaverActive".to_string());
+        }
+        if let Some(sec) = self.settings.idle_threshold_sec {
+            booleans.push(format!("System.IdleTime({sec})"));
+        }
+        if booleans.is_empty() {
+            return Ok(false);
+        }
+        let request = XbmcGetInfoBooleans {
+            booleans: booleans.clone(),
+        };
+        let values = self.client.send_method(request).await?;
+        debug!("Info booleans: {values:?}");
+        // Both are true while nobody uses Kodi.
+        Ok(booleans.iter().any(|name| values.get(name) == Some(&false)))
     }
 }
 
@@ -45,19 +105,61 @@ impl Source for KodiSource {
         self.settings.base()
     }
 
-    async fn is_active(&self) -> SourceIsActiveResult {
-        let mut url = reqwest::Url::parse(&self.settings.jsonrpc)?;
-        if let Some(user) = &self.settings.user {
-            url.set_username(user)
-                .map_err(|_| "failed setting user on kodi rpc")?;
-        }
-        if let Some(pass) = &self.settings.pass {
-            url.set_password(Some(pass))
-                .map_err(|_| "failed setting pass on kodi rpc")?;
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        Ok(self.is_playing().await? || self.is_in_use().await?)
+    }
+}
+
+mod kodi_cmd {
+    use kodi_jsonrpc_client::KodiMethod;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Debug, Serialize)]
+    pub struct PlayerGetActivePlayers {}
+
+    #[derive(Debug, Deserialize)]
+    pub struct ActivePlayer {
+        pub playerid: i64,
+    }
+
+    impl KodiMethod for PlayerGetActivePlayers {
+        const NAME: &'static str = "Player.GetActivePlayers";
+        type Response = Vec<ActivePlayer>;
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct PlayerGetProperties {
+        playerid: i64,
+        properties: &'static [&'static str],
+    }
+
+    impl PlayerGetProperties {
+        pub fn speed(playerid: i64) -> Self {
+            Self {
+                playerid,
+                properties: &["speed"],
+            }
         }
-        let client = KodiClient::new(reqwest::Client::new(), url);
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct PlayerProperties {
+        pub speed: f64,
+    }
+
+    impl KodiMethod for PlayerGetProperties {
+        const NAME: &'static str = "Player.GetProperties";
+        type Response = PlayerProperties;
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct XbmcGetInfoBooleans {
+        pub booleans: Vec<String>,
+    }
 
-        let players = client.send_method(PlayerGetActivePlayers {}).await?;
-        Ok(!players.is_empty())
+    impl KodiMethod for XbmcGetInfoBooleans {
+        const NAME: &'static str = "XBMC.GetInfoBooleans";
+        type Response = HashMap<String, bool>;
     }
 }
@@ -0,0 +1,102 @@
+#![cfg(all(feature = "source-logind", target_os = "linux"))]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use tokio::sync::OnceCell;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{Connection, Proxy};
+
+const LOGIND: &str = "org.freedesktop.login1";
+const GRAPHICAL_SESSION_TYPES: [&str; 3] = ["x11", "wayland", "mir"];
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "LogindSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Whether only graphical sessions count, not e.g. SSH logins.
+    #[serde(default = "default_graphical_only")]
+    pub graphical_only: bool,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+fn default_graphical_only() -> bool {
+    true
+}
+
+impl SourceSettings for Settings {
+    type Impl = LogindSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(LogindSource {
+            settings: self.clone(),
+            connection: OnceCell::new(),
+        })
+    }
+}
+
+/// Active while a session on the local machine is active and not idle according to
+/// systemd-logind.
+pub struct LogindSource {
+    settings: Settings,
+    connection: OnceCell<Connection>,
+}
+
+impl LogindSource {
+    async fn session_in_use(
+        &self,
+        connection: &Connection,
+        path: OwnedObjectPath,
+    ) -> zbus::Result<bool> {
+        let session =
+            Proxy::new(connection, LOGIND, path, "org.freedesktop.login1.Session").await?;
+        if self.settings.graphical_only {
+            let session_type: String = session.get_property("Type").await?;
+            if !GRAPHICAL_SESSION_TYPES.contains(&session_type.as_str()) {
+                return Ok(false);
+            }
+        }
+        let active: bool = session.get_property("Active").await?;
+        let idle: bool = session.get_property("IdleHint").await?;
+        debug!("{}: active={active}, idle={idle}", session.path());
+        Ok(active && !idle)
+    }
+}
+
+#[async_trait]
+impl Source for LogindSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let connection = self.connection.get_or_try_init(Connection::system).await?;
+        let manager = Proxy::new(
+            connection,
+            LOGIND,
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+        )
+        .await?;
+        // ID, user ID, user name, seat and object path of each session.
+        let sessions: Vec<(String, u32, String, String, OwnedObjectPath)> =
+            manager.call("ListSessions", &()).await?;
+        for (.., path) in sessions {
+            if self.session_in_use(connection, path).await? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "source-net-presence")]
+
+use crate::neighbor;
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// Port of the discard service, used to make the kernel resolve an address.
+const DISCARD_PORT: u16 = 9;
+/// Time given to the kernel to resolve an address after probing it.
+const PROBE_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "NetPresenceSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// IP or MAC address of the device.
+    pub address: String,
+    /// Seconds the device still counts as present after it was last seen, to bridge devices
+    /// such as phones dropping off the network while sleeping.
+    #[serde(default)]
+    pub grace_sec: u64,
+    /// If the device is not in the neighbor table, send a packet to it to make the kernel
+    /// resolve it and check again. Only possible with an IP address.
+    #[serde(default)]
+    pub probe: bool,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = NetPresenceSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        NetPresenceSource::new(self.clone())
+    }
+}
+
+/// Active while the device has an entry in the neighbor (ARP) table.
+pub struct NetPresenceSource {
+    settings: Settings,
+    probe_ip: Option<IpAddr>,
+    last_seen: Mutex<Option<Instant>>,
+}
+
+impl NetPresenceSource {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let probe_ip = if settings.probe {
+            Some(
+                settings
+                    .address
+                    .parse()
+                    .map_err(|_| "probe requires an IP address")?,
+            )
+        } else {
+            None
+        };
+        Ok(Self {
+            settings,
+            probe_ip,
+            last_seen: Mutex::new(None),
+        })
+    }
+
+    async fn is_present(&self) -> std::io::Result<bool> {
+        if neighbor::contains(&self.settings.address)? {
+            return Ok(true);
+        }
+        let Some(ip) = self.probe_ip else {
+            return Ok(false);
+        };
+        debug!("Not in the neighbor table, probing.");
+        let socket = UdpSocket::bind(match ip {
+            IpAddr::V4(_) => "0.0.0.0:0",
+            IpAddr::V6(_) => "[::]:0",
+        })
+        .await?;
+        socket.send_to(&[], (ip, DISCARD_PORT)).await?;
+        sleep(PROBE_WAIT).await;
+        neighbor::contains(&self.settings.address)
+    }
+}
+
+#[async_trait]
+impl Source for NetPresenceSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let present = self.is_present().await?;
+        let mut last_seen = self.last_seen.lock().unwrap();
+        if present {
+            *last_seen = Some(Instant::now());
+            return Ok(true);
+        }
+        let grace = Duration::from_secs(self.settings.grace_sec);
+        Ok(last_seen.is_some_and(|seen| seen.elapsed() < grace))
+    }
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "source-pdu-outlet")]
+
+use crate::modbus::{Connection, RegisterKind, DEFAULT_PORT};
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::threshold::{Hysteresis, ThresholdSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "PduOutletSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the PDU.
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default = "default_unit_id")]
+    pub unit_id: u8,
+    /// Address of the coil of the outlet, starting at 0. The source is active while it's set.
+    pub coil: Option<u16>,
+    /// Read the current draw of the outlet instead, e.g. to find out whether the device
+    /// plugged into it is actually running.
+    pub current: Option<CurrentSettings>,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+/// A register with the current draw of an outlet.
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct CurrentSettings {
+    /// Address of the register, starting at 0.
+    pub register: u16,
+    #[serde(default)]
+    pub register_kind: RegisterKind,
+    /// Amperes per unit of the register value, e.g. 0.1 if it's in tenths of an ampere.
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+    /// Current in amperes.
+    #[serde(flatten)]
+    pub threshold: ThresholdSettings,
+}
+
+fn default_port() -> u16 {
+    DEFAULT_PORT
+}
+
+fn default_unit_id() -> u8 {
+    1
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+impl SourceSettings for Settings {
+    type Impl = PduOutletSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        if self.coil.is_some() == self.current.is_some() {
+            return Err("exactly one of coil and current is required".into());
+        }
+        Ok(PduOutletSource {
+            settings: self.clone(),
+            hysteresis: Hysteresis::default(),
+        })
+    }
+}
+
+/// Active while an outlet of a PDU is switched on or draws current, read over Modbus TCP. Shows
+/// the actual state of an outlet that is also a sink, including manual switching.
+pub struct PduOutletSource {
+    settings: Settings,
+    hysteresis: Hysteresis,
+}
+
+#[async_trait]
+impl Source for PduOutletSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let settings = &self.settings;
+        let mut connection =
+            Connection::connect(&settings.host, settings.port, settings.unit_id).await?;
+        if let Some(coil) = settings.coil {
+            let state = connection.read_coil(coil).await?;
+            debug!("Coil {coil} reads {state}");
+            return Ok(state);
+        }
+        let current = settings.current.as_ref().expect("checked on creation");
+        let value = connection
+            .read_register(current.register_kind, current.register)
+            .await?;
+        let amperes = value as f64 * current.scale;
+        debug!("Current: {amperes} A");
+        Ok(self.hysteresis.update(amperes, &current.threshold))
+    }
+}
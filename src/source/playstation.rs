@@ -0,0 +1,101 @@
+#![cfg(feature = "source-playstation")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{udp_probe, Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Model {
+    Ps4,
+    #[default]
+    Ps5,
+}
+
+impl Model {
+    /// UDP port of the device discovery protocol.
+    fn port(self) -> u16 {
+        match self {
+            Model::Ps4 => 987,
+            Model::Ps5 => 9302,
+        }
+    }
+
+    fn protocol_version(self) -> &'static str {
+        match self {
+            Model::Ps4 => "00020020",
+            Model::Ps5 => "00030010",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "PlaystationSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the console.
+    pub host: String,
+    #[serde(default)]
+    pub model: Model,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = PlayStationSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(PlayStationSource {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// Active while the console is awake. In rest mode the console answers discovery requests
+/// with a standby status, when it is turned off completely it does not answer at all.
+pub struct PlayStationSource {
+    settings: Settings,
+}
+
+#[async_trait]
+impl Source for PlayStationSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let model = self.settings.model;
+        let request = format!(
+            "SRCH * HTTP/1.1\ndevice-discovery-protocol-version:{}\n",
+            model.protocol_version()
+        );
+        // Leave time for the poll timeout to not trigger if the console does not answer.
+        let wait = Duration::from_secs(self.settings.base.timeout_sec as u64) / 2;
+        let Some(buf) =
+            udp_probe::probe(&self.settings.host, model.port(), request.as_bytes(), wait).await?
+        else {
+            debug!("No discovery response, assuming the console is off.");
+            return Ok(false);
+        };
+        let response = String::from_utf8_lossy(&buf);
+        let status_line = response.lines().next().unwrap_or_default();
+        debug!("Discovery response: {status_line}");
+        match status_line.split_whitespace().nth(1) {
+            Some("200") => Ok(true),
+            Some("620") => Ok(false),
+            _ => Err(format!("unexpected discovery response: {status_line}").into()),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+#![cfg(all(feature = "source-process", target_os = "linux"))]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ProcessSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Regex matched against the process name (`comm`), e.g. `^streaming_client$`.
+    pub process_name: Option<String>,
+    /// Regex matched against the command line, with the arguments joined by spaces.
+    pub cmdline: Option<String>,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = ProcessSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        ProcessSource::new(self.clone())
+    }
+}
+
+/// Active while a process on the local machine matches all configured patterns.
+pub struct ProcessSource {
+    settings: Settings,
+    matcher: Arc<Matcher>,
+}
+
+struct Matcher {
+    process_name: Option<Regex>,
+    cmdline: Option<Regex>,
+}
+
+impl ProcessSource {
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        if settings.process_name.is_none() && settings.cmdline.is_none() {
+            return Err("at least one of process-name and cmdline is required".into());
+        }
+        let matcher = Matcher {
+            process_name: settings
+                .process_name
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(crate::error::Error::other)?,
+            cmdline: settings
+                .cmdline
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(crate::error::Error::other)?,
+        };
+        Ok(Self {
+            settings,
+            matcher: Arc::new(matcher),
+        })
+    }
+}
+
+impl Matcher {
+    fn any_running(&self) -> io::Result<bool> {
+        for entry in fs::read_dir("/proc")? {
+            let path = entry?.path();
+            let is_pid = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()));
+            if !is_pid {
+                continue;
+            }
+            // Processes may exit while iterating, so failing to read them is not an error.
+            if let Some(regex) = &self.process_name {
+                match fs::read_to_string(path.join("comm")) {
+                    Ok(comm) if regex.is_match(comm.trim_end_matches('\n')) => {}
+                    _ => continue,
+                }
+            }
+            if let Some(regex) = &self.cmdline {
+                match fs::read(path.join("cmdline")) {
+                    Ok(cmdline) => {
+                        let cmdline = String::from_utf8_lossy(&cmdline);
+                        let cmdline = cmdline.trim_end_matches('\0').replace('\0', " ");
+                        if !regex.is_match(&cmdline) {
+                            continue;
+                        }
+                    }
+                    Err(_) => continue,
+                }
+            }
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl Source for ProcessSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let matcher = self.matcher.clone();
+        Ok(tokio::task::spawn_blocking(move || matcher.any_running()).await??)
+    }
+}
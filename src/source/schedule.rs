@@ -0,0 +1,237 @@
+#![cfg(feature = "source-schedule")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use cron::Schedule;
+use serde::Deserialize;
+use std::future::pending;
+use std::str::FromStr;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "ScheduleSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// IANA time zone the times are in, e.g. `Europe/Berlin`. Local time if not set.
+    pub timezone: Option<String>,
+    /// Daily time windows during which the source is on.
+    #[serde(default)]
+    pub windows: Vec<WindowSettings>,
+    /// Cron expressions, each turning the source on for a duration.
+    #[serde(default)]
+    pub cron: Vec<CronSettings>,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct WindowSettings {
+    /// Start time, such as `08:00`.
+    pub from: String,
+    /// End time, such as `20:00`. If it's before the start, the window ends on the next day.
+    pub to: String,
+    /// Days the window starts on, such as `["mon", "tue"]`. Every day if not set.
+    pub days: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct CronSettings {
+    /// With seconds, e.g. `0 30 18 * * Fri` for Fridays at 18:30.
+    pub expression: String,
+    /// How long the source is on for each time the expression matches.
+    pub duration_sec: u64,
+}
+
+impl SourceSettings for Settings {
+    type Impl = ScheduleSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        if self.windows.is_empty() && self.cron.is_empty() {
+            return Err("at least one window or cron expression is required".into());
+        }
+        let timezone = self
+            .timezone
+            .as_deref()
+            .map(Tz::from_str)
+            .transpose()
+            .map_err(|e| format!("invalid timezone: {e}"))?;
+        let windows = self
+            .windows
+            .iter()
+            .map(Window::new)
+            .collect::<Result<_, _>>()?;
+        let cron = self
+            .cron
+            .iter()
+            .map(|cron| {
+                Ok::<_, crate::error::Error>(Cron {
+                    schedule: Schedule::from_str(&cron.expression)
+                        .map_err(|e| format!("invalid cron expression {}: {e}", cron.expression))?,
+                    duration: Duration::seconds(cron.duration_sec as i64),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(ScheduleSource {
+            settings: self.clone(),
+            timezone,
+            windows,
+            cron,
+        })
+    }
+}
+
+struct Window {
+    from: NaiveTime,
+    to: NaiveTime,
+    days: Option<Vec<Weekday>>,
+}
+
+impl Window {
+    fn new(settings: &WindowSettings) -> Result<Self, crate::error::Error> {
+        let parse_time = |time: &str| {
+            NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|e| format!("invalid time {time}: {e}"))
+        };
+        let days = settings
+            .days
+            .as_ref()
+            .map(|days| {
+                days.iter()
+                    .map(|day| Weekday::from_str(day).map_err(|_| format!("invalid day {day}")))
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()?;
+        Ok(Self {
+            from: parse_time(&settings.from)?,
+            to: parse_time(&settings.to)?,
+            days,
+        })
+    }
+
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.as_ref().is_none_or(|days| days.contains(&day))
+    }
+
+    fn contains<Z: TimeZone>(&self, now: &DateTime<Z>) -> bool {
+        let (time, day) = (now.time(), now.weekday());
+        if self.from <= self.to {
+            self.from <= time && time < self.to && self.starts_on(day)
+        } else {
+            (self.from <= time && self.starts_on(day))
+                || (time < self.to && self.starts_on(day.pred()))
+        }
+    }
+
+    /// The next time after now at which the window may start or end.
+    fn next_boundary<Z: TimeZone>(&self, now: &DateTime<Z>) -> Option<DateTime<Utc>> {
+        let timezone = now.timezone();
+        (0..3)
+            .filter_map(|days| now.date_naive().checked_add_days(chrono::Days::new(days)))
+            .flat_map(|date| [date.and_time(self.from), date.and_time(self.to)])
+            .filter_map(|local| timezone.from_local_datetime(&local).earliest())
+            .filter(|boundary| boundary > now)
+            .map(|boundary| boundary.with_timezone(&Utc))
+            .min()
+    }
+}
+
+struct Cron {
+    schedule: Schedule,
+    duration: Duration,
+}
+
+impl Cron {
+    /// When the source turned on because of the expression, if it is still on now.
+    fn active_since<Z: TimeZone>(&self, now: &DateTime<Z>) -> Option<DateTime<Z>> {
+        self.schedule
+            .after(&(now.clone() - self.duration))
+            .next()
+            .filter(|since| since <= now)
+    }
+
+    /// The next time after now at which the source may turn on or off.
+    fn next_boundary<Z: TimeZone>(&self, now: &DateTime<Z>) -> Option<DateTime<Utc>> {
+        let next = match self.active_since(now) {
+            Some(since) => since + self.duration,
+            None => self.schedule.after(now).next()?,
+        };
+        Some(next.with_timezone(&Utc))
+    }
+}
+
+/// Active during time windows or after cron expressions matched, to keep sinks on by time.
+pub struct ScheduleSource {
+    settings: Settings,
+    timezone: Option<Tz>,
+    windows: Vec<Window>,
+    cron: Vec<Cron>,
+}
+
+impl ScheduleSource {
+    fn active_at<Z: TimeZone>(&self, now: &DateTime<Z>) -> bool {
+        self.windows.iter().any(|window| window.contains(now))
+            || self
+                .cron
+                .iter()
+                .any(|cron| cron.active_since(now).is_some())
+    }
+
+    fn next_boundary_at<Z: TimeZone>(&self, now: &DateTime<Z>) -> Option<DateTime<Utc>> {
+        let windows = self.windows.iter().map(|window| window.next_boundary(now));
+        let cron = self.cron.iter().map(|cron| cron.next_boundary(now));
+        windows.chain(cron).flatten().min()
+    }
+
+    fn active(&self) -> bool {
+        match &self.timezone {
+            Some(timezone) => self.active_at(&Utc::now().with_timezone(timezone)),
+            None => self.active_at(&Local::now()),
+        }
+    }
+
+    fn next_boundary(&self) -> Option<DateTime<Utc>> {
+        match &self.timezone {
+            Some(timezone) => self.next_boundary_at(&Utc::now().with_timezone(timezone)),
+            None => self.next_boundary_at(&Local::now()),
+        }
+    }
+}
+
+#[async_trait]
+impl Source for ScheduleSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        Ok(self.active())
+    }
+
+    /// Completes when the source may turn on or off, so that it does so on time.
+    async fn wait_for_change(&self) {
+        match self.next_boundary() {
+            Some(next) => {
+                let wait = (next - Utc::now()).to_std().unwrap_or_default();
+                sleep(wait).await
+            }
+            None => pending().await,
+        }
+    }
+}
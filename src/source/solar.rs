@@ -0,0 +1,192 @@
+#![cfg(feature = "source-solar")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+const SECS_PER_DAY: f64 = 86_400.0;
+/// Julian date of the Unix epoch.
+const UNIX_EPOCH_JD: f64 = 2_440_587.5;
+/// Julian date of J2000.0.
+const J2000: f64 = 2_451_545.0;
+/// Days around today that sunrises and sunsets are computed for, so that the last and next one
+/// are among them even around the poles.
+const DAYS_AROUND: i64 = 2;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "SolarSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Latitude in degrees, north is positive.
+    pub latitude: f64,
+    /// Longitude in degrees, east is positive.
+    pub longitude: f64,
+    /// Which twilight still counts as day.
+    #[serde(default)]
+    pub twilight: Twilight,
+    /// Minutes to move the start of the night by, negative for earlier.
+    #[serde(default)]
+    pub dusk_offset_min: i64,
+    /// Minutes to move the end of the night by, negative for earlier.
+    #[serde(default)]
+    pub dawn_offset_min: i64,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+/// Until which angle of the sun below the horizon it is still day.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Twilight {
+    /// Night is from sunset to sunrise.
+    #[default]
+    None,
+    /// 6°, until it is too dark to be outside without lighting.
+    Civil,
+    /// 12°.
+    Nautical,
+    /// 18°.
+    Astronomical,
+}
+
+impl Twilight {
+    /// Elevation of the center of the sun at dusk and dawn, in degrees.
+    fn elevation(self) -> f64 {
+        match self {
+            // Accounts for refraction and the radius of the sun.
+            Twilight::None => -0.833,
+            Twilight::Civil => -6.0,
+            Twilight::Nautical => -12.0,
+            Twilight::Astronomical => -18.0,
+        }
+    }
+}
+
+impl SourceSettings for Settings {
+    type Impl = SolarSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        if !(-90.0..=90.0).contains(&self.latitude) || !(-180.0..=180.0).contains(&self.longitude) {
+            return Err("coordinates out of range".into());
+        }
+        Ok(SolarSource {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// Active at night, between dusk and dawn at the configured coordinates.
+pub struct SolarSource {
+    settings: Settings,
+}
+
+/// A change between day and night, at a time in seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug)]
+struct Change {
+    time: f64,
+    night: bool,
+}
+
+impl SolarSource {
+    /// Dusk and dawn of the day, with the offsets applied. For days without either, the day
+    /// starts with it being day or night.
+    fn changes_on(&self, day: i64) -> Vec<Change> {
+        let settings = &self.settings;
+        // Mean solar noon at the longitude, in days since J2000.
+        let noon = (day as f64 + UNIX_EPOCH_JD + 0.5 - J2000).round() - settings.longitude / 360.0;
+        let anomaly = (357.5291 + 0.985_600_28 * noon)
+            .rem_euclid(360.0)
+            .to_radians();
+        let center = 1.9148 * anomaly.sin()
+            + 0.0200 * (2.0 * anomaly).sin()
+            + 0.0003 * (3.0 * anomaly).sin();
+        let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372)
+            .rem_euclid(360.0)
+            .to_radians();
+        let transit =
+            J2000 + noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+        let declination = (ecliptic_longitude.sin() * 23.4397_f64.to_radians().sin()).asin();
+        let latitude = settings.latitude.to_radians();
+        let cos_hour_angle = (settings.twilight.elevation().to_radians().sin()
+            - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+        let to_unix = |jd: f64| (jd - UNIX_EPOCH_JD) * SECS_PER_DAY;
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            // Polar night or midnight sun.
+            return vec![Change {
+                time: to_unix(transit - 0.5),
+                night: cos_hour_angle > 1.0,
+            }];
+        }
+        let hour_angle = cos_hour_angle.acos().to_degrees() / 360.0;
+        vec![
+            Change {
+                time: to_unix(transit - hour_angle) + settings.dawn_offset_min as f64 * 60.0,
+                night: false,
+            },
+            Change {
+                time: to_unix(transit + hour_angle) + settings.dusk_offset_min as f64 * 60.0,
+                night: true,
+            },
+        ]
+    }
+
+    /// The last change up to now and the next one after it.
+    fn changes_around(&self, now: f64) -> (Option<Change>, Option<Change>) {
+        let today = (now / SECS_PER_DAY).floor() as i64;
+        let mut changes: Vec<Change> = (today - DAYS_AROUND..=today + DAYS_AROUND)
+            .flat_map(|day| self.changes_on(day))
+            .collect();
+        changes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let last = changes.iter().rev().find(|change| change.time <= now);
+        let next = changes.iter().find(|change| change.time > now);
+        (last.copied(), next.copied())
+    }
+}
+
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64()
+}
+
+#[async_trait]
+impl Source for SolarSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let (last, _) = self.changes_around(unix_now());
+        Ok(last.is_some_and(|change| change.night))
+    }
+
+    /// Completes at dusk and dawn, so that the source changes on time.
+    async fn wait_for_change(&self) {
+        let now = unix_now();
+        let wait = match self.changes_around(now) {
+            (_, Some(next)) => {
+                let kind = if next.night { "Dusk" } else { "Dawn" };
+                debug!("{} in {:.0} min", kind, (next.time - now) / 60.0);
+                next.time - now
+            }
+            // Only possible far into a polar night or day, check again tomorrow.
+            (_, None) => SECS_PER_DAY,
+        };
+        sleep(Duration::from_secs_f64(wait.max(0.0))).await
+    }
+}
@@ -3,26 +3,29 @@
 use crate::log::panic_to_string;
 use crate::settings::{SourceBaseSettings, SourceSettings};
 use crate::source::{Source, SourceIsActiveResult};
-use anyhow::anyhow;
-use bidirectional_channel::{bounded, ReceivedRequest, Requester, Responder};
+use crate::ssh::{self, SshSettings};
 use futures::FutureExt;
 use serde::Deserialize;
-use ssh2::{Channel, Session};
-use std::convert::Infallible;
-use std::error::Error;
-use std::io::Read;
-use std::net::TcpStream;
 use std::panic::AssertUnwindSafe;
 use std::time::Duration;
+use tokio::select;
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, instrument, warn};
 
 const MAX_CONNECTION_TRIES: usize = 3;
+/// Prints the number of Steam streaming client processes.
+const COUNT_STREAMING: &str = "sh -c 'ps | grep -v grep | grep -c streaming_client || true'";
 
 #[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "SteamlinkSourceSettings")
+)]
 pub struct Settings {
-    pub host: String,
-    pub user: String,
-    pub pass: String,
+    #[serde(flatten)]
+    pub ssh: SshSettings,
     #[serde(flatten)]
     base: SourceBaseSettings,
 }
@@ -34,31 +37,34 @@ impl SourceSettings for Settings {
         &self.base
     }
 
-    fn create_source(&self) -> Result<Self::Impl, Box<dyn Error>> {
-        SteamLinkSource::new(self.clone()).map_err(Into::into)
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        SteamLinkSource::new(self.clone())
     }
 }
 
+/// A poll request to the watcher. The watcher abandons it once the receiving end is dropped,
+/// because the poll timed out, or once the poll is cancelled.
+struct PollRequest {
+    reply: oneshot::Sender<Result<bool, crate::error::Error>>,
+    cancel: CancellationToken,
+}
+
 pub struct SteamLinkSource {
     settings: Settings,
-    requester: Requester<(), Result<bool, anyhow::Error>>,
+    requests: mpsc::Sender<PollRequest>,
 }
 
 impl SteamLinkSource {
-    fn new(settings: Settings) -> Result<Self, Infallible> {
-        let (requester, responder) = bounded::<(), Result<bool, anyhow::Error>>(1);
-        Self::ssh_thread(settings.clone(), responder);
-        Ok(Self {
-            settings,
-            requester,
-        })
+    fn new(settings: Settings) -> Result<Self, crate::error::Error> {
+        let pass = settings.ssh.resolve_pass()?;
+        let (requests, receiver) = mpsc::channel(1);
+        Self::ssh_thread(settings.clone(), pass, receiver);
+        Ok(Self { settings, requests })
     }
 
-    #[instrument("source-steamlink:thread")]
-    fn ssh_thread(
-        settings: Settings,
-        responder: Responder<ReceivedRequest<(), Result<bool, anyhow::Error>>>,
-    ) {
+    #[instrument("source-steamlink:thread", skip(pass))]
+    fn ssh_thread(settings: Settings, pass: String, receiver: mpsc::Receiver<PollRequest>) {
+        let receiver = tokio::sync::Mutex::new(receiver);
         let mut opt_set_disabled_after: Option<usize> = None;
         let wait_timeout = (settings.base.timeout_sec / 2) as u64;
 
@@ -69,10 +75,27 @@ impl SteamLinkSource {
                         loop {
                             debug!("Steam Link watcher thread receiving.");
 
-                            if let Ok(req) = responder.recv().await {
-                                let res_active: Result<bool, anyhow::Error> = Self::make_session(&settings).map_err(Into::into)
-                                    .and_then(|sess| sess.channel_session().map_err(Into::into))
-                                    .and_then(|chann| Self::check_active(chann).map_err(Into::into));
+                            if let Some(PollRequest { reply: mut req, cancel }) = receiver.lock().await.recv().await {
+                                if req.is_closed() || cancel.is_cancelled() {
+                                    debug!("Steam Link watcher thread skipping timed out request.");
+                                    continue;
+                                }
+                                // If the request times out or is cancelled, the SSH session is shut
+                                // down, so that no blocked thread is left behind and the next request
+                                // is handled right away.
+                                let session = cancel.child_token();
+                                let res_active = select! {
+                                    result = Self::check_active(&settings.ssh, &pass, &session) => result,
+                                    _ = req.closed() => {
+                                        debug!("Steam Link watcher thread abandoning timed out request.");
+                                        session.cancel();
+                                        continue;
+                                    }
+                                    _ = cancel.cancelled() => {
+                                        debug!("Steam Link watcher thread abandoning cancelled request.");
+                                        continue;
+                                    }
+                                };
 
                                 debug!("Steam Link watcher thread result: {:?}", res_active);
                                 match res_active {
@@ -81,24 +104,24 @@ impl SteamLinkSource {
                                         if res {
                                             opt_set_disabled_after = Some(MAX_CONNECTION_TRIES);
                                         }
-                                        req.respond(Ok(res)).ok();
+                                        req.send(Ok(res)).ok();
                                     }
                                     Err(e) => {
                                         match opt_set_disabled_after {
                                             None => {
                                                 warn!("Steam Link watcher thread encountered an error in the connection: {}. Restarting attempts in {} seconds.", e, wait_timeout);
-                                                req.respond(Err(e)).ok();
+                                                req.send(Err(e)).ok();
                                             }
                                             Some(set_disabled_after) => {
                                                 opt_set_disabled_after = set_disabled_after.checked_sub(1);
                                                 match opt_set_disabled_after {
                                                     None => {
                                                         warn!("Steam Link watcher thread continues to fail connecting. Assuming Link went offline.");
-                                                        req.respond(Ok(false)).ok();
+                                                        req.send(Ok(false)).ok();
                                                     }
                                                     Some(set_disabled_after) => {
                                                         warn!("Steam Link watcher thread encountered an error in the connection: {}. It may be offline now, retrying earliest in {} seconds. Max retries before assuming offline: {}", e, wait_timeout, set_disabled_after);
-                                                        req.respond(Err(e)).ok();
+                                                        req.send(Err(e)).ok();
                                                     }
                                                 }
                                             }
@@ -107,7 +130,7 @@ impl SteamLinkSource {
                                     }
                                 }
                             } else {
-                                error!("Steam Link watcher thread failed reading from responder endpoint. Exiting thread.");
+                                debug!("Steam Link source dropped. Exiting thread.");
                                 return;
                             }
                         }
@@ -122,40 +145,23 @@ impl SteamLinkSource {
                         );
                         tokio::time::sleep(Duration::from_secs(wait_timeout)).await;
                     }
-                    Ok(()) => {
-                        panic!(
-                            "Steam Link watcher thread exited because of failed request/responder."
-                        );
-                    }
+                    Ok(()) => return,
                 };
             }
         });
     }
 
-    fn make_session(settings: &Settings) -> Result<Session, anyhow::Error> {
-        let tcp = TcpStream::connect(&settings.host)?;
-        let mut sess = Session::new()?;
-        sess.set_tcp_stream(tcp);
-        sess.handshake()?;
-        sess.userauth_password(&settings.user, &settings.pass)?;
-        if sess.authenticated() {
-            Ok(sess)
-        } else {
-            Err(anyhow!(
-                "Failed to authenticate with Steam Link via SSH via password."
-            ))
-        }
-    }
-
-    fn check_active(mut channel: Channel) -> Result<bool, anyhow::Error> {
-        channel.exec("sh -c 'ps | grep streaming_client | grep -v grep'")?;
-        let mut buffer = String::new();
-        channel.read_to_string(&mut buffer)?;
-        channel.wait_close()?;
-        match channel.exit_status()? {
-            0 => Ok(true),
-            1 => Ok(false),
-            v => Err(anyhow!("unexpected steam grep exit code: {v}")),
+    async fn check_active(
+        ssh: &SshSettings,
+        pass: &str,
+        cancel: &CancellationToken,
+    ) -> Result<bool, crate::error::Error> {
+        let output = ssh::exec(ssh, pass, COUNT_STREAMING, cancel).await?;
+        match output.trim().parse::<u32>() {
+            Ok(count) => Ok(count > 0),
+            Err(_) => Err(crate::error::Error::protocol(format!(
+                "unexpected output counting streaming clients: {output:?}"
+            ))),
         }
     }
 }
@@ -166,7 +172,15 @@ impl Source for SteamLinkSource {
         self.settings.base()
     }
 
-    async fn is_active(&self) -> SourceIsActiveResult {
-        self.requester.send(()).await?.map_err(Into::into)
+    async fn is_active(&self, cancel: &CancellationToken) -> SourceIsActiveResult {
+        let (reply, response) = oneshot::channel();
+        let cancel = cancel.clone();
+        self.requests
+            .send(PollRequest { reply, cancel })
+            .await
+            .map_err(|_| "Steam Link watcher thread is not running")?;
+        response
+            .await
+            .map_err(|_| "Steam Link watcher thread dropped the request")?
     }
 }
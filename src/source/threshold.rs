@@ -0,0 +1,37 @@
+#![cfg(any(
+    feature = "source-cpu-load",
+    feature = "source-gpu",
+    feature = "source-pdu-outlet"
+))]
+
+use serde::Deserialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Thresholds of a measured value, to be used with `#[serde(flatten)]`.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct ThresholdSettings {
+    /// The source becomes active once the value reaches this.
+    pub on_above: f64,
+    /// The source becomes inactive again once the value drops below this. Defaults to
+    /// `on-above`, without any hysteresis.
+    pub off_below: Option<f64>,
+}
+
+/// Whether a measured value is above its threshold, with hysteresis.
+#[derive(Default)]
+pub struct Hysteresis {
+    active: AtomicBool,
+}
+
+impl Hysteresis {
+    /// Update with a new measurement and return whether the value counts as above the threshold.
+    pub fn update(&self, value: f64, settings: &ThresholdSettings) -> bool {
+        let off_below = settings.off_below.unwrap_or(settings.on_above);
+        let was_active = self.active.load(Ordering::Acquire);
+        let active = value >= settings.on_above || (was_active && value >= off_below);
+        self.active.store(active, Ordering::Release);
+        active
+    }
+}
@@ -0,0 +1,24 @@
+#![cfg(any(feature = "source-playstation", feature = "source-xbox"))]
+
+use std::io;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::timeout;
+
+/// Send a single discovery request to the host and wait for the first response, for at most
+/// `wait`. Returns `None` if no response arrived in time.
+pub async fn probe(
+    host: &str,
+    port: u16,
+    request: &[u8],
+    wait: Duration,
+) -> io::Result<Option<Vec<u8>>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect((host, port)).await?;
+    socket.send(request).await?;
+    let mut buf = [0; 2048];
+    match timeout(wait, socket.recv(&mut buf)).await {
+        Ok(result) => Ok(Some(buf[..result?].to_vec())),
+        Err(_) => Ok(None),
+    }
+}
@@ -0,0 +1,164 @@
+#![cfg(feature = "source-ups")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::error::Error;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Protocol {
+    /// The network protocol of upsd, the server of Network UPS Tools.
+    Nut,
+    /// The network information server of apcupsd.
+    Apcupsd,
+}
+
+impl Protocol {
+    fn default_port(self) -> u16 {
+        match self {
+            Protocol::Nut => 3493,
+            Protocol::Apcupsd => 3551,
+        }
+    }
+}
+
+/// When the source is active.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ActiveOn {
+    /// While the UPS runs on battery.
+    #[default]
+    Battery,
+    /// While the UPS runs on line power, e.g. to only turn on the sinks of a route with
+    /// `trigger-mode = "all"` while it does.
+    LinePower,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "UpsSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// Host name or IP address of the server.
+    pub host: String,
+    /// Port of the server, by default the usual one of the protocol.
+    pub port: Option<u16>,
+    pub protocol: Protocol,
+    /// Name of the UPS on the NUT server.
+    #[serde(default = "default_ups")]
+    pub ups: String,
+    #[serde(default)]
+    pub active_on: ActiveOn,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+fn default_ups() -> String {
+    "ups".to_string()
+}
+
+impl SourceSettings for Settings {
+    type Impl = UpsSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> std::result::Result<Self::Impl, crate::error::Error> {
+        Ok(UpsSource {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// Active depending on whether a UPS runs on line power or on battery.
+pub struct UpsSource {
+    settings: Settings,
+}
+
+impl UpsSource {
+    async fn connect(&self) -> Result<TcpStream> {
+        let port = self.settings.port;
+        let port = port.unwrap_or(self.settings.protocol.default_port());
+        Ok(TcpStream::connect((self.settings.host.as_str(), port)).await?)
+    }
+
+    /// Whether the UPS is on battery according to its `ups.status`, e.g. `OB LB`.
+    async fn nut_on_battery(&self) -> Result<bool> {
+        let ups = &self.settings.ups;
+        let mut stream = BufReader::new(self.connect().await?);
+        stream
+            .get_mut()
+            .write_all(format!("GET VAR {ups} ups.status\n").as_bytes())
+            .await?;
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+        stream.get_mut().write_all(b"LOGOUT\n").await.ok();
+        let line = line.trim();
+        if let Some(error) = line.strip_prefix("ERR ") {
+            return Err(format!("server error {error}").into());
+        }
+        let status = line
+            .strip_prefix(&format!("VAR {ups} ups.status "))
+            .ok_or_else(|| format!("unexpected response {line}"))?
+            .trim_matches('"');
+        debug!("Status {status}");
+        Ok(status.split(' ').any(|flag| flag == "OB"))
+    }
+
+    /// Whether the UPS is on battery according to the `STATUS` record, e.g. `ONBATT`.
+    async fn apcupsd_on_battery(&self) -> Result<bool> {
+        let mut stream = self.connect().await?;
+        stream.write_u16(6).await?;
+        stream.write_all(b"status").await?;
+        // Records of "KEY : VALUE", ending with an empty one.
+        let mut status = None;
+        loop {
+            let len = stream.read_u16().await? as usize;
+            if len == 0 {
+                break;
+            }
+            let mut record = vec![0; len];
+            stream.read_exact(&mut record).await?;
+            let record = String::from_utf8_lossy(&record);
+            if let Some((key, value)) = record.split_once(':') {
+                if key.trim() == "STATUS" {
+                    status = Some(value.trim().to_string());
+                }
+            }
+        }
+        let status = status.ok_or("status without STATUS record")?;
+        debug!("Status {status}");
+        Ok(status.split(' ').any(|flag| flag == "ONBATT"))
+    }
+}
+
+#[async_trait]
+impl Source for UpsSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        let on_battery = match self.settings.protocol {
+            Protocol::Nut => self.nut_on_battery().await?,
+            Protocol::Apcupsd => self.apcupsd_on_battery().await?,
+        };
+        Ok(match self.settings.active_on {
+            ActiveOn::LinePower => !on_battery,
+            ActiveOn::Battery => on_battery,
+        })
+    }
+}
@@ -0,0 +1,119 @@
+#![cfg(feature = "source-webhook")]
+
+use crate::settings::{PassSettings, SourceBaseSettings, SourceSettings};
+use crate::source::{PushError, Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::future::pending;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::select;
+use tokio::sync::Notify;
+use tokio::time::sleep_until;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "WebhookSourceSettings")
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct Settings {
+    /// The shared secret that has to be sent along.
+    #[serde(flatten)]
+    pub pass: PassSettings,
+    /// Turn off if the state was not pushed again for this many seconds.
+    pub expire_after_sec: Option<u64>,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = WebhookSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        let secret = self
+            .pass
+            .resolve()?
+            .ok_or("a secret is required for webhooks")?;
+        Ok(WebhookSource {
+            settings: self.clone(),
+            secret,
+            pushed: Mutex::new(None),
+            changed: Notify::new(),
+        })
+    }
+}
+
+/// Active as pushed to `/webhook/<name>` of the HTTP API, off until the first push.
+pub struct WebhookSource {
+    settings: Settings,
+    secret: String,
+    /// The last pushed state and when it was pushed.
+    pushed: Mutex<Option<(bool, Instant)>>,
+    changed: Notify,
+}
+
+impl WebhookSource {
+    /// When the pushed state expires, if it does.
+    fn expires_at(&self) -> Option<Instant> {
+        let expire_after = Duration::from_secs(self.settings.expire_after_sec?);
+        let (active, pushed_at) = (*self.pushed.lock().unwrap())?;
+        active.then_some(pushed_at + expire_after)
+    }
+}
+
+/// Compare without exiting early, so that the secret can't be guessed from response times.
+fn secret_matches(expected: &str, given: &str) -> bool {
+    expected.len() == given.len()
+        && expected
+            .bytes()
+            .zip(given.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[async_trait]
+impl Source for WebhookSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        if self.expires_at().is_some_and(|at| at <= Instant::now()) {
+            return Ok(false);
+        }
+        Ok(self
+            .pushed
+            .lock()
+            .unwrap()
+            .is_some_and(|(active, _)| active))
+    }
+
+    async fn wait_for_change(&self) {
+        let expiry = async {
+            match self.expires_at() {
+                Some(at) if at > Instant::now() => sleep_until(at.into()).await,
+                _ => pending().await,
+            }
+        };
+        select! {
+            _ = self.changed.notified() => {},
+            _ = expiry => debug!("Pushed state expired"),
+        }
+    }
+
+    fn push(&self, secret: &str, active: bool) -> Result<(), PushError> {
+        if !secret_matches(&self.secret, secret) {
+            return Err(PushError::Unauthorized);
+        }
+        *self.pushed.lock().unwrap() = Some((active, Instant::now()));
+        self.changed.notify_one();
+        Ok(())
+    }
+}
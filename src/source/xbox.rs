@@ -0,0 +1,83 @@
+#![cfg(feature = "source-xbox")]
+
+use crate::settings::{SourceBaseSettings, SourceSettings};
+use crate::source::{udp_probe, Source, SourceIsActiveResult};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+/// UDP port of the SmartGlass protocol.
+const SMARTGLASS_PORT: u16 = 5050;
+const DISCOVERY_REQUEST: u16 = 0xDD00;
+const DISCOVERY_RESPONSE: u16 = 0xDD01;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(
+    feature = "schema",
+    derive(schemars::JsonSchema),
+    schemars(rename = "XboxSourceSettings")
+)]
+pub struct Settings {
+    /// Host name or IP address of the console.
+    pub host: String,
+    #[serde(flatten)]
+    base: SourceBaseSettings,
+}
+
+impl SourceSettings for Settings {
+    type Impl = XboxSource;
+
+    fn base(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    fn create_source(&self) -> Result<Self::Impl, crate::error::Error> {
+        Ok(XboxSource {
+            settings: self.clone(),
+        })
+    }
+}
+
+/// Active while the console answers SmartGlass discovery requests, which it only does while
+/// it is powered on.
+pub struct XboxSource {
+    settings: Settings,
+}
+
+impl XboxSource {
+    fn discovery_request() -> Vec<u8> {
+        // Flags, client type (Android), minimum and maximum protocol version.
+        let payload = [0, 0, 0, 0, 0, 8, 0, 0, 0, 2];
+        let mut packet = Vec::with_capacity(6 + payload.len());
+        packet.extend_from_slice(&DISCOVERY_REQUEST.to_be_bytes());
+        packet.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        // Packet version.
+        packet.extend_from_slice(&0u16.to_be_bytes());
+        packet.extend_from_slice(&payload);
+        packet
+    }
+}
+
+#[async_trait]
+impl Source for XboxSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        self.settings.base()
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        // Leave time for the poll timeout to not trigger if the console does not answer.
+        let wait = Duration::from_secs(self.settings.base.timeout_sec as u64) / 2;
+        let request = Self::discovery_request();
+        let Some(buf) =
+            udp_probe::probe(&self.settings.host, SMARTGLASS_PORT, &request, wait).await?
+        else {
+            debug!("No discovery response, assuming the console is off.");
+            return Ok(false);
+        };
+        match buf.get(..2) {
+            Some(&[hi, lo]) if u16::from_be_bytes([hi, lo]) == DISCOVERY_RESPONSE => Ok(true),
+            _ => Err(format!("unexpected discovery response: {buf:02x?}").into()),
+        }
+    }
+}
@@ -0,0 +1,75 @@
+#![cfg(feature = "ssh")]
+
+use crate::settings::PassSettings;
+use serde::Deserialize;
+use ssh2::Session;
+use std::error::Error;
+use std::io::Read;
+use std::net::{Shutdown, TcpStream};
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub struct SshSettings {
+    /// Host and port, e.g. `htpc.local:22`.
+    pub host: String,
+    pub user: String,
+    #[serde(flatten)]
+    pub pass: PassSettings,
+}
+
+impl SshSettings {
+    pub fn resolve_pass(&self) -> std::result::Result<String, crate::error::Error> {
+        Ok(self
+            .pass
+            .resolve()?
+            .ok_or("a password is required for SSH")?)
+    }
+}
+
+/// Run the command on the host and return its output. Once `cancel` is cancelled, the
+/// connection is shut down, so that the blocking SSH session ends early.
+pub async fn exec(
+    ssh: &SshSettings,
+    pass: &str,
+    command: &str,
+    cancel: &CancellationToken,
+) -> Result<String> {
+    let stream = tokio::net::TcpStream::connect(&ssh.host)
+        .await?
+        .into_std()?;
+    stream.set_nonblocking(false)?;
+    let connection = stream.try_clone()?;
+    let (user, pass, command) = (ssh.user.clone(), pass.to_string(), command.to_string());
+    let session =
+        tokio::task::spawn_blocking(move || exec_blocking(stream, &user, &pass, &command));
+    let cancel = cancel.clone();
+    let watcher = tokio::spawn(async move {
+        cancel.cancelled().await;
+        debug!("Aborting SSH session");
+        connection.shutdown(Shutdown::Both).ok();
+    });
+    let result = session.await;
+    watcher.abort();
+    result?
+}
+
+fn exec_blocking(stream: TcpStream, user: &str, pass: &str, command: &str) -> Result<String> {
+    let mut sess = Session::new()?;
+    sess.set_tcp_stream(stream);
+    sess.handshake()?;
+    sess.userauth_password(user, pass)?;
+    let mut channel = sess.channel_session()?;
+    channel.exec(command)?;
+    let mut buffer = String::new();
+    channel.read_to_string(&mut buffer)?;
+    channel.wait_close()?;
+    match channel.exit_status()? {
+        0 => Ok(buffer),
+        v => Err(format!("\"{command}\" failed with exit code {v}").into()),
+    }
+}
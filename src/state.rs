@@ -1,31 +1,54 @@
-use crate::async_util::Wakeup;
+use crate::async_util::{Drain, Wakeup};
+use crate::control::{SinkStatus, SourceStatus, StatusReport};
+use crate::error::ErrorKind;
+use crate::event::Event;
+#[cfg(any(feature = "http", test))]
+use crate::health::{ComponentHealth, HealthReport};
 use crate::identity::{Identity, IsSink, IsSource, Named};
-use crate::log::{panic_to_string, pwrst_log};
-use crate::settings::GeneralSettings;
-use crate::sink::Sink;
+use crate::log::{panic_to_string, pwrst_log, ErrorLog, RepeatedErrors};
+use crate::neighbor;
+use crate::settings::{
+    GeneralSettings, OnError, OnUnknown, RouteSettings, ShutdownAction, SleepySettings,
+    TriggerMode, ZoneSettings,
+};
+use crate::sink::{hook, Sink, SinkCommandResult};
+#[cfg(feature = "source-webhook")]
+use crate::source::PushError;
 use crate::source::Source;
-use futures::future::{select_all, Fuse, FusedFuture, LocalBoxFuture};
+use crate::statistics::{unix_now, PeriodReport, Statistics, StatisticsReport, SECS_PER_DAY};
+use futures::future::join_all;
 use futures::FutureExt;
+use serde::{Deserialize, Serialize};
 use std::any::Any;
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::error::Error;
-use std::iter::once;
+use std::fs::OpenOptions;
+use std::future::pending;
+use std::io::Write;
 use std::panic::AssertUnwindSafe;
-use std::rc::{Rc, Weak};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
 use tokio::select;
-use tokio::time::{sleep, timeout};
+use tokio::sync::{broadcast, mpsc, Notify};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::time::error::Elapsed;
+use tokio::time::{sleep, sleep_until, timeout, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, info_span, trace, warn, Instrument};
 
-type StateCheckFut<'a> = Fuse<LocalBoxFuture<'a, ()>>;
+const STATISTICS_SAVE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Time after the end of a day the reports are made at, so that they're not off by a day if the
+/// clock and the timer disagree slightly.
+const REPORT_DELAY: Duration = Duration::from_secs(60);
 
 #[atomic_enum]
-#[derive(PartialEq, Eq, Default)]
-enum PowerState {
+#[derive(PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerState {
     On,
     Off,
+    /// Only for sinks, see [`crate::sink::Sink::standby`].
+    Standby,
     #[default]
     Unknown,
 }
@@ -39,6 +62,17 @@ impl From<bool> for PowerState {
     }
 }
 
+impl std::fmt::Display for PowerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PowerState::On => write!(f, "on"),
+            PowerState::Off => write!(f, "off"),
+            PowerState::Standby => write!(f, "standby"),
+            PowerState::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
 /// If this fails, the variant was unknown.
 impl TryFrom<PowerState> for bool {
     type Error = ();
@@ -46,7 +80,7 @@ impl TryFrom<PowerState> for bool {
     fn try_from(value: PowerState) -> Result<Self, Self::Error> {
         match value {
             PowerState::On => Ok(true),
-            PowerState::Off => Ok(false),
+            PowerState::Off | PowerState::Standby => Ok(false),
             PowerState::Unknown => Err(()),
         }
     }
@@ -55,6 +89,23 @@ impl TryFrom<PowerState> for bool {
 struct SourceState {
     source: IsSource,
     current_power_state: AtomicPowerState,
+    consecutive_failures: AtomicU32,
+    last_poll: Mutex<Option<SystemTime>>,
+    last_error: Mutex<Option<String>>,
+    last_error_kind: Mutex<Option<ErrorKind>>,
+    errors: RepeatedErrors,
+    /// When the source is polled next, if it is waiting for its next poll.
+    next_poll: Mutex<Option<Instant>>,
+    last_success: Mutex<Option<SystemTime>>,
+    /// Since when the source has been failing, if its last poll failed.
+    failing_since: Mutex<Option<Instant>>,
+    consecutive_timeouts: AtomicU32,
+    /// Whether the source seems to be stuck, see [`crate::settings::WatchdogSettings`].
+    degraded: AtomicBool,
+    /// Until when the source is still reported as on, if it turned off but is lingering.
+    lingering_until: Mutex<Option<Instant>>,
+    /// Woken when one of the sources the source combines changes.
+    members_changed: Notify,
 }
 
 impl SourceState {
@@ -62,51 +113,648 @@ impl SourceState {
         Self {
             source: IsSource(source),
             current_power_state: AtomicPowerState::new(PowerState::Unknown),
+            consecutive_failures: AtomicU32::new(0),
+            last_poll: Mutex::new(None),
+            last_error: Mutex::new(None),
+            last_error_kind: Mutex::new(None),
+            errors: RepeatedErrors::default(),
+            next_poll: Mutex::new(None),
+            last_success: Mutex::new(None),
+            failing_since: Mutex::new(None),
+            consecutive_timeouts: AtomicU32::new(0),
+            degraded: AtomicBool::new(false),
+            lingering_until: Mutex::new(None),
+            members_changed: Notify::new(),
+        }
+    }
+    fn get_sleep_before_check(&self) -> Duration {
+        let is_on = self.current_power_state.load(Ordering::Acquire) == PowerState::On;
+        let sleep = self.source.base_settings().poll_interval_sec.next(is_on);
+        // Check again as soon as lingering ends, so that turning off isn't delayed further.
+        match *self.lingering_until.lock().unwrap() {
+            Some(until) => sleep.min(until.saturating_duration_since(Instant::now())),
+            None => sleep,
+        }
+    }
+    /// The power state to report for a newly determined state, keeping the source on while it
+    /// lingers after turning off.
+    fn linger(&self, new_state: PowerState) -> PowerState {
+        let mut lingering_until = self.lingering_until.lock().unwrap();
+        let Some(linger_sec) = self.source.base_settings().linger_sec else {
+            return new_state;
+        };
+        if new_state != PowerState::Off
+            || (lingering_until.is_none()
+                && self.current_power_state.load(Ordering::Acquire) != PowerState::On)
+        {
+            *lingering_until = None;
+            return new_state;
+        }
+        let until = *lingering_until.get_or_insert_with(|| {
+            debug!(
+                "{} Turned off, lingering for {} sec.",
+                self.source.identity(),
+                linger_sec
+            );
+            Instant::now() + Duration::from_secs(linger_sec)
+        });
+        if Instant::now() < until {
+            PowerState::On
+        } else {
+            *lingering_until = None;
+            new_state
+        }
+    }
+    /// Returns the sleepy settings if the source has them and is currently considered asleep.
+    fn asleep(&self) -> Option<&SleepySettings> {
+        self.source
+            .base_settings()
+            .sleepy
+            .as_ref()
+            .filter(|sleepy| {
+                self.consecutive_failures.load(Ordering::Acquire) >= sleepy.after_failures
+            })
+    }
+    /// Wait until the source should be checked next.
+    async fn wait_before_check(&self) {
+        match self.asleep() {
+            None => select!(
+                _ = self.sleep_until_poll(self.get_sleep_before_check()) => {},
+                _ = self.source.wait_for_change() => {
+                    *self.next_poll.lock().unwrap() = None;
+                    debug!("{} Changed, checking right away.", self.source.identity());
+                }
+                _ = self.members_changed.notified() => {
+                    *self.next_poll.lock().unwrap() = None;
+                    debug!("{} Combined sources changed, re-evaluating.", self.source.identity());
+                }
+            ),
+            Some(sleepy) => {
+                let probe = self.sleep_until_poll(Duration::from_secs(sleepy.probe_interval_sec));
+                match &sleepy.wake_hint {
+                    None => probe.await,
+                    Some(wake_hint) => select!(
+                        _ = probe => {},
+                        _ = Self::wait_for_wake_hint(wake_hint, sleepy.wake_hint_interval_sec) => {
+                            debug!("{} Wake hint {} seen, probing.", self.source.identity(), wake_hint);
+                        }
+                    ),
+                }
+            }
+        }
+    }
+    /// Sleep for the given duration, remembering when the next poll is due.
+    async fn sleep_until_poll(&self, duration: Duration) {
+        *self.next_poll.lock().unwrap() = Some(Instant::now() + duration);
+        sleep(duration).await;
+        *self.next_poll.lock().unwrap() = None;
+    }
+    /// Wait until the address shows up in the neighbor table. Never completes if the neighbor
+    /// table can not be read.
+    async fn wait_for_wake_hint(wake_hint: &str, interval_sec: u64) {
+        loop {
+            match neighbor::contains(wake_hint) {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(e) => {
+                    warn!("Failed reading neighbor table for wake hint: {}", e);
+                    return pending().await;
+                }
+            }
+            sleep(Duration::from_secs(interval_sec)).await;
+        }
+    }
+    /// The power state to assume for the source according to its error settings, given that
+    /// its last poll failed.
+    fn error_fallback(&self) -> Option<PowerState> {
+        match self.source.base_settings().on_error {
+            OnError::Keep => None,
+            OnError::AssumeOff => Some(PowerState::Off),
+            OnError::AssumeUnknownAfterSec(sec) => self
+                .failing_since
+                .lock()
+                .unwrap()
+                .filter(|since| since.elapsed() >= Duration::from_secs(sec))
+                .map(|_| PowerState::Unknown),
+        }
+    }
+    /// Record a successful poll. Returns whether the previous poll failed.
+    fn record_success(&self) -> bool {
+        if self.asleep().is_some() {
+            info!(
+                "{} Woke up, resuming regular polling.",
+                self.source.identity()
+            );
+        }
+        if self.degraded.swap(false, Ordering::AcqRel) {
+            info!("{} Responding again.", self.source.identity());
+        }
+        self.consecutive_timeouts.store(0, Ordering::Release);
+        let failures = self.consecutive_failures.swap(0, Ordering::AcqRel);
+        if failures > 0 {
+            info!(
+                "{} Recovered after {} failed polls.",
+                self.source.identity(),
+                failures
+            );
+        }
+        self.errors.clear();
+        *self.last_poll.lock().unwrap() = Some(SystemTime::now());
+        *self.last_success.lock().unwrap() = Some(SystemTime::now());
+        *self.last_error.lock().unwrap() = None;
+        *self.last_error_kind.lock().unwrap() = None;
+        *self.failing_since.lock().unwrap() = None;
+        failures > 0
+    }
+    /// Record a failed or timed out poll. Returns the number of consecutive failures.
+    fn record_failure(&self, error: String, kind: ErrorKind) -> u32 {
+        self.failing_since
+            .lock()
+            .unwrap()
+            .get_or_insert_with(Instant::now);
+        *self.last_poll.lock().unwrap() = Some(SystemTime::now());
+        *self.last_error.lock().unwrap() = Some(error);
+        *self.last_error_kind.lock().unwrap() = Some(kind);
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::AcqRel) + 1;
+        if let Some(sleepy) = &self.source.base_settings().sleepy {
+            if failures == sleepy.after_failures {
+                info!(
+                    "{} Seems to be asleep, probing only every {} sec.",
+                    self.source.identity(),
+                    sleepy.probe_interval_sec
+                );
+            }
         }
+        failures
     }
-    fn get_sleep_before_check(&self) -> u64 {
-        match self.current_power_state.load(Ordering::Acquire) {
-            PowerState::On => self.source.base_settings().poll_interval_sec.on,
-            _ => self.source.base_settings().poll_interval_sec.off,
+    /// Count a timed out poll, and escalate according to the watchdog settings if the source
+    /// seems to be stuck.
+    fn record_timeout(&self) {
+        if self.asleep().is_some() {
+            return;
+        }
+        let watchdog = &self.source.base_settings().watchdog;
+        let timeouts = self.consecutive_timeouts.fetch_add(1, Ordering::AcqRel) + 1;
+        if timeouts < watchdog.after_timeouts {
+            return;
+        }
+        if !self.degraded.swap(true, Ordering::AcqRel) {
+            error!(
+                "{} Timed out {} times in a row, it seems to be stuck.",
+                self.source.identity(),
+                timeouts
+            );
+        }
+        if watchdog.recreate {
+            self.consecutive_timeouts.store(0, Ordering::Release);
+            match self.source.recreate() {
+                Ok(()) => info!("{} Re-created.", self.source.identity()),
+                Err(e) => error!("{} Failed re-creating: {}", self.source.identity(), e),
+            }
+        }
+    }
+}
+
+/// Outcome of trying to send a command to a sink.
+enum CommandOutcome {
+    /// The command succeeded.
+    Success,
+    /// The command was not sent yet, because a retry or the minimum time between toggles is
+    /// pending. It should be tried again after the duration.
+    Deferred(Duration),
+    /// The command failed, it should be tried again after the duration.
+    RetryIn(Duration),
+    /// The command failed for the given number of attempts and will not be retried until the
+    /// next source transition.
+    GivingUp(u32),
+    /// The command was given up on before and is not sent until the next source transition.
+    GaveUp,
+}
+
+/// Staggers turning on sinks, see [`GeneralSettings::power_on_delay_sec`].
+struct PowerOnStagger {
+    default_delay_sec: Option<u64>,
+    /// When the latest sink was or is going to be turned on.
+    last_on: Mutex<Option<Instant>>,
+}
+
+impl PowerOnStagger {
+    /// Wait until the sink may be turned on, according to its delay after the previous one.
+    async fn wait_turn(&self, sink: &IsSink) {
+        let delay = sink
+            .base_settings()
+            .power_on_delay_sec
+            .or(self.default_delay_sec)
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let now = Instant::now();
+        // Reserve the slot right away, so that sinks turning on concurrently queue up.
+        let slot = {
+            let mut last_on = self.last_on.lock().unwrap();
+            let slot = last_on.map_or(now, |last_on| now.max(last_on + delay));
+            *last_on = Some(last_on.map_or(slot, |last_on| last_on.max(slot)));
+            slot
+        };
+        if slot > now {
+            debug!(
+                "{} Waiting {:.1} sec to stagger turning on.",
+                sink.identity(),
+                (slot - now).as_secs_f64()
+            );
+            sleep_until(slot).await;
         }
     }
 }
 
 struct SinkState {
     sink: IsSink,
+    /// Index of the zone of the sink in [`State::zones`].
+    zone: usize,
+    /// Indices of the routes of the sink in [`State::routes`]. If empty, the sink follows the
+    /// sources of its zone.
+    routes: Box<[usize]>,
     current_power_state: AtomicPowerState,
     should_turn_on: AtomicBool,
+    last_command: Mutex<Option<SystemTime>>,
+    last_error: Mutex<Option<String>>,
+    last_error_kind: Mutex<Option<ErrorKind>>,
+    errors: RepeatedErrors,
+    last_success: Mutex<Option<SystemTime>>,
+    /// Failed commands in a row. Unlike `failed_attempts`, this is not reset on source
+    /// transitions.
+    consecutive_failures: AtomicU32,
+    failed_attempts: AtomicU32,
+    next_retry: Mutex<Option<Instant>>,
+    gave_up: AtomicBool,
+    /// When the power state was last changed successfully.
+    last_toggle: Mutex<Option<Instant>>,
+    /// When the sink was last turned off successfully.
+    last_off: Mutex<Option<Instant>>,
+    /// When all sources relevant for this sink are off, the time at which it will be turned off.
+    next_poweroff_write_time: Mutex<Option<Instant>>,
+    /// With `confirm-off`, when the announced window before turning the sink off ends.
+    confirm_off_until: Mutex<Option<Instant>>,
+    /// If set, the sink is held in this state regardless of the sources.
+    forced: Mutex<Option<bool>>,
+    /// Whether no commands are sent until the sink is reset manually.
+    needs_reset: AtomicBool,
 }
 
 impl SinkState {
-    fn new(sink: Box<dyn Sink>) -> Self {
+    fn new(sink: Box<dyn Sink>, zone: usize, routes: Box<[usize]>) -> Self {
+        let sink = IsSink(sink);
+        if sink.base_settings().standby_after_sec.is_some() && !sink.supports_standby() {
+            warn!(
+                "{} Does not support standby, it is only turned off.",
+                sink.identity()
+            );
+        }
         Self {
-            sink: IsSink(sink),
+            sink,
+            zone,
+            routes,
             current_power_state: AtomicPowerState::new(PowerState::Unknown),
             should_turn_on: AtomicBool::new(false),
+            last_command: Mutex::new(None),
+            last_error: Mutex::new(None),
+            last_error_kind: Mutex::new(None),
+            errors: RepeatedErrors::default(),
+            last_success: Mutex::new(None),
+            consecutive_failures: AtomicU32::new(0),
+            failed_attempts: AtomicU32::new(0),
+            next_retry: Mutex::new(None),
+            gave_up: AtomicBool::new(false),
+            last_toggle: Mutex::new(None),
+            last_off: Mutex::new(None),
+            next_poweroff_write_time: Mutex::new(None),
+            confirm_off_until: Mutex::new(None),
+            forced: Mutex::new(None),
+            needs_reset: AtomicBool::new(false),
+        }
+    }
+    /// Turn the sink on or off, following the retry policy of the sink.
+    async fn command_with_retry(&self, on: bool, stagger: &PowerOnStagger) -> CommandOutcome {
+        if self.gave_up.load(Ordering::Acquire) {
+            return CommandOutcome::GaveUp;
+        }
+        if let Some(wait_time) = self.wait_before_command(on) {
+            #[cfg(debug_assertions)]
+            trace!(
+                "{} Waiting {} sec before sending command.",
+                self.sink.identity(),
+                wait_time.as_secs()
+            );
+            return CommandOutcome::Deferred(wait_time);
+        }
+        if on {
+            stagger.wait_turn(&self.sink).await;
+        }
+
+        info!(
+            "{} Turning {}...",
+            self.sink.identity(),
+            if on { "on" } else { "off" }
+        );
+        let Err(kind) = self.command(on).await else {
+            self.reset_retries();
+            *self.last_toggle.lock().unwrap() = Some(Instant::now());
+            if !on {
+                *self.last_off.lock().unwrap() = Some(Instant::now());
+            }
+            return CommandOutcome::Success;
+        };
+        let retry = &self.sink.base_settings().retry;
+        let failed_attempts = self.failed_attempts.fetch_add(1, Ordering::AcqRel) + 1;
+        let exhausted = retry.max_attempts.is_some_and(|max| failed_attempts >= max);
+        if exhausted || !kind.is_retryable() {
+            if exhausted {
+                warn!(
+                    "{} Giving up after {} failed attempts, not retrying until sources change.",
+                    self.sink.identity(),
+                    failed_attempts
+                );
+            } else {
+                warn!(
+                    "{} Not retrying after {} error until sources change.",
+                    self.sink.identity(),
+                    kind
+                );
+            }
+            self.gave_up.store(true, Ordering::Release);
+            *self.next_retry.lock().unwrap() = None;
+            CommandOutcome::GivingUp(failed_attempts)
+        } else {
+            let delay = retry.delay(failed_attempts);
+            info!(
+                "{} Retrying in {} sec.",
+                self.sink.identity(),
+                delay.as_secs()
+            );
+            *self.next_retry.lock().unwrap() = Some(Instant::now() + delay);
+            CommandOutcome::RetryIn(delay)
+        }
+    }
+    /// How long to wait until the next command may be sent, because of a pending retry, the
+    /// minimum time between toggles or, when turning on, the minimum time off.
+    fn wait_before_command(&self, on: bool) -> Option<Duration> {
+        let base = self.sink.base_settings();
+        let min_between_toggles = base
+            .min_seconds_between_toggles
+            .map(Duration::from_secs)
+            .unwrap_or_default();
+        let next_toggle = self
+            .last_toggle
+            .lock()
+            .unwrap()
+            .map(|t| t + min_between_toggles);
+        let min_off = base.min_off_seconds.map(Duration::from_secs);
+        let next_on = match (on, min_off) {
+            (true, Some(min_off)) => self.last_off.lock().unwrap().map(|t| t + min_off),
+            _ => None,
+        };
+        let not_before = [*self.next_retry.lock().unwrap(), next_toggle, next_on]
+            .into_iter()
+            .flatten()
+            .max()?;
+        Some(not_before.saturating_duration_since(Instant::now())).filter(|d| !d.is_zero())
+    }
+    /// Forget about previously failed commands.
+    fn reset_retries(&self) {
+        self.failed_attempts.store(0, Ordering::Release);
+        *self.next_retry.lock().unwrap() = None;
+        if self.gave_up.swap(false, Ordering::AcqRel) {
+            info!(
+                "{} Sources changed, trying again after giving up.",
+                self.sink.identity()
+            );
+        }
+    }
+    /// Turn the sink on or off, with a timeout, and record the result. Returns the kind of
+    /// error if it failed.
+    async fn command(&self, on: bool) -> Result<(), ErrorKind> {
+        let base = self.sink.base_settings();
+        let (pre, post) = match on {
+            true => (&base.pre_on, &base.post_on),
+            false => (&base.pre_off, &base.post_off),
+        };
+        let fut = async {
+            if let Some(hook) = pre {
+                if let Err(e) = hook::run(hook, base.name(), on).await {
+                    if hook.abort_on_failure {
+                        return Err(format!("pre hook failed: {e}").into());
+                    }
+                    warn!("{} Pre hook failed: {}", self.sink.identity(), e);
+                }
+            }
+            match on {
+                true => self.sink.on().await,
+                false => self.sink.off().await,
+            }?;
+            if let Some(hook) = post {
+                if let Err(e) = hook::run(hook, base.name(), on).await {
+                    warn!("{} Post hook failed: {}", self.sink.identity(), e);
+                }
+            }
+            Ok(())
+        };
+        let result = timeout(
+            Duration::from_secs(self.sink.base_settings().timeout_sec as u64),
+            AssertUnwindSafe(fut).catch_unwind(),
+        )
+        .await;
+        self.record_command(result)
+    }
+    /// How long after all sources turned off the sink is put into standby, if it supports it.
+    fn standby_after(&self) -> Option<Duration> {
+        self.sink
+            .base_settings()
+            .standby_after_sec
+            .filter(|_| self.sink.supports_standby())
+            .map(Duration::from_secs)
+    }
+    /// Put the sink into standby, with a timeout, and record the result. Returns whether it was
+    /// successful.
+    async fn standby(&self) -> bool {
+        info!("{} Going to standby...", self.sink.identity());
+        let result = timeout(
+            Duration::from_secs(self.sink.base_settings().timeout_sec as u64),
+            AssertUnwindSafe(self.sink.standby()).catch_unwind(),
+        )
+        .await;
+        self.record_command(result).is_ok()
+    }
+    /// Record the result of an on or off command. Returns the kind of error if it failed.
+    fn record_command(
+        &self,
+        result: Result<Result<SinkCommandResult, Box<dyn Any + Send>>, Elapsed>,
+    ) -> Result<(), ErrorKind> {
+        *self.last_command.lock().unwrap() = Some(SystemTime::now());
+        let error = match result {
+            Ok(Ok(Ok(_))) => None,
+            Ok(Ok(Err(err))) => {
+                let identity = self.sink.identity();
+                match self.errors.record(err.kind()) {
+                    ErrorLog::First => error!("{} Failed setting power state: {}", identity, err),
+                    ErrorLog::Summary(count, since) => {
+                        error!("{} {}: {}", identity, still_failing(count, since), err)
+                    }
+                    ErrorLog::Suppressed => {
+                        debug!("{} Failed setting power state: {}", identity, err)
+                    }
+                }
+                Some((err.to_string(), err.kind()))
+            }
+            Ok(Err(panic)) => {
+                let panic = panic_to_string(panic);
+                error!(
+                    "{} Panic while setting power state: {}",
+                    self.sink.identity(),
+                    panic
+                );
+                Some((format!("panic: {panic}"), ErrorKind::Other))
+            }
+            Err(_) => {
+                let identity = self.sink.identity();
+                match self.errors.record(ErrorKind::Timeout) {
+                    ErrorLog::First => error!("{} Timeout while setting power state.", identity),
+                    ErrorLog::Summary(count, since) => {
+                        error!(
+                            "{} {} with timeouts.",
+                            identity,
+                            still_failing(count, since)
+                        )
+                    }
+                    ErrorLog::Suppressed => {
+                        debug!("{} Timeout while setting power state.", identity)
+                    }
+                }
+                Some(("timeout".to_string(), ErrorKind::Timeout))
+            }
+        };
+        let kind = error.as_ref().map(|(_, kind)| *kind);
+        *self.last_error_kind.lock().unwrap() = kind;
+        *self.last_error.lock().unwrap() = error.map(|(error, _)| error);
+        match kind {
+            None => {
+                *self.last_success.lock().unwrap() = Some(SystemTime::now());
+                let failures = self.consecutive_failures.swap(0, Ordering::AcqRel);
+                if failures > 0 {
+                    info!(
+                        "{} Succeeded again after {} failed commands.",
+                        self.sink.identity(),
+                        failures
+                    );
+                }
+                self.errors.clear();
+                Ok(())
+            }
+            Some(kind) => {
+                self.consecutive_failures.fetch_add(1, Ordering::AcqRel);
+                Err(kind)
+            }
         }
     }
 }
 
+/// A group of sinks that is checked independently of the others, see [`ZoneSettings`].
+struct Zone {
+    /// `None` for the default zone of all sources and sinks that are not in a configured zone.
+    settings: Option<ZoneSettings>,
+    power_off_check_interval: Duration,
+    startup_grace: Duration,
+    /// Woken up whenever the sinks of the zone should be checked again.
+    wakeup_sink_check: Wakeup,
+}
+
+impl Zone {
+    fn new(settings: Option<ZoneSettings>, general: &GeneralSettings) -> Self {
+        let zone = settings.as_ref();
+        Self {
+            power_off_check_interval: Duration::from_secs(
+                zone.and_then(|z| z.power_off_check_interval_sec)
+                    .unwrap_or(general.power_off_check_interval_sec),
+            ),
+            startup_grace: Duration::from_secs(
+                zone.and_then(|z| z.startup_grace_sec)
+                    .unwrap_or(general.startup_grace_sec),
+            ),
+            settings,
+            wakeup_sink_check: Wakeup::new(true),
+        }
+    }
+    fn name(&self) -> &str {
+        self.settings
+            .as_ref()
+            .map(|zone| zone.name.as_str())
+            .unwrap_or("default")
+    }
+    fn lists_source(&self, source_name: &str) -> bool {
+        self.settings
+            .as_ref()
+            .is_some_and(|zone| zone.sources.iter().any(|name| name == source_name))
+    }
+}
+
 pub struct State {
     config: GeneralSettings,
-    sources: HashMap<Identity<'static>, SourceState>,
-    sinks: Rc<HashMap<Identity<'static>, SinkState>>,
+    /// The default zone comes first.
+    zones: Box<[Zone]>,
+    routes: Box<[RouteSettings]>,
+    sources: RwLock<HashMap<Identity<'static>, Arc<SourceState>>>,
+    sinks: RwLock<HashMap<Identity<'static>, Arc<SinkState>>>,
+    /// Sources added at runtime, which the run loop starts polling.
+    added_sources: mpsc::UnboundedSender<Identity<'static>>,
+    added_sources_rx: Mutex<Option<mpsc::UnboundedReceiver<Identity<'static>>>>,
+    /// The tasks polling the sources, to stop polling removed sources.
+    poll_tasks: Mutex<HashMap<Identity<'static>, AbortHandle>>,
+    events: broadcast::Sender<Event>,
+    statistics: Statistics,
+    power_on_stagger: PowerOnStagger,
+    started: Instant,
+    /// If set, load shedding is forced on or off regardless of its source.
+    shedding_forced: Mutex<Option<bool>>,
+    /// Whether load is shed at the moment.
+    shedding: AtomicBool,
+    /// Sink commands in progress, closed on shutdown.
+    commands: Drain,
 }
 
 impl State {
-    pub fn new(config: GeneralSettings) -> Self {
+    pub fn new(config: GeneralSettings, zones: &[ZoneSettings], routes: &[RouteSettings]) -> Self {
+        let (added_sources, added_sources_rx) = mpsc::unbounded_channel();
+        let zones = [None]
+            .into_iter()
+            .chain(zones.iter().cloned().map(Some))
+            .map(|zone| Zone::new(zone, &config))
+            .collect();
+        let statistics = Statistics::load(config.statistics_file.clone());
+        let power_on_stagger = PowerOnStagger {
+            default_delay_sec: config.power_on_delay_sec,
+            last_on: Mutex::new(None),
+        };
         Self {
             config,
+            zones,
+            routes: routes.into(),
             sources: Default::default(),
-            sinks: Rc::new(Default::default()),
+            sinks: Default::default(),
+            added_sources,
+            added_sources_rx: Mutex::new(Some(added_sources_rx)),
+            poll_tasks: Default::default(),
+            events: broadcast::channel(64).0,
+            statistics,
+            power_on_stagger,
+            started: Instant::now(),
+            shedding_forced: Mutex::new(None),
+            shedding: AtomicBool::new(false),
+            commands: Drain::new(),
         }
     }
 
     pub async fn try_register_sources(
         &mut self,
-        sources: impl Iterator<Item = Result<Box<dyn Source>, Box<dyn Error>>>,
-    ) -> Result<(), Box<dyn Error>> {
+        sources: impl Iterator<Item = Result<Box<dyn Source>, crate::error::Error>>,
+    ) -> Result<(), crate::error::Error> {
         let mut new_sources = HashMap::new();
         for maybe_source in sources {
             let source = maybe_source?;
@@ -114,7 +762,7 @@ impl State {
             let existed = new_sources
                 .insert(
                     source.base_settings().identity().clone_owned(),
-                    SourceState::new(source),
+                    Arc::new(SourceState::new(source)),
                 )
                 .is_some();
             if existed {
@@ -123,22 +771,24 @@ impl State {
                 info!("{} Loaded.", identity_str);
             }
         }
-        self.sources = new_sources;
+        *self.sources.get_mut().unwrap() = new_sources;
         Ok(())
     }
 
     pub async fn try_register_sinks(
         &mut self,
-        sinks: impl Iterator<Item = Result<Box<dyn Sink>, Box<dyn Error>>>,
-    ) -> Result<(), Box<dyn Error>> {
+        sinks: impl Iterator<Item = Result<Box<dyn Sink>, crate::error::Error>>,
+    ) -> Result<(), crate::error::Error> {
         let mut new_sinks = HashMap::new();
         for maybe_sink in sinks {
             let sink = maybe_sink?;
             let identity_str = sink.base_settings().identity().to_string();
+            let zone = self.zone_of_sink(sink.base_settings().name());
+            let routes = self.routes_of_sink(sink.base_settings().name());
             let existed = new_sinks
                 .insert(
                     sink.base_settings().identity().clone_owned(),
-                    SinkState::new(sink),
+                    Arc::new(SinkState::new(sink, zone, routes)),
                 )
                 .is_some();
             if existed {
@@ -147,61 +797,468 @@ impl State {
                 info!("{} Loaded.", identity_str);
             }
         }
-        self.sinks = Rc::new(new_sinks);
+        *self.sinks.get_mut().unwrap() = new_sinks;
         Ok(())
     }
 
-    pub async fn run(&self) -> ! {
-        // On the first run, do not wait before getting source states.
-        let mut is_first_run = true;
-        let mut source_futs: HashMap<Identity, StateCheckFut> = HashMap::new();
-        let wakeup_sink_check = Rc::new(Wakeup::new(true));
-        let mut check_sinks = self
-            .check_sinks(wakeup_sink_check.clone())
-            .instrument(info_span!("check_sink"))
-            .boxed_local()
-            .fuse();
+    /// Run the state machine. Each source is polled and the sinks of each zone are checked in
+    /// their own tasks. Sources added while running are polled in new tasks.
+    pub async fn run(self: Arc<Self>) -> ! {
+        let mut added_sources = self
+            .added_sources_rx
+            .lock()
+            .unwrap()
+            .take()
+            .expect("State is only run once.");
+        let mut tasks = JoinSet::new();
+        tasks.spawn(self.clone().save_statistics());
+        if self.config.report.is_some() {
+            tasks.spawn(self.clone().send_reports());
+        }
+        for (i, zone) in self.zones.iter().enumerate() {
+            tasks.spawn(
+                self.clone()
+                    .check_sinks(i)
+                    .instrument(info_span!("check_sink", zone = zone.name())),
+            );
+        }
+        let identities = self
+            .sources
+            .read()
+            .unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        for identity in identities {
+            self.spawn_poll_source(&mut tasks, identity);
+        }
 
         loop {
-            // Set up futures for checking active.
-            for (ident, state) in &self.sources {
-                #[allow(unused_must_use)] // the future is terminated so it has already been used.
-                match source_futs.entry(ident.clone()) {
-                    Entry::Occupied(mut e) if e.get().is_terminated() => {
-                        e.insert(Self::create_source_is_active_fut(
-                            Rc::downgrade(&self.sinks),
-                            state,
-                            is_first_run,
-                            Rc::downgrade(&wakeup_sink_check),
-                        ));
-                    }
-                    Entry::Vacant(e) => {
-                        e.insert(Self::create_source_is_active_fut(
-                            Rc::downgrade(&self.sinks),
-                            state,
-                            is_first_run,
-                            Rc::downgrade(&wakeup_sink_check),
-                        ));
-                    }
-                    _ => {}
-                };
+            select! {
+                Some(identity) = added_sources.recv() => self.spawn_poll_source(&mut tasks, identity),
+                // The tasks never complete unless they panic or their source was removed.
+                result = tasks.join_next() => match result {
+                    Some(Err(e)) if e.is_cancelled() => {}
+                    Some(Err(e)) => panic!("State task failed: {e}"),
+                    _ => unreachable!("State task somehow completed."),
+                },
             }
+        }
+    }
 
-            // Select any of the source scan or sink set futures.
-            select_all(once(&mut check_sinks).chain(source_futs.values_mut())).await;
-            is_first_run = false;
+    fn spawn_poll_source(self: &Arc<Self>, tasks: &mut JoinSet<()>, identity: Identity<'static>) {
+        // It may have been removed again in the meantime.
+        let Some(state) = self.sources.read().unwrap().get(&identity).cloned() else {
+            return;
+        };
+        let span = info_span!("check_source", source = state.source.name());
+        let handle = tasks.spawn(
+            self.clone()
+                .poll_source(identity.clone(), state)
+                .instrument(span),
+        );
+        if let Some(previous) = self.poll_tasks.lock().unwrap().insert(identity, handle) {
+            previous.abort();
+        }
+    }
+
+    /// Add a source while running, replacing a source with the same name, and start polling it.
+    pub fn add_source(&self, source: Box<dyn Source>) {
+        let identity = source.base_settings().identity().clone_owned();
+        let name = source.base_settings().name.clone();
+        let previous = self
+            .sources
+            .write()
+            .unwrap()
+            .insert(identity.clone(), Arc::new(SourceState::new(source)));
+        if previous.is_some() {
+            self.stop_polling(&identity);
+            info!("{} Replaced.", identity);
+            // Its state is unknown until it is polled again.
+            self.wakeup_combining_sources(&name);
+        } else {
+            info!("{} Added.", identity);
+        }
+        // Fails only if the state is not running, then it is polled once it runs.
+        self.added_sources.send(identity).ok();
+    }
+
+    /// Stop polling a source and remove it while running. Fails if no source with the name
+    /// exists.
+    pub fn remove_source(&self, source_name: &str) -> Result<(), String> {
+        let mut sources = self.sources.write().unwrap();
+        let identity = sources
+            .iter()
+            .find(|(_, state)| state.source.name() == source_name)
+            .map(|(identity, _)| identity.clone())
+            .ok_or_else(|| format!("no source named \"{source_name}\""))?;
+        sources.remove(&identity);
+        drop(sources);
+        self.stop_polling(&identity);
+        info!("{} Removed.", identity);
+        // Sinks may have to be turned off without it.
+        self.wakeup_zones_of_source(source_name);
+        self.wakeup_combining_sources(source_name);
+        if self.is_shedding_source(source_name) {
+            self.update_shedding();
+        }
+        Ok(())
+    }
+
+    /// Push a state to the source with the name, e.g. from a webhook.
+    #[cfg(feature = "source-webhook")]
+    pub fn push_to_source(
+        &self,
+        source_name: &str,
+        secret: &str,
+        active: bool,
+    ) -> Result<(), PushError> {
+        let state = self
+            .sources
+            .read()
+            .unwrap()
+            .values()
+            .find(|state| state.source.name() == source_name)
+            .cloned()
+            .ok_or(PushError::NotFound)?;
+        state.source.push(secret, active)?;
+        debug!("{} Pushed {}.", state.source.identity(), pwrst_log(active));
+        Ok(())
+    }
+
+    fn stop_polling(&self, identity: &Identity<'static>) {
+        if let Some(handle) = self.poll_tasks.lock().unwrap().remove(identity) {
+            handle.abort();
+        }
+    }
+
+    /// Add a sink while running, replacing a sink with the same name. It follows the current
+    /// state of the sources.
+    pub fn add_sink(&self, sink: Box<dyn Sink>) {
+        let identity = sink.base_settings().identity().clone_owned();
+        let zone = self.zone_of_sink(sink.base_settings().name());
+        let routes = self.routes_of_sink(sink.base_settings().name());
+        let state = Arc::new(SinkState::new(sink, zone, routes));
+        let previous = self
+            .sinks
+            .write()
+            .unwrap()
+            .insert(identity.clone(), state.clone());
+        match previous {
+            Some(_) => info!("{} Replaced.", identity),
+            None => info!("{} Added.", identity),
+        }
+        self.resync_sink(&state);
+    }
+
+    /// Remove a sink while running, leaving its device in its current state. Fails if no sink
+    /// with the name exists.
+    pub fn remove_sink(&self, sink_name: &str) -> Result<(), String> {
+        let mut sinks = self.sinks.write().unwrap();
+        let identity = sinks
+            .iter()
+            .find(|(_, state)| state.sink.name() == sink_name)
+            .map(|(identity, _)| identity.clone())
+            .ok_or_else(|| format!("no sink named \"{sink_name}\""))?;
+        sinks.remove(&identity);
+        info!("{} Removed.", identity);
+        Ok(())
+    }
+
+    /// Index of the zone the sink is in.
+    fn zone_of_sink(&self, sink_name: &str) -> usize {
+        self.zones
+            .iter()
+            .position(|zone| {
+                zone.settings
+                    .as_ref()
+                    .is_some_and(|zone| zone.sinks.iter().any(|name| name == sink_name))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Indices of the routes the sink is in.
+    fn routes_of_sink(&self, sink_name: &str) -> Box<[usize]> {
+        self.routes
+            .iter()
+            .enumerate()
+            .filter(|(_, route)| route.sinks.iter().any(|name| name == sink_name))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    fn zone_has_source(&self, zone: &Zone, source_name: &str) -> bool {
+        match zone.settings {
+            Some(_) => zone.lists_source(source_name),
+            None => !self.zones.iter().any(|zone| zone.lists_source(source_name)),
+        }
+    }
+
+    /// Whether the source is relevant for the sink at all, through its routes or its zone.
+    fn sink_has_source(&self, sink_state: &SinkState, source_name: &str) -> bool {
+        if self.is_shedding_source(source_name) {
+            return false;
+        }
+        if sink_state.routes.is_empty() {
+            return self.zone_has_source(&self.zones[sink_state.zone], source_name);
+        }
+        sink_state.routes.iter().any(|&i| {
+            self.routes[i]
+                .sources
+                .iter()
+                .any(|name| name == source_name)
+        })
+    }
+
+    fn wakeup_zones_of_source(&self, source_name: &str) {
+        for zone in self.zones.iter() {
+            if self.zone_has_source(zone, source_name) {
+                zone.wakeup_sink_check.wakeup();
+            }
+        }
+        // Routes may lead to sinks in other zones.
+        for sink_state in self.sinks.read().unwrap().values() {
+            if !sink_state.routes.is_empty() && self.sink_has_source(sink_state, source_name) {
+                self.zones[sink_state.zone].wakeup_sink_check.wakeup();
+            }
+        }
+    }
+
+    /// Time after which the sink is turned off once all of its sources are off.
+    fn power_off_check_interval(&self, sink_state: &SinkState) -> Duration {
+        sink_state
+            .routes
+            .iter()
+            .filter_map(|&i| self.routes[i].power_off_check_interval_sec)
+            .max()
+            .map(Duration::from_secs)
+            .unwrap_or(self.zones[sink_state.zone].power_off_check_interval)
+    }
+
+    /// Whether the source may turn the sink on.
+    fn source_triggers(&self, sink_state: &SinkState, source_name: &str) -> bool {
+        self.sink_has_source(sink_state, source_name)
+            && sink_state
+                .sink
+                .base_settings()
+                .allows_source_for_on(source_name)
+    }
+
+    /// Whether the source keeps the sink from being turned off.
+    fn source_keeps_on(&self, sink_state: &SinkState, source_name: &str) -> bool {
+        self.sink_has_source(sink_state, source_name)
+            && sink_state
+                .sink
+                .base_settings()
+                .allows_source_for_off(source_name)
+    }
+
+    /// The sinks at this moment, so that they can be used without holding the lock.
+    fn current_sinks(&self) -> Vec<Arc<SinkState>> {
+        self.sinks.read().unwrap().values().cloned().collect()
+    }
+
+    /// Stop sending sink commands and wait up to `grace` for the ones in progress, so that no
+    /// device is left half switched. The ones still in progress then are aborted, so that the
+    /// shutdown actions are the only commands sent afterwards.
+    pub async fn drain(&self, grace: Duration) {
+        if timeout(grace, self.commands.close()).await.is_err() {
+            warn!(
+                "Sink commands still in progress after {} sec, aborting them.",
+                grace.as_secs()
+            );
+            self.commands.abort().await;
         }
     }
 
-    async fn check_sinks(&self, manual_wakeup: Rc<Wakeup>) {
-        let mut next_poweroff_write_time: Option<Instant> = None;
+    /// Run the configured shutdown actions of all sinks.
+    pub async fn shutdown(&self) {
+        join_all(self.current_sinks().into_iter().map(|state| async move {
+            let on = match state.sink.base_settings().on_shutdown {
+                ShutdownAction::Leave => return,
+                ShutdownAction::On => true,
+                ShutdownAction::Off => false,
+            };
+            info!(
+                "{} Turning {} for shutdown...",
+                state.sink.identity(),
+                if on { "on" } else { "off" }
+            );
+            state.command(on).await.ok();
+        }))
+        .await;
+        self.statistics.flush();
+    }
+
+    /// On-time of all sources and sinks, with energy estimates for sinks with a wattage.
+    pub fn statistics(&self) -> StatisticsReport {
+        let watts = self.sink_watts();
+        self.statistics.report(|name| watts.get(name).copied())
+    }
+
+    /// Summary of the sinks over the `days` full days before today.
+    pub fn power_report(&self, days: u64) -> PeriodReport {
+        let watts = self.sink_watts();
+        self.statistics
+            .period_report(days, |name| watts.get(name).copied())
+    }
+
+    fn sink_watts(&self) -> HashMap<String, f64> {
+        self.sinks
+            .read()
+            .unwrap()
+            .values()
+            .filter_map(|state| {
+                Some((
+                    state.sink.name().to_string(),
+                    state.sink.base_settings().watts?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Emit a power report after the end of each report period, and append it to the report
+    /// file.
+    async fn send_reports(self: Arc<Self>) {
+        let Some(settings) = self.config.report.clone() else {
+            return pending().await;
+        };
+        loop {
+            let now = unix_now();
+            let today = now / SECS_PER_DAY;
+            let until_tomorrow = Duration::from_secs((today + 1) * SECS_PER_DAY - now);
+            sleep(until_tomorrow + REPORT_DELAY).await;
+            if !settings.period.ends_before(today + 1) {
+                continue;
+            }
+            let report = self.power_report(settings.period.days());
+            if let Some(path) = &settings.file {
+                let appended = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .and_then(|mut file| writeln!(file, "{report}\n"));
+                if let Err(e) = appended {
+                    error!("Failed writing report to {}: {}", path.display(), e);
+                }
+            }
+            self.emit(Event::PowerReport { report });
+        }
+    }
 
+    /// Persist the statistics regularly, so that not much is lost if the daemon is killed.
+    async fn save_statistics(self: Arc<Self>) {
+        loop {
+            sleep(STATISTICS_SAVE_INTERVAL).await;
+            self.statistics.flush();
+        }
+    }
+
+    /// Health of all sources and sinks. A component is unhealthy if it failed more often in a
+    /// row than allowed by the health settings. Sources that are asleep and failing are healthy.
+    #[cfg(any(feature = "http", test))]
+    pub fn health(&self) -> HealthReport {
+        let thresholds = &self.config.health;
+        // The locks are taken one after the other, never both at once.
+        let mut components = self
+            .sources
+            .read()
+            .unwrap()
+            .values()
+            .map(|state| {
+                let consecutive_errors = state.consecutive_failures.load(Ordering::Acquire);
+                let degraded = state.degraded.load(Ordering::Acquire);
+                let error_kind = *state.last_error_kind.lock().unwrap();
+                ComponentHealth {
+                    category: state.source.category().to_string(),
+                    name: state.source.name().to_string(),
+                    // Errors that won't go away by themselves count right away.
+                    healthy: (consecutive_errors < thresholds.source_failures
+                        || state.asleep().is_some())
+                        && error_kind.is_none_or(ErrorKind::is_retryable)
+                        && !degraded,
+                    degraded,
+                    consecutive_errors,
+                    error_kind,
+                    last_success: *state.last_success.lock().unwrap(),
+                }
+            })
+            .collect::<Vec<_>>();
+        components.extend(self.sinks.read().unwrap().values().map(|state| {
+            let consecutive_errors = state.consecutive_failures.load(Ordering::Acquire);
+            ComponentHealth {
+                category: state.sink.category().to_string(),
+                name: state.sink.name().to_string(),
+                healthy: consecutive_errors < thresholds.sink_failures
+                    && !state.gave_up.load(Ordering::Acquire)
+                    && !state.needs_reset.load(Ordering::Acquire),
+                degraded: false,
+                consecutive_errors,
+                error_kind: *state.last_error_kind.lock().unwrap(),
+                last_success: *state.last_success.lock().unwrap(),
+            }
+        }));
+        HealthReport {
+            healthy: components.iter().all(|c| c.healthy),
+            components,
+        }
+    }
+
+    /// Current state of all sources and sinks.
+    pub fn status(&self) -> StatusReport {
+        let sources = self
+            .sources
+            .read()
+            .unwrap()
+            .values()
+            .map(|state| SourceStatus {
+                name: state.source.name().to_string(),
+                power_state: state.current_power_state.load(Ordering::Acquire),
+                asleep: state.asleep().is_some(),
+                degraded: state.degraded.load(Ordering::Acquire),
+                last_poll: *state.last_poll.lock().unwrap(),
+                next_poll_in_sec: state
+                    .next_poll
+                    .lock()
+                    .unwrap()
+                    .map(|t| t.saturating_duration_since(Instant::now()).as_secs()),
+                last_error: state.last_error.lock().unwrap().clone(),
+            })
+            .collect();
+        let sinks = self
+            .sinks
+            .read()
+            .unwrap()
+            .values()
+            .map(|state| SinkStatus {
+                name: state.sink.name().to_string(),
+                power_state: state.current_power_state.load(Ordering::Acquire),
+                pending_on: state.should_turn_on.load(Ordering::Acquire),
+                gave_up: state.gave_up.load(Ordering::Acquire),
+                forced: state.forced.lock().unwrap().map(PowerState::from),
+                needs_reset: state.needs_reset.load(Ordering::Acquire),
+                shed: self.sheds(state),
+                power_off_pending_in_sec: state
+                    .next_poweroff_write_time
+                    .lock()
+                    .unwrap()
+                    .max(*state.confirm_off_until.lock().unwrap())
+                    .map(|t| t.saturating_duration_since(Instant::now()).as_secs()),
+                last_command: *state.last_command.lock().unwrap(),
+                last_error: state.last_error.lock().unwrap().clone(),
+            })
+            .collect();
+        StatusReport { sources, sinks }
+    }
+
+    /// Check the sinks of the zone with the given index whenever needed.
+    async fn check_sinks(self: Arc<Self>, zone: usize) {
+        let zone_state = &self.zones[zone];
         loop {
-            let mut wakeup_soon = None;
             #[cfg(debug_assertions)]
             {
                 let mut all_info_sources = String::new();
-                for (ident, state) in &self.sources {
+                for (ident, state) in self.sources.read().unwrap().iter() {
                     all_info_sources.push_str(&format!(
                         "{}: {:?}\n",
                         ident,
@@ -209,7 +1266,7 @@ impl State {
                     ));
                 }
                 let mut all_info_sinks = String::new();
-                for (ident, state) in &*self.sinks {
+                for (ident, state) in self.sinks.read().unwrap().iter() {
                     all_info_sinks.push_str(&format!(
                         "{}: {:?} -> {}\n",
                         ident,
@@ -223,203 +1280,656 @@ impl State {
             }
             debug!("processing sinks...");
 
-            // Check if all sources are off, if so, turn this one of as well.
-            if self
-                .sources
-                .values()
-                .all(|s| s.current_power_state.load(Ordering::Acquire) != PowerState::On)
-            {
-                debug!("all off or unknown.");
-                let npwt_mut = next_poweroff_write_time.get_or_insert_with(|| {
-                    Instant::now() + Duration::from_secs(self.config.power_off_check_interval_sec)
-                });
-                let wait_time = npwt_mut.duration_since(Instant::now());
-                if wait_time.as_secs() > 0 {
-                    #[cfg(debug_assertions)]
-                    trace!(
-                        "Pending potential poweroff, but next poweroff write scheduled for in {} sec.",
-                        wait_time.as_secs()
-                    );
-                    wakeup_soon = Some(wait_time);
-                } else {
-                    for state in self.sinks.values() {
-                        match state
-                            .current_power_state
-                            .swap(PowerState::Off, Ordering::AcqRel)
-                        {
-                            PowerState::Off => {
-                                #[cfg(debug_assertions)]
-                                trace!("{} Was already turned off.", state.sink.identity())
-                            }
-                            _ => {
-                                info!("{} Turning off...", state.sink.identity());
-                                if !Self::log_sink_error(
-                                    &state.sink,
-                                    AssertUnwindSafe(state.sink.off()).catch_unwind().await,
-                                ) {
-                                    wakeup_soon = Some(Duration::from_secs(5));
-                                    state
-                                        .current_power_state
-                                        .store(PowerState::Unknown, Ordering::Release);
-                                }
-                            }
-                        }
-                    }
-                }
-            } else {
-                debug!("at least one on.");
-                next_poweroff_write_time = None;
-                for state in self.sinks.values() {
-                    // this is not really fully thread safe since the loads and stores are
-                    // detached, but it's fine probably?
-                    let condition = {
-                        state.should_turn_on.load(Ordering::Acquire)
-                            && state.current_power_state.load(Ordering::Acquire) != PowerState::On
-                    };
-                    debug!("{} turn on condition: {}", state.sink.identity(), condition);
-                    if condition {
-                        info!("{} Turning on...", state.sink.identity());
-                        if Self::log_sink_error(
-                            &state.sink,
-                            AssertUnwindSafe(state.sink.on()).catch_unwind().await,
-                        ) {
-                            state.should_turn_on.store(false, Ordering::Release);
-                            state
-                                .current_power_state
-                                .store(PowerState::On, Ordering::Release);
-                        } else {
-                            wakeup_soon = Some(Duration::from_secs(5));
-                            state
-                                .current_power_state
-                                .store(PowerState::Unknown, Ordering::Release);
-                        }
-                    } else {
-                        #[cfg(debug_assertions)]
-                        trace!(
-                            "{} Was already turned on or should not turn on.",
-                            state.sink.identity()
-                        )
-                    }
-                }
-            }
+            // Check all sinks concurrently, so that slow sinks don't hold up others.
+            let mut sinks = self.current_sinks();
+            sinks.retain(|state| state.zone == zone);
+            let wakeup_soon = join_all(sinks.iter().map(|state| self.check_sink(state)))
+                .await
+                .into_iter()
+                .flatten()
+                .min();
 
             if let Some(wakeup_time) = wakeup_soon {
                 select!(
-                    _ = &*manual_wakeup => {},
+                    _ = zone_state.wakeup_sink_check.wait() => {},
                     _ = sleep(wakeup_time) => {}
                 )
             } else {
-                (&*manual_wakeup).await;
+                zone_state.wakeup_sink_check.wait().await;
             }
         }
     }
 
-    fn create_source_is_active_fut<'a>(
-        sinks: Weak<HashMap<Identity<'a>, SinkState>>,
-        state: &'a SourceState,
-        is_first_run: bool,
-        manual_wakeup: Weak<Wakeup>,
-    ) -> StateCheckFut<'a> {
-        let identity = state.source.identity();
-        trace!("{} setting up future", state.source.identity());
+    /// Turn the sink on or off if needed. Returns when the sink should be checked again, if it
+    /// needs to be checked before the next source transition.
+    async fn check_sink(&self, state: &SinkState) -> Option<Duration> {
+        if state.needs_reset.load(Ordering::Acquire) {
+            #[cfg(debug_assertions)]
+            trace!("{} Waiting for manual reset.", state.sink.identity());
+            return None;
+        }
+        let forced = *state.forced.lock().unwrap();
+        if let Some(on) = forced {
+            debug!("{} forced {}.", state.sink.identity(), pwrst_log(on));
+            *state.confirm_off_until.lock().unwrap() = None;
+            return self.set_sink_power(state, on).await;
+        }
+        if self.sheds(state) {
+            debug!("{} shedding load.", state.sink.identity());
+            *state.next_poweroff_write_time.lock().unwrap() = None;
+            *state.confirm_off_until.lock().unwrap() = None;
+            // Recomputed once load shedding ends.
+            state.should_turn_on.store(false, Ordering::Release);
+            return self.set_sink_power(state, false).await;
+        }
+        let zone = &self.zones[state.zone];
+        // Check if all sources relevant for this sink are off, if so, turn it off as well.
+        let all_off = self
+            .sources
+            .read()
+            .unwrap()
+            .values()
+            .filter(|s| self.source_keeps_on(state, s.source.name()))
+            .all(|s| s.current_power_state.load(Ordering::Acquire) != PowerState::On);
+        if all_off {
+            debug!("{} all off or unknown.", state.sink.identity());
+            let grace_end = self.started + zone.startup_grace;
+            let grace_left = grace_end.saturating_duration_since(Instant::now());
+            if !grace_left.is_zero() {
+                #[cfg(debug_assertions)]
+                trace!(
+                    "{} Not turning off during the startup grace period, {} sec left.",
+                    state.sink.identity(),
+                    grace_left.as_secs()
+                );
+                return Some(grace_left);
+            }
+            let power_off_check_interval = self.power_off_check_interval(state);
+            let off_at = *state
+                .next_poweroff_write_time
+                .lock()
+                .unwrap()
+                .get_or_insert_with(|| Instant::now() + power_off_check_interval);
+            let wait_time = off_at.saturating_duration_since(Instant::now());
+            if wait_time.as_secs() > 0 {
+                if let Some(standby_after) = state.standby_after() {
+                    let standby_at = off_at - power_off_check_interval + standby_after;
+                    let standby_wait = standby_at.saturating_duration_since(Instant::now());
+                    if standby_wait.is_zero() {
+                        self.set_sink_standby(state).await;
+                    } else if standby_wait < wait_time
+                        && state.current_power_state.load(Ordering::Acquire) == PowerState::On
+                    {
+                        return Some(standby_wait);
+                    }
+                }
+                #[cfg(debug_assertions)]
+                trace!(
+                    "{} Pending potential poweroff, but next poweroff write scheduled for in {} sec.",
+                    state.sink.identity(),
+                    wait_time.as_secs()
+                );
+                return Some(wait_time);
+            }
 
-        // First sleep until the next scan interval, then check, but with a timeout.
-        sleep(Duration::from_secs(if is_first_run {
-            0
+            if let Some(wait_time) = self.wait_for_off_confirmation(state) {
+                return Some(wait_time);
+            }
+            // A pending on that was never sent is superseded by turning off.
+            state.should_turn_on.store(false, Ordering::Release);
+            self.set_sink_power(state, false).await
         } else {
-            state.get_sleep_before_check()
-        }))
-        .then(|_| {
-            timeout(
-                Duration::from_secs(state.source.base_settings().timeout_sec as u64),
-                AssertUnwindSafe(state.source.is_active()).catch_unwind(),
-            )
+            debug!("{} at least one on.", state.sink.identity());
+            *state.next_poweroff_write_time.lock().unwrap() = None;
+            *state.confirm_off_until.lock().unwrap() = None;
+            let condition = state.should_turn_on.load(Ordering::Acquire);
+            debug!("{} turn on condition: {}", state.sink.identity(), condition);
+            if !condition {
+                #[cfg(debug_assertions)]
+                trace!("{} Should not turn on.", state.sink.identity());
+                return None;
+            }
+            // With `trigger-mode = "all"`, a source may have turned off since it was marked.
+            if !self.triggers_on(state) {
+                debug!("{} Not all sources are on anymore.", state.sink.identity());
+                state.should_turn_on.store(false, Ordering::Release);
+                return None;
+            }
+            self.set_sink_power(state, true).await
+        }
+    }
+
+    /// With `confirm-off`, announce that the sink is about to be turned off and return how long
+    /// to wait before doing so, until the window has passed.
+    fn wait_for_off_confirmation(&self, state: &SinkState) -> Option<Duration> {
+        let window_sec = state.sink.base_settings().confirm_off.as_ref()?.window_sec;
+        if state.current_power_state.load(Ordering::Acquire) == PowerState::Off {
+            return None;
+        }
+        let until = *state
+            .confirm_off_until
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| {
+                info!(
+                    "{} Turning off in {window_sec} sec, unless overridden.",
+                    state.sink.identity()
+                );
+                self.emit(Event::SinkOffPending {
+                    sink: state.sink.name().to_string(),
+                    in_sec: window_sec,
+                });
+                Instant::now() + Duration::from_secs(window_sec)
+            });
+        let wait_time = until.saturating_duration_since(Instant::now());
+        (!wait_time.is_zero()).then_some(wait_time)
+    }
+
+    /// Turn the sink on or off, unless it already is. Returns when the sink should be checked
+    /// again, if the command needs to be retried.
+    async fn set_sink_power(&self, state: &SinkState, on: bool) -> Option<Duration> {
+        if state.current_power_state.load(Ordering::Acquire) == on.into() {
+            #[cfg(debug_assertions)]
+            trace!(
+                "{} Was already turned {}.",
+                state.sink.identity(),
+                pwrst_log(on)
+            );
+            if on {
+                state.should_turn_on.store(false, Ordering::Release);
+            }
+            return None;
+        }
+        let command = state.command_with_retry(on, &self.power_on_stagger);
+        let Some(outcome) = self.commands.run(command).await else {
+            debug!(
+                "{} Shutting down, command not sent or aborted.",
+                state.sink.identity()
+            );
+            return None;
+        };
+        match outcome {
+            CommandOutcome::Success => {
+                if on {
+                    state.should_turn_on.store(false, Ordering::Release);
+                }
+                state
+                    .current_power_state
+                    .store(on.into(), Ordering::Release);
+                self.emit(Event::SinkChanged {
+                    sink: state.sink.name().to_string(),
+                    power_state: on.into(),
+                });
+                None
+            }
+            CommandOutcome::Deferred(delay) => Some(delay),
+            outcome @ (CommandOutcome::RetryIn(_) | CommandOutcome::GivingUp(_)) => {
+                self.emit_command_failed(state);
+                if let CommandOutcome::GivingUp(attempts) = outcome {
+                    self.emit(Event::SinkGaveUp {
+                        sink: state.sink.name().to_string(),
+                        attempts,
+                    });
+                }
+                self.handle_failed_command(state, outcome)
+            }
+            CommandOutcome::GaveUp => {
+                state
+                    .current_power_state
+                    .store(PowerState::Unknown, Ordering::Release);
+                None
+            }
+        }
+    }
+
+    /// Put the sink into standby if it is on. If that fails, its state is unknown, and it is
+    /// turned off fully when due.
+    async fn set_sink_standby(&self, state: &SinkState) {
+        if state.current_power_state.load(Ordering::Acquire) != PowerState::On {
+            return;
+        }
+        let new_state = match self.commands.run(state.standby()).await {
+            None => return,
+            Some(true) => PowerState::Standby,
+            Some(false) => {
+                self.emit_command_failed(state);
+                PowerState::Unknown
+            }
+        };
+        state
+            .current_power_state
+            .store(new_state, Ordering::Release);
+        self.emit(Event::SinkChanged {
+            sink: state.sink.name().to_string(),
+            power_state: new_state,
+        });
+    }
+
+    /// Apply the unknown state policy of the sink after a failed command. Returns when the
+    /// command should be retried, if at all.
+    fn handle_failed_command(
+        &self,
+        state: &SinkState,
+        outcome: CommandOutcome,
+    ) -> Option<Duration> {
+        match state.sink.base_settings().on_unknown {
+            OnUnknown::Retry => {
+                state
+                    .current_power_state
+                    .store(PowerState::Unknown, Ordering::Release);
+                match outcome {
+                    CommandOutcome::RetryIn(delay) => Some(delay),
+                    _ => None,
+                }
+            }
+            policy @ (OnUnknown::AssumeOn | OnUnknown::AssumeOff) => {
+                let assumed = policy == OnUnknown::AssumeOn;
+                warn!(
+                    "{} Assuming it is {} until the next source transition.",
+                    state.sink.identity(),
+                    pwrst_log(assumed)
+                );
+                state.reset_retries();
+                state.should_turn_on.store(false, Ordering::Release);
+                state
+                    .current_power_state
+                    .store(assumed.into(), Ordering::Release);
+                None
+            }
+            OnUnknown::ManualReset => {
+                warn!(
+                    "{} Not sending any more commands until it is reset manually.",
+                    state.sink.identity()
+                );
+                state.needs_reset.store(true, Ordering::Release);
+                state
+                    .current_power_state
+                    .store(PowerState::Unknown, Ordering::Release);
+                None
+            }
+        }
+    }
+
+    fn sink_by_name(&self, sink_name: &str) -> Result<Arc<SinkState>, String> {
+        self.sinks
+            .read()
+            .unwrap()
+            .values()
+            .find(|state| state.sink.name() == sink_name)
+            .cloned()
+            .ok_or_else(|| format!("no sink named \"{sink_name}\""))
+    }
+
+    /// Make the sink follow the sources again as if they had just changed.
+    fn resync_sink(&self, state: &SinkState) {
+        self.update_pending_on(state);
+        state.needs_reset.store(false, Ordering::Release);
+        state.reset_retries();
+        self.zones[state.zone].wakeup_sink_check.wakeup();
+    }
+
+    /// Force a sink on or off regardless of the sources, or with `None` return it to following
+    /// the sources. Fails if no sink with the name exists.
+    pub fn set_override(&self, sink_name: &str, forced: Option<bool>) -> Result<(), String> {
+        let state = self.sink_by_name(sink_name)?;
+        match forced {
+            Some(on) => info!("{} Forced {}.", state.sink.identity(), pwrst_log(on)),
+            None => info!("{} Override removed.", state.sink.identity()),
+        }
+        *state.forced.lock().unwrap() = forced;
+        self.resync_sink(&state);
+        Ok(())
+    }
+
+    /// Force load shedding on or off regardless of its source, or with `None` return it to
+    /// following the source. Fails if load shedding is not configured.
+    pub fn set_shedding(&self, forced: Option<bool>) -> Result<(), String> {
+        if self.config.load_shedding.is_none() {
+            return Err("load shedding is not configured".to_string());
+        }
+        match forced {
+            Some(on) => info!("Load shedding forced {}.", pwrst_log(on)),
+            None => info!("Load shedding override removed."),
+        }
+        *self.shedding_forced.lock().unwrap() = forced;
+        self.update_shedding();
+        Ok(())
+    }
+
+    /// Whether the source activates load shedding instead of controlling sinks.
+    fn is_shedding_source(&self, source_name: &str) -> bool {
+        self.config
+            .load_shedding
+            .as_ref()
+            .and_then(|settings| settings.source.as_deref())
+            == Some(source_name)
+    }
+
+    /// Whether the sink is kept off because load is shed.
+    fn sheds(&self, state: &SinkState) -> bool {
+        self.config.load_shedding.as_ref().is_some_and(|settings| {
+            self.shedding.load(Ordering::Acquire)
+                && state.sink.base_settings().priority < settings.min_priority
         })
-        .then(move |result| async move {
-            match result {
+    }
+
+    /// Start or end load shedding according to its override and source, and check the sinks
+    /// affected by the change.
+    fn update_shedding(&self) {
+        let Some(settings) = &self.config.load_shedding else {
+            return;
+        };
+        let forced = *self.shedding_forced.lock().unwrap();
+        let active = forced.unwrap_or_else(|| {
+            self.sources.read().unwrap().values().any(|s| {
+                self.is_shedding_source(s.source.name())
+                    && s.current_power_state.load(Ordering::Acquire) == PowerState::On
+            })
+        });
+        if self.shedding.swap(active, Ordering::AcqRel) == active {
+            return;
+        }
+        if active {
+            warn!(
+                "Load shedding started, turning off sinks with a priority below {}.",
+                settings.min_priority
+            );
+        } else {
+            info!("Load shedding ended.");
+        }
+        self.emit(Event::LoadShedding { active });
+        for state in self.current_sinks() {
+            if state.sink.base_settings().priority < settings.min_priority {
+                self.update_pending_on(&state);
+                self.zones[state.zone].wakeup_sink_check.wakeup();
+            }
+        }
+    }
+
+    /// Let a sink that is waiting for a manual reset after a failed command follow the sources
+    /// again. Fails if no sink with the name exists.
+    pub fn reset_sink(&self, sink_name: &str) -> Result<(), String> {
+        let state = self.sink_by_name(sink_name)?;
+        info!("{} Reset.", state.sink.identity());
+        self.resync_sink(&state);
+        Ok(())
+    }
+
+    /// Subscribe to events of the state machine.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.events.subscribe()
+    }
+
+    /// Send an event to all subscribers.
+    pub fn emit(&self, event: Event) {
+        self.statistics.record(&event);
+        // Fails if there are no subscribers, which is fine.
+        self.events.send(event).ok();
+    }
+
+    fn emit_command_failed(&self, state: &SinkState) {
+        self.emit(Event::SinkCommandFailed {
+            sink: state.sink.name().to_string(),
+            error: state.last_error.lock().unwrap().clone().unwrap_or_default(),
+        });
+    }
+
+    /// Tell subscribers about a failed poll, and if the source failed often enough in a row to
+    /// be considered unknown.
+    fn emit_source_failed(&self, state: &SourceState, failures: u32) {
+        let error = state.last_error.lock().unwrap().clone().unwrap_or_default();
+        self.emit(Event::SourceFailed {
+            source: state.source.name().to_string(),
+            error: error.clone(),
+        });
+        if failures == self.config.source_unknown_after_failures
+            && state.source.base_settings().sleepy.is_none()
+        {
+            self.emit(Event::SourceUnknown {
+                source: state.source.name().to_string(),
+                failures,
+                error,
+            });
+        }
+    }
+
+    async fn poll_source(self: Arc<Self>, identity: Identity<'static>, state: Arc<SourceState>) {
+        let state = &*state;
+        // On the first run, do not wait before getting source states.
+        let mut is_first_run = true;
+
+        loop {
+            // First sleep until the next scan interval, then check, but with a timeout.
+            if !is_first_run {
+                state.wait_before_check().await;
+            }
+            is_first_run = false;
+            if !state.source.combines().is_empty() {
+                let combined = self.combined_power(state);
+                state.record_success();
+                self.set_source_power(state, combined);
+                continue;
+            }
+            let cancel = CancellationToken::new();
+            let result = {
+                // Cancelled when the poll is over, in particular when it timed out or polling
+                // the source was stopped, so it can abort work that outlives the future.
+                let _cancel_guard = cancel.clone().drop_guard();
+                timeout(
+                    Duration::from_secs(state.source.base_settings().timeout_sec as u64),
+                    AssertUnwindSafe(state.source.is_active(&cancel)).catch_unwind(),
+                )
+                .await
+            };
+
+            let timed_out = result.is_err();
+            let (error, kind) = match result {
                 Ok(Ok(Ok(new_state))) => {
-                    let prev_state: Result<bool, _> = state
-                        .current_power_state
-                        .swap(new_state.into(), Ordering::AcqRel)
-                        .try_into();
-                    if prev_state != Ok(new_state) {
-                        info!("{} New power state: {}", identity, pwrst_log(new_state));
-                        Self::update_pending_sink_states(
-                            sinks,
-                            &state.source.base_settings().name,
-                            new_state,
-                        )
-                        .await;
-                        if let Some(wakeup) = manual_wakeup.upgrade() {
-                            debug!("waking up sink check");
-                            wakeup.wakeup();
+                    if state.record_success() {
+                        self.emit(Event::SourceRecovered {
+                            source: state.source.name().to_string(),
+                        });
+                    }
+                    self.set_source_power(state, new_state.into());
+                    continue;
+                }
+                Ok(Err(e)) => {
+                    let panic = panic_to_string(e);
+                    error!("{} Panic while getting power state: {}", identity, panic);
+                    (format!("panic: {panic}"), ErrorKind::Other)
+                }
+                Ok(Ok(Err(e))) => {
+                    match state.errors.record(e.kind()) {
+                        ErrorLog::First => {
+                            error!("{} Error while getting power state: {}", identity, e)
+                        }
+                        ErrorLog::Summary(count, since) => {
+                            error!("{} {}: {}", identity, still_failing(count, since), e)
+                        }
+                        ErrorLog::Suppressed => {
+                            debug!("{} Error while getting power state: {}", identity, e)
                         }
                     }
+                    (e.to_string(), e.kind())
                 }
-                Ok(Err(e)) => error!(
-                    "{} Panic while getting power state: {}",
-                    identity,
-                    panic_to_string(e)
-                ),
-                Ok(Ok(Err(e))) => error!("{} Error while getting power state: {}", identity, e),
-                Err(_) => error!("{} Timeout while scanning for power state.", identity),
+                // Only logged as an error once, when the watchdog escalates.
+                Err(_) if state.degraded.load(Ordering::Acquire) => {
+                    debug!("{} Timeout while scanning for power state.", identity);
+                    ("timeout".to_string(), ErrorKind::Timeout)
+                }
+                Err(_) => {
+                    match state.errors.record(ErrorKind::Timeout) {
+                        ErrorLog::First => {
+                            warn!("{} Timeout while scanning for power state.", identity)
+                        }
+                        ErrorLog::Summary(count, since) => {
+                            warn!(
+                                "{} {} with timeouts.",
+                                identity,
+                                still_failing(count, since)
+                            )
+                        }
+                        ErrorLog::Suppressed => {
+                            debug!("{} Timeout while scanning for power state.", identity)
+                        }
+                    }
+                    ("timeout".to_string(), ErrorKind::Timeout)
+                }
+            };
+            let failures = state.record_failure(error, kind);
+            if timed_out {
+                state.record_timeout();
+            } else {
+                state.consecutive_timeouts.store(0, Ordering::Release);
             }
-        })
-        .instrument(info_span!(
-            "check_source",
-            source = state.source.base_settings().name()
-        ))
-        .boxed_local()
-        .fuse()
+            self.emit_source_failed(state, failures);
+            if let Some(fallback) = state.error_fallback() {
+                self.set_source_power(state, fallback);
+            }
+        }
     }
 
-    async fn update_pending_sink_states(
-        sinks: Weak<HashMap<Identity<'_>, SinkState>>,
-        source_name: &str,
-        state: bool,
-    ) {
-        let maybe_fut = sinks.upgrade().map(|sinks| async move {
-            for sink_state in sinks.values() {
-                if sink_state
-                    .sink
-                    .base_settings()
-                    .allows_source_for_on(source_name)
-                {
-                    if state {
-                        sink_state.should_turn_on.store(true, Ordering::Release);
-                    }
-                    debug!(
-                        "{} Marked for new pending power state: {}.",
-                        sink_state.sink.identity(),
-                        pwrst_log(state)
-                    );
-                }
+    /// The power state of a source that combines other sources, from their current states.
+    fn combined_power(&self, state: &SourceState) -> PowerState {
+        let sources = self.sources.read().unwrap();
+        let active = sources
+            .values()
+            .filter(|s| state.source.combines().iter().any(|n| n == s.source.name()))
+            .map(|s| {
+                let power = s.current_power_state.load(Ordering::Acquire);
+                (s.source.name(), power.try_into().ok())
+            })
+            .collect();
+        state
+            .source
+            .combine(&active)
+            .map_or(PowerState::Unknown, PowerState::from)
+    }
+
+    /// Re-evaluate the sources that combine the source with the name.
+    fn wakeup_combining_sources(&self, source_name: &str) {
+        for state in self.sources.read().unwrap().values() {
+            if state.source.combines().iter().any(|n| n == source_name) {
+                state.members_changed.notify_one();
             }
+        }
+    }
+
+    /// Mark the sink to be turned on if its sources are on, as if they had just changed.
+    fn update_pending_on(&self, state: &SinkState) {
+        let any_on = self.sources.read().unwrap().values().any(|s| {
+            self.source_triggers(state, s.source.name())
+                && s.current_power_state.load(Ordering::Acquire) == PowerState::On
         });
-        match maybe_fut {
-            None => {}
-            Some(fut) => fut.await,
+        state
+            .should_turn_on
+            .store(any_on && self.triggers_on(state), Ordering::Release);
+    }
+
+    /// Update the power state of a source and, if it changed, the pending states of the sinks.
+    fn set_source_power(&self, state: &SourceState, new_state: PowerState) {
+        let new_state = state.linger(new_state);
+        let prev_state = state.current_power_state.swap(new_state, Ordering::AcqRel);
+        if prev_state == new_state {
+            return;
         }
+        info!("{} New power state: {}", state.source.identity(), new_state);
+        self.emit(Event::SourceChanged {
+            source: state.source.name().to_string(),
+            power_state: new_state,
+        });
+        if let Ok(new_state) = new_state.try_into() {
+            self.update_pending_sink_states(&state.source.base_settings().name, new_state);
+        }
+        // Sources that combine others can't be combined themselves.
+        if state.source.combines().is_empty() {
+            self.wakeup_combining_sources(state.source.name());
+        }
+        if self.is_shedding_source(state.source.name()) {
+            self.update_shedding();
+            return;
+        }
+        debug!("waking up sink check");
+        self.wakeup_zones_of_source(state.source.name());
     }
 
-    fn log_sink_error(
-        sink: &impl Named,
-        result: Result<Result<(), Box<dyn Error>>, Box<dyn Any + Send>>,
-    ) -> bool {
-        match result {
-            Ok(Ok(_)) => true,
-            Ok(Err(err)) => {
-                error!("{} Failed setting power state: {}", sink.identity(), err);
-                false
+    fn update_pending_sink_states(&self, source_name: &str, state: bool) {
+        // Snapshot, since checking the trigger mode takes the lock of the sources.
+        for sink_state in &self.current_sinks() {
+            if !self.sink_has_source(sink_state, source_name) {
+                continue;
             }
-            Err(panic) => {
-                error!(
-                    "{} Panic while setting power state: {}",
-                    sink.identity(),
-                    panic_to_string(panic)
+            // Sinks that were given up on get a new chance on every source transition.
+            sink_state.reset_retries();
+            if sink_state
+                .sink
+                .base_settings()
+                .allows_source_for_on(source_name)
+            {
+                if state && self.triggers_on(sink_state) {
+                    sink_state.should_turn_on.store(true, Ordering::Release);
+                }
+                debug!(
+                    "{} Marked for new pending power state: {}.",
+                    sink_state.sink.identity(),
+                    pwrst_log(state)
                 );
-                false
             }
         }
     }
+
+    /// Whether the currently active sources are enough to turn on the sink, according to its
+    /// trigger mode.
+    fn triggers_on(&self, sink_state: &SinkState) -> bool {
+        if !sink_state.routes.is_empty() {
+            return sink_state
+                .routes
+                .iter()
+                .any(|&i| self.route_triggers_on(sink_state, &self.routes[i]));
+        }
+        match sink_state.sink.base_settings().trigger_mode {
+            TriggerMode::Any => true,
+            TriggerMode::All => self
+                .sources
+                .read()
+                .unwrap()
+                .values()
+                .filter(|s| self.source_triggers(sink_state, s.source.name()))
+                .all(|s| s.current_power_state.load(Ordering::Acquire) == PowerState::On),
+        }
+    }
+
+    /// Whether the currently active sources of the route are enough to turn on the sink,
+    /// according to the trigger mode of the route.
+    fn route_triggers_on(&self, sink_state: &SinkState, route: &RouteSettings) -> bool {
+        let sources = self.sources.read().unwrap();
+        let active: Vec<bool> = sources
+            .values()
+            .filter(|s| route.sources.iter().any(|name| name == s.source.name()))
+            .filter(|s| {
+                sink_state
+                    .sink
+                    .base_settings()
+                    .allows_source_for_on(s.source.name())
+            })
+            .map(|s| s.current_power_state.load(Ordering::Acquire) == PowerState::On)
+            .collect();
+        match route.trigger_mode {
+            TriggerMode::Any => active.contains(&true),
+            // A route without any running sources can't trigger.
+            TriggerMode::All => !active.is_empty() && !active.contains(&false),
+        }
+    }
 }
+
+/// Summary of an error that repeated since it was last logged.
+fn still_failing(count: u32, since: Duration) -> String {
+    format!(
+        "Still failing, {} times in the last {} min",
+        count,
+        since.as_secs() / 60
+    )
+}
+
+#[cfg(test)]
+mod tests;
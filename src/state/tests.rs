@@ -0,0 +1,262 @@
+use crate::error::ErrorKind;
+use crate::state::PowerState;
+use crate::testing::{Harness, MockCommand, MockSink, MockSource};
+use std::time::Duration;
+use tokio::time::{sleep, Instant};
+
+const CONFIG: &str = r#"
+[general]
+power-off-check-interval-sec = 60
+"#;
+
+async fn advance(sec: u64) {
+    sleep(Duration::from_secs(sec)).await;
+}
+
+#[tokio::test(start_paused = true)]
+async fn turns_off_after_power_off_check_interval() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    let _harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    advance(59).await;
+    assert_eq!(sink.commands(), []);
+    advance(2).await;
+    assert_eq!(sink.commands(), [MockCommand::Off]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn follows_source() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    let _harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    advance(1).await;
+    source.set(true);
+    advance(1).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+
+    source.set(false);
+    advance(59).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+    advance(2).await;
+    assert_eq!(sink.commands(), [MockCommand::On, MockCommand::Off]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn source_on_again_cancels_power_off() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    let _harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(1).await;
+    source.set(false);
+    advance(30).await;
+    source.set(true);
+    advance(60).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_with_backoff() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    sink.fail_next([ErrorKind::Network, ErrorKind::Timeout]);
+    let start = Instant::now();
+    let _harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(30).await;
+    let offsets: Vec<_> = sink
+        .calls()
+        .into_iter()
+        .map(|(command, at)| (command, (at - start).as_secs()))
+        .collect();
+    // The default retry delay starts at 5 sec and doubles.
+    assert_eq!(
+        offsets,
+        [
+            (MockCommand::On, 0),
+            (MockCommand::On, 5),
+            (MockCommand::On, 15)
+        ]
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn does_not_retry_rejected_credentials() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    sink.fail_next([ErrorKind::Auth]);
+    let harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(600).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+    let health = harness.state.health();
+    let lamp = health.components.iter().find(|c| c.name == "Lamp").unwrap();
+    assert!(!lamp.healthy);
+    assert_eq!(lamp.error_kind, Some(ErrorKind::Auth));
+
+    // The next source transition is a new chance.
+    source.set(false);
+    advance(1).await;
+    source.set(true);
+    advance(1).await;
+    assert_eq!(sink.commands(), [MockCommand::On, MockCommand::On]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn all_trigger_mode_rechecks_sources_before_retrying() {
+    let desk = MockSource::named("Desk");
+    let tv = MockSource::named("TV");
+    let sink = MockSink::new(
+        r#"name = "Lamp"
+        enable = true
+        timeout-sec = 5
+        trigger-mode = "all""#,
+    );
+    sink.fail_next([ErrorKind::Network]);
+    let _harness = Harness::start(CONFIG, [desk.clone(), tv.clone()], [sink.clone()]).await;
+
+    desk.set(true);
+    tv.set(true);
+    advance(1).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+
+    // The retry is due while only one of the sources is on.
+    tv.set(false);
+    advance(30).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn override_holds_sink() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    let harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    harness.state.set_override("Lamp", Some(true)).unwrap();
+    advance(600).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+
+    harness.state.set_override("Lamp", None).unwrap();
+    advance(61).await;
+    assert_eq!(sink.commands(), [MockCommand::On, MockCommand::Off]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn failing_source_keeps_last_state() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    let _harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(1).await;
+    source.script([Err(ErrorKind::Network)]);
+    advance(120).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+    assert!(source.polls() >= 12);
+}
+
+#[tokio::test(start_paused = true)]
+async fn failing_source_assumed_off() {
+    let source = MockSource::new(
+        r#"name = "Desk"
+        enable = true
+        timeout-sec = 5
+        poll-interval-sec = { off = 10, on = 10 }
+        on-error = "assume-off""#,
+    );
+    let sink = MockSink::named("Lamp");
+    let _harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(1).await;
+    source.script([Err(ErrorKind::Network)]);
+    advance(62).await;
+    assert_eq!(sink.commands(), [MockCommand::On, MockCommand::Off]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn drain_waits_for_commands_in_progress() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::named("Lamp");
+    sink.set_delay(Duration::from_secs(3));
+    let harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(1).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+    let start = Instant::now();
+    harness.state.drain(Duration::from_secs(30)).await;
+    assert_eq!((Instant::now() - start).as_secs(), 2);
+
+    // No new commands are sent once drained.
+    source.set(false);
+    advance(120).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn drain_aborts_commands_after_grace() {
+    let source = MockSource::named("Desk");
+    let sink = MockSink::new(
+        r#"name = "Lamp"
+        enable = true
+        timeout-sec = 900"#,
+    );
+    sink.set_delay(Duration::from_secs(600));
+    let harness = Harness::start(CONFIG, [source.clone()], [sink.clone()]).await;
+
+    source.set(true);
+    advance(1).await;
+    let start = Instant::now();
+    harness.state.drain(Duration::from_secs(5)).await;
+    assert!(Instant::now() - start <= Duration::from_secs(5));
+
+    // The aborted command never completes, and is not retried.
+    advance(1200).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+    let status = harness.state.status();
+    assert_ne!(status.sinks[0].power_state, PowerState::On);
+}
+
+#[cfg(feature = "source-composite")]
+#[tokio::test(start_paused = true)]
+async fn composite_source_follows_its_sources() {
+    let desk = MockSource::named("Desk");
+    let tv = MockSource::named("TV");
+    let sink = MockSink::new(
+        r#"name = "Lamp"
+        enable = true
+        timeout-sec = 5
+        on-source-whitelist = ["Both"]
+        off-source-whitelist = ["Both"]"#,
+    );
+    let harness = Harness::start(CONFIG, [desk.clone(), tv.clone()], [sink.clone()]).await;
+    let both: crate::source::composite::Settings = crate::testing::parse(
+        r#"name = "Both"
+        enable = true
+        timeout-sec = 5
+        poll-interval-sec = { off = 600, on = 600 }
+        expression = { all = [{ source = "Desk" }, { source = "TV" }] }"#,
+    );
+    harness
+        .state
+        .add_source(Box::new(both.create_source().unwrap()));
+
+    desk.set(true);
+    advance(1).await;
+    assert_eq!(sink.commands(), []);
+    // Re-evaluated as soon as a source changes, not only every poll interval.
+    tv.set(true);
+    advance(1).await;
+    assert_eq!(sink.commands(), [MockCommand::On]);
+    assert_eq!(desk.polls() + tv.polls(), 4);
+
+    desk.set(false);
+    advance(61).await;
+    assert_eq!(sink.commands(), [MockCommand::On, MockCommand::Off]);
+}
@@ -0,0 +1,387 @@
+use crate::event::Event;
+use crate::state::PowerState;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::{error, warn};
+
+pub const SECS_PER_DAY: u64 = 24 * 60 * 60;
+const DAYS_PER_WEEK: u64 = 7;
+/// Number of days the daily counts are kept for, including today, so that the week before
+/// today can still be reported.
+const DAYS_KEPT: u64 = DAYS_PER_WEEK + 1;
+
+/// On-time of all sources and sinks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StatisticsReport {
+    pub sources: Vec<OnTimeReport>,
+    pub sinks: Vec<OnTimeReport>,
+}
+
+/// On-time of a source or sink. Days are UTC days.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OnTimeReport {
+    pub name: String,
+    pub today_sec: u64,
+    /// The last seven days, including today.
+    pub week_sec: u64,
+    pub total_sec: u64,
+    /// Estimated energy use in watt-hours, for sinks with `watts` set.
+    pub today_wh: Option<f64>,
+    pub week_wh: Option<f64>,
+    pub total_wh: Option<f64>,
+}
+
+/// Summary of the sinks over the full days of a period, such as yesterday.
+#[derive(Clone, Debug, Serialize)]
+pub struct PeriodReport {
+    /// The first day of the period, in days since the Unix epoch.
+    pub first_day: u64,
+    pub days: u64,
+    pub sinks: Vec<SinkPeriodReport>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SinkPeriodReport {
+    pub name: String,
+    pub on_sec: u64,
+    /// How often the sink was turned on or off.
+    pub toggles: u32,
+    /// Failed commands.
+    pub errors: u32,
+    /// Estimated energy use in watt-hours, if the sink has `watts` set.
+    pub wh: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct OnTime {
+    total_sec: u64,
+    /// Seconds on per day, by days since the Unix epoch. Only the last days are kept.
+    daily_sec: BTreeMap<u64, u64>,
+    /// Transitions between on and off per day, like `daily_sec`.
+    #[serde(default)]
+    daily_toggles: BTreeMap<u64, u32>,
+    /// Failed commands or polls per day, like `daily_sec`.
+    #[serde(default)]
+    daily_errors: BTreeMap<u64, u32>,
+}
+
+impl OnTime {
+    /// Add the time between the two Unix timestamps, split into days.
+    fn add(&mut self, mut from: u64, to: u64) {
+        self.total_sec += to.saturating_sub(from);
+        while from < to {
+            let day = from / SECS_PER_DAY;
+            let end = to.min((day + 1) * SECS_PER_DAY);
+            *self.daily_sec.entry(day).or_default() += end - from;
+            from = end;
+        }
+        self.prune(to);
+    }
+
+    fn count_toggle(&mut self, now: u64) {
+        *self.daily_toggles.entry(now / SECS_PER_DAY).or_default() += 1;
+        self.prune(now);
+    }
+
+    fn count_error(&mut self, now: u64) {
+        *self.daily_errors.entry(now / SECS_PER_DAY).or_default() += 1;
+        self.prune(now);
+    }
+
+    /// Forget the days that are no longer kept.
+    fn prune(&mut self, now: u64) {
+        let first_kept = (now / SECS_PER_DAY).saturating_sub(DAYS_KEPT - 1);
+        self.daily_sec.retain(|day, _| *day >= first_kept);
+        self.daily_toggles.retain(|day, _| *day >= first_kept);
+        self.daily_errors.retain(|day, _| *day >= first_kept);
+    }
+
+    fn since_day(&self, first_day: u64) -> u64 {
+        self.daily_sec.range(first_day..).map(|(_, sec)| sec).sum()
+    }
+
+    fn period_report(
+        &self,
+        name: &str,
+        first_day: u64,
+        days: u64,
+        watts: Option<f64>,
+    ) -> SinkPeriodReport {
+        let period = first_day..first_day + days;
+        let on_sec = self
+            .daily_sec
+            .range(period.clone())
+            .map(|(_, sec)| sec)
+            .sum();
+        SinkPeriodReport {
+            name: name.to_string(),
+            on_sec,
+            toggles: self
+                .daily_toggles
+                .range(period.clone())
+                .map(|(_, n)| n)
+                .sum(),
+            errors: self.daily_errors.range(period).map(|(_, n)| n).sum(),
+            wh: watts.map(|watts| watts * on_sec as f64 / 3600.0),
+        }
+    }
+
+    fn report(&self, name: &str, now: u64, watts: Option<f64>) -> OnTimeReport {
+        let today = now / SECS_PER_DAY;
+        let today_sec = self.since_day(today);
+        let week_sec = self.since_day(today.saturating_sub(DAYS_PER_WEEK - 1));
+        let wh = |sec: u64| watts.map(|watts| watts * sec as f64 / 3600.0);
+        OnTimeReport {
+            name: name.to_string(),
+            today_sec,
+            week_sec,
+            total_sec: self.total_sec,
+            today_wh: wh(today_sec),
+            week_wh: wh(week_sec),
+            total_wh: wh(self.total_sec),
+        }
+    }
+}
+
+/// What is persisted: the on-time by category and name.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct Totals {
+    source: BTreeMap<String, OnTime>,
+    sink: BTreeMap<String, OnTime>,
+}
+
+impl Totals {
+    fn category(&mut self, category: &str) -> &mut BTreeMap<String, OnTime> {
+        match category {
+            "source" => &mut self.source,
+            _ => &mut self.sink,
+        }
+    }
+
+    fn on_time(&mut self, category: &str, name: &str) -> &mut OnTime {
+        self.category(category).entry(name.to_string()).or_default()
+    }
+}
+
+struct Inner {
+    totals: Totals,
+    /// Unix timestamps since which sources and sinks that are on have not been added to the
+    /// totals yet, by category and name.
+    on_since: HashMap<(&'static str, String), u64>,
+}
+
+impl Inner {
+    /// Add the on-time of everything that is on up to now to the totals.
+    fn fold(&mut self, now: u64) {
+        for ((category, name), since) in &mut self.on_since {
+            self.totals.on_time(category, name).add(*since, now);
+            *since = now;
+        }
+    }
+}
+
+/// Cumulative on-time of sources and sinks, counted from their transitions.
+pub struct Statistics {
+    path: Option<PathBuf>,
+    inner: Mutex<Inner>,
+}
+
+impl Statistics {
+    /// Continue with the statistics persisted at `path`. Without a path, they are only kept
+    /// while running.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let totals = match &path {
+            Some(path) if path.exists() => match Self::read(path) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(
+                        "Failed reading statistics from {}, starting over: {}",
+                        path.display(),
+                        e
+                    );
+                    Totals::default()
+                }
+            },
+            _ => Totals::default(),
+        };
+        Self {
+            path,
+            inner: Mutex::new(Inner {
+                totals,
+                on_since: HashMap::new(),
+            }),
+        }
+    }
+
+    fn read(path: &Path) -> Result<Totals, Box<dyn Error>> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Count the transitions and failures of sources and sinks in the event.
+    pub fn record(&self, event: &Event) {
+        let (category, name, power_state) = match event {
+            Event::SourceChanged {
+                source,
+                power_state,
+            } => ("source", source, Some(*power_state)),
+            Event::SinkChanged { sink, power_state } => ("sink", sink, Some(*power_state)),
+            Event::SourceFailed { source, .. } => ("source", source, None),
+            Event::SinkCommandFailed { sink, .. } => ("sink", sink, None),
+            _ => return,
+        };
+        let now = unix_now();
+        let mut inner = self.inner.lock().unwrap();
+        let Some(power_state) = power_state else {
+            inner.totals.on_time(category, name).count_error(now);
+            return;
+        };
+        let key = (category, name.clone());
+        let was_on = inner.on_since.contains_key(&key);
+        if (power_state == PowerState::On) != was_on {
+            inner.totals.on_time(category, name).count_toggle(now);
+        }
+        if power_state == PowerState::On {
+            inner.on_since.entry(key).or_insert(now);
+        } else if let Some(since) = inner.on_since.remove(&key) {
+            inner.totals.on_time(category, name).add(since, now);
+            self.save(&inner);
+        }
+    }
+
+    /// Add the on-time up to now to the totals and persist them.
+    pub fn flush(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.fold(unix_now());
+        self.save(&inner);
+    }
+
+    fn save(&self, inner: &Inner) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        if let Err(e) = Self::write(path, &inner.totals) {
+            error!("Failed saving statistics to {}: {}", path.display(), e);
+        }
+    }
+
+    fn write(path: &Path, totals: &Totals) -> Result<(), Box<dyn Error>> {
+        // Written to a temporary file first, so that a crash can't leave a truncated file.
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, serde_json::to_vec(totals)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// The on-time of all sources and sinks so far, with energy estimates for the sinks that
+    /// have a wattage.
+    pub fn report(&self, sink_watts: impl Fn(&str) -> Option<f64>) -> StatisticsReport {
+        let now = unix_now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.fold(now);
+        let totals = &inner.totals;
+        StatisticsReport {
+            sources: totals
+                .source
+                .iter()
+                .map(|(name, on_time)| on_time.report(name, now, None))
+                .collect(),
+            sinks: totals
+                .sink
+                .iter()
+                .map(|(name, on_time)| on_time.report(name, now, sink_watts(name)))
+                .collect(),
+        }
+    }
+
+    /// Summary of the sinks over the `days` full days before today.
+    pub fn period_report(
+        &self,
+        days: u64,
+        sink_watts: impl Fn(&str) -> Option<f64>,
+    ) -> PeriodReport {
+        let now = unix_now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.fold(now);
+        let first_day = (now / SECS_PER_DAY).saturating_sub(days);
+        PeriodReport {
+            first_day,
+            days,
+            sinks: inner
+                .totals
+                .sink
+                .iter()
+                .map(|(name, on_time)| {
+                    on_time.period_report(name, first_day, days, sink_watts(name))
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Display for PeriodReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let last_day = self.first_day + self.days.saturating_sub(1);
+        match self.days {
+            1 => writeln!(f, "Power report for {}:", format_day(self.first_day))?,
+            _ => writeln!(
+                f,
+                "Power report for {} to {}:",
+                format_day(self.first_day),
+                format_day(last_day)
+            )?,
+        }
+        for sink in &self.sinks {
+            write!(
+                f,
+                "- {}: on for {}, turned on or off {} times, {} failed commands",
+                sink.name,
+                format_duration(sink.on_sec),
+                sink.toggles,
+                sink.errors
+            )?;
+            match sink.wh {
+                Some(wh) => writeln!(f, ", {:.2} kWh", wh / 1000.0)?,
+                None => writeln!(f)?,
+            }
+        }
+        let wh = self.sinks.iter().filter_map(|sink| sink.wh).sum::<f64>();
+        write!(f, "Estimated energy use: {:.2} kWh", wh / 1000.0)
+    }
+}
+
+pub fn format_duration(sec: u64) -> String {
+    format!("{}h {:02}m", sec / 3600, sec / 60 % 60)
+}
+
+/// The UTC date of a day since the Unix epoch, as `YYYY-MM-DD`.
+fn format_day(day: u64) -> String {
+    // Converted from days since 0000-03-01, so that the leap day is the last day of the year.
+    let days = day + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = (month_from_march + 2) % 12 + 1;
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day_of_month:02}")
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests;
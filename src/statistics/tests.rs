@@ -0,0 +1,31 @@
+use super::*;
+
+#[test]
+fn formats_days() {
+    assert_eq!(format_day(0), "1970-01-01");
+    assert_eq!(format_day(11_016), "2000-02-29");
+    assert_eq!(format_day(11_017), "2000-03-01");
+    assert_eq!(format_day(20_741), "2026-10-15");
+}
+
+#[test]
+fn reports_full_days_of_period() {
+    let day = 20_000;
+    let mut on_time = OnTime::default();
+    // Two hours on the day before, half an hour today.
+    on_time.add(day * SECS_PER_DAY - 7200, day * SECS_PER_DAY + 1800);
+    on_time.count_toggle((day - 1) * SECS_PER_DAY);
+    on_time.count_error((day - 2) * SECS_PER_DAY);
+    on_time.count_toggle(day * SECS_PER_DAY);
+
+    let yesterday = on_time.period_report("Lamp", day - 1, 1, Some(100.0));
+    assert_eq!(yesterday.on_sec, 7200);
+    assert_eq!(yesterday.toggles, 1);
+    assert_eq!(yesterday.errors, 0);
+    assert_eq!(yesterday.wh, Some(200.0));
+
+    let week = on_time.period_report("Lamp", day - 7, 7, None);
+    assert_eq!(week.on_sec, 7200);
+    assert_eq!(week.toggles, 1);
+    assert_eq!(week.errors, 1);
+}
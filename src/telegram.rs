@@ -0,0 +1,371 @@
+#![cfg(feature = "telegram")]
+
+use crate::event::{Event, EventKind};
+use crate::http_client;
+use crate::settings::TelegramSettings;
+use crate::state::State;
+use crate::statistics::format_duration;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::pending;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::AbortHandle;
+use tokio::time::sleep;
+use tracing::{debug, error, info, warn};
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Seconds the Bot API holds a request for updates open while there are none.
+const LONG_POLL_SEC: u64 = 50;
+/// Time to wait after failing to get updates.
+const RETRY_DELAY: Duration = Duration::from_secs(10);
+
+const HELP: &str = "/status - state of all sources and sinks
+/force on|off <sink> [duration] - force a sink on or off, e.g. /force on Projector 30m
+/release <sink> - let a sink follow its sources again";
+
+/// A command sent to the bot.
+#[derive(Clone, PartialEq, Debug)]
+enum Command {
+    Help,
+    Status,
+    Force {
+        sink: String,
+        on: bool,
+        duration: Option<Duration>,
+    },
+    Release {
+        sink: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct ApiResponse<T> {
+    ok: bool,
+    description: Option<String>,
+    result: Option<T>,
+}
+
+#[derive(Deserialize)]
+struct Update {
+    update_id: i64,
+    message: Option<Message>,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    chat: Chat,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Chat {
+    id: i64,
+}
+
+struct Bot {
+    settings: TelegramSettings,
+    client: Client,
+    /// The API URL with the token, e.g. `https://api.telegram.org/bot<token>/`.
+    base_url: Url,
+    state: Arc<State>,
+    /// Tasks ending timed overrides, by sink name.
+    timers: Mutex<HashMap<String, AbortHandle>>,
+}
+
+/// Answer commands from the configured chats and report events to them. Never completes.
+pub async fn serve(settings: &TelegramSettings, state: Arc<State>) {
+    // Subscribe before connecting, so no events are missed.
+    let mut events = state.subscribe();
+    let bot = match Bot::new(settings.clone(), state) {
+        Ok(v) => v,
+        Err(e) => {
+            error!("Failed creating Telegram bot: {}", e);
+            return pending().await;
+        }
+    };
+    info!("Answering Telegram chats {:?}.", settings.chat_ids);
+    let report = async {
+        loop {
+            match events.recv().await {
+                Ok(event) if bot.wants(&event) => bot.broadcast(&event.to_string()).await,
+                Ok(_) => {}
+                Err(RecvError::Lagged(n)) => warn!("Telegram bot missed {} events.", n),
+                Err(RecvError::Closed) => return pending().await,
+            }
+        }
+    };
+    tokio::join!(report, bot.answer_commands());
+}
+
+impl Bot {
+    fn new(settings: TelegramSettings, state: Arc<State>) -> Result<Self> {
+        let token = settings
+            .token
+            .resolve()?
+            .ok_or("the bot token is required")?;
+        // Not joined, since the colon in the token would be taken for a scheme.
+        let api_url = settings.api_url.trim_end_matches('/');
+        let base_url = Url::parse(&format!("{api_url}/bot{token}/"))?;
+        let client = http_client::builder(&base_url)?
+            .timeout(Duration::from_secs(LONG_POLL_SEC + 10))
+            .build()?;
+        Ok(Self {
+            settings,
+            client,
+            base_url,
+            state,
+            timers: Default::default(),
+        })
+    }
+
+    fn wants(&self, event: &Event) -> bool {
+        let kind = event.kind();
+        match &self.settings.events {
+            Some(events) => events.contains(&kind),
+            None => kind == EventKind::SinkChanged || kind.notify_by_default(),
+        }
+    }
+
+    /// Call a method of the Bot API.
+    async fn call<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<T> {
+        let response = self
+            .client
+            .post(self.base_url.join(method)?)
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(&params)?)
+            .send()
+            .await?;
+        // Errors are described in the body, also with an error status.
+        let response: ApiResponse<T> = serde_json::from_slice(&response.bytes().await?)?;
+        match (response.ok, response.result) {
+            (true, Some(result)) => Ok(result),
+            _ => Err(response
+                .description
+                .unwrap_or_else(|| "unknown error".to_string())
+                .into()),
+        }
+    }
+
+    async fn send(&self, chat_id: i64, text: &str) -> Result<()> {
+        let params = json!({ "chat_id": chat_id, "text": text });
+        self.call::<serde_json::Value>("sendMessage", params)
+            .await
+            .map(|_| ())
+    }
+
+    /// Send the text to all configured chats.
+    async fn broadcast(&self, text: &str) {
+        for &chat_id in &self.settings.chat_ids {
+            if let Err(e) = self.send(chat_id, text).await {
+                warn!("Failed sending Telegram message to chat {}: {}", chat_id, e);
+            }
+        }
+    }
+
+    /// Receive messages and answer the commands in them. Never completes.
+    async fn answer_commands(&self) {
+        let mut offset = 0;
+        loop {
+            let params = json!({
+                "offset": offset,
+                "timeout": LONG_POLL_SEC,
+                "allowed_updates": ["message"],
+            });
+            let updates: Vec<Update> = match self.call("getUpdates", params).await {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!("Failed getting Telegram updates: {}", e);
+                    sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            for update in updates {
+                offset = offset.max(update.update_id + 1);
+                let Some(Message {
+                    chat,
+                    text: Some(text),
+                }) = update.message
+                else {
+                    continue;
+                };
+                if !self.settings.chat_ids.contains(&chat.id) {
+                    debug!("Ignoring message from chat {}.", chat.id);
+                    continue;
+                }
+                let answer = match parse_command(&text) {
+                    Ok(command) => self.run(command),
+                    Err(e) => e,
+                };
+                if let Err(e) = self.send(chat.id, &answer).await {
+                    warn!("Failed answering Telegram chat {}: {}", chat.id, e);
+                }
+            }
+        }
+    }
+
+    /// Run the command and return the answer.
+    fn run(&self, command: Command) -> String {
+        match command {
+            Command::Help => HELP.to_string(),
+            Command::Status => self.status(),
+            Command::Force { sink, on, duration } => {
+                let sink = self.sink_name(&sink);
+                if let Err(e) = self.state.set_override(&sink, Some(on)) {
+                    return e;
+                }
+                let state = if on { "on" } else { "off" };
+                match duration {
+                    Some(duration) => {
+                        self.release_after(&sink, duration);
+                        format!(
+                            "Forced {sink} {state} for {}.",
+                            format_duration(duration.as_secs())
+                        )
+                    }
+                    None => {
+                        self.cancel_timer(&sink);
+                        format!("Forced {sink} {state}.")
+                    }
+                }
+            }
+            Command::Release { sink } => {
+                let sink = self.sink_name(&sink);
+                if let Err(e) = self.state.set_override(&sink, None) {
+                    return e;
+                }
+                self.cancel_timer(&sink);
+                format!("{sink} follows its sources again.")
+            }
+        }
+    }
+
+    fn status(&self) -> String {
+        let status = self.state.status();
+        let mut text = String::from("Sources:");
+        for source in &status.sources {
+            text.push_str(&format!("\n- {}: {}", source.name, source.power_state));
+        }
+        text.push_str("\n\nSinks:");
+        for sink in &status.sinks {
+            text.push_str(&format!("\n- {}: {}", sink.name, sink.power_state));
+            if let Some(forced) = sink.forced {
+                text.push_str(&format!(", forced {forced}"));
+            }
+            if let Some(error) = &sink.last_error {
+                text.push_str(&format!(", failed: {error}"));
+            }
+        }
+        text
+    }
+
+    /// The name of the sink, matched ignoring case, since phone keyboards like to capitalize.
+    fn sink_name(&self, name: &str) -> String {
+        self.state
+            .status()
+            .sinks
+            .into_iter()
+            .map(|sink| sink.name)
+            .find(|sink| sink.eq_ignore_ascii_case(name))
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    /// Remove the override of the sink after the duration, replacing an earlier timer.
+    fn release_after(&self, sink: &str, duration: Duration) {
+        let state = self.state.clone();
+        let name = sink.to_string();
+        let task = tokio::spawn(async move {
+            sleep(duration).await;
+            info!("Timed override of {} ended.", name);
+            state.set_override(&name, None).ok();
+        });
+        let previous = self
+            .timers
+            .lock()
+            .unwrap()
+            .insert(sink.to_string(), task.abort_handle());
+        if let Some(previous) = previous {
+            previous.abort();
+        }
+    }
+
+    fn cancel_timer(&self, sink: &str) {
+        if let Some(timer) = self.timers.lock().unwrap().remove(sink) {
+            timer.abort();
+        }
+    }
+}
+
+/// Parse a message such as `/force on Projector 30m`. Fails with the answer to send.
+fn parse_command(text: &str) -> std::result::Result<Command, String> {
+    let mut words = text.split_whitespace();
+    // In groups, commands may be addressed to a bot, e.g. `/status@MyBot`.
+    let command = words.next().unwrap_or_default();
+    let command = command.split('@').next().unwrap_or_default();
+    let mut args: Vec<&str> = words.collect();
+    match command {
+        "/start" | "/help" => Ok(Command::Help),
+        "/status" => Ok(Command::Status),
+        "/force" => {
+            let usage = || "Usage: /force on|off <sink> [duration]".to_string();
+            let on = match args.first().copied() {
+                Some("on") => true,
+                Some("off") => false,
+                _ => return Err(usage()),
+            };
+            let duration = args.last().and_then(|last| parse_duration(last));
+            if duration.is_some() {
+                args.pop();
+            }
+            if args.len() < 2 {
+                return Err(usage());
+            }
+            Ok(Command::Force {
+                sink: args[1..].join(" "),
+                on,
+                duration,
+            })
+        }
+        "/release" if !args.is_empty() => Ok(Command::Release {
+            sink: args.join(" "),
+        }),
+        "/release" => Err("Usage: /release <sink>".to_string()),
+        _ => Err(format!("Unknown command.\n\n{HELP}")),
+    }
+}
+
+/// Parse a duration such as `30m`, `2h` or `1h30m`.
+fn parse_duration(text: &str) -> Option<Duration> {
+    let mut sec = 0;
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            _ => return None,
+        };
+        // Too large numbers are not a duration, rather than overflowing.
+        let part = number.parse::<u64>().ok()?.checked_mul(unit)?;
+        sec = part.checked_add(sec)?;
+        number.clear();
+    }
+    (number.is_empty() && sec > 0).then(|| Duration::from_secs(sec))
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,36 @@
+use super::*;
+
+#[test]
+fn parses_force() {
+    assert_eq!(
+        parse_command("/force on Living Room TV 1h30m"),
+        Ok(Command::Force {
+            sink: "Living Room TV".to_string(),
+            on: true,
+            duration: Some(Duration::from_secs(90 * 60)),
+        })
+    );
+    assert_eq!(
+        parse_command("/force@PowerBot off projector"),
+        Ok(Command::Force {
+            sink: "projector".to_string(),
+            on: false,
+            duration: None,
+        })
+    );
+    assert!(parse_command("/force on").is_err());
+    assert!(parse_command("/force maybe projector").is_err());
+}
+
+#[test]
+fn parses_durations() {
+    assert_eq!(parse_duration("45s"), Some(Duration::from_secs(45)));
+    assert_eq!(
+        parse_duration("2d"),
+        Some(Duration::from_secs(2 * 24 * 3600))
+    );
+    assert_eq!(parse_duration("30"), None);
+    assert_eq!(parse_duration("TV"), None);
+    assert_eq!(parse_duration("999999999999999d"), None);
+    assert_eq!(parse_duration("18446744073709551615s1s"), None);
+}
@@ -0,0 +1,236 @@
+#![cfg(any(test, feature = "testing"))]
+#![cfg_attr(not(test), allow(dead_code))]
+//! Scriptable sinks and sources, to drive [`State`] in tests, usually with tokio's time paused.
+
+use crate::error::{Error, ErrorKind};
+use crate::settings::{Settings, SinkBaseSettings, SourceBaseSettings};
+use crate::sink::{Sink, SinkCommandResult};
+use crate::source::{Source, SourceIsActiveResult};
+use crate::state::State;
+use config::{Config, File, FileFormat};
+use serde::de::DeserializeOwned;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Parse settings written like in the config file.
+pub fn parse<T: DeserializeOwned>(toml: &str) -> T {
+    Config::builder()
+        .add_source(File::from_str(toml, FileFormat::Toml))
+        .build()
+        .and_then(Config::try_deserialize)
+        .expect("invalid test settings")
+}
+
+fn mock_error(kind: ErrorKind) -> Error {
+    match kind {
+        ErrorKind::Network => Error::network("mock network error"),
+        ErrorKind::Auth => Error::auth("mock authentication error"),
+        ErrorKind::Protocol => Error::protocol("mock protocol error"),
+        ErrorKind::Timeout => Error::Timeout,
+        ErrorKind::Other => Error::other("mock error"),
+    }
+}
+
+/// A source that reports the results of its script in order, repeating the last one. Clones
+/// share the script, so a test keeps one to change it while the state polls another.
+#[derive(Clone)]
+pub struct MockSource {
+    base: SourceBaseSettings,
+    script: Arc<Mutex<VecDeque<Result<bool, ErrorKind>>>>,
+    polls: Arc<Mutex<u32>>,
+    changed: Arc<Notify>,
+}
+
+impl MockSource {
+    /// An inactive source, polled every 10 seconds.
+    pub fn named(name: &str) -> Self {
+        Self::new(&format!(
+            r#"name = "{name}"
+            enable = true
+            timeout-sec = 5
+            poll-interval-sec = {{ off = 10, on = 10 }}"#
+        ))
+    }
+
+    /// An inactive source with the given base settings.
+    pub fn new(base_toml: &str) -> Self {
+        Self {
+            base: parse(base_toml),
+            script: Arc::new(Mutex::new(VecDeque::from([Ok(false)]))),
+            polls: Default::default(),
+            changed: Default::default(),
+        }
+    }
+
+    /// Report the results, in order, from the next poll on. The source is polled right away.
+    pub fn script(&self, results: impl IntoIterator<Item = Result<bool, ErrorKind>>) {
+        let mut script = self.script.lock().unwrap();
+        *script = results.into_iter().collect();
+        assert!(!script.is_empty(), "the script must not be empty");
+        self.changed.notify_one();
+    }
+
+    /// Report the state from the next poll on. The source is polled right away.
+    pub fn set(&self, active: bool) {
+        self.script([Ok(active)]);
+    }
+
+    /// How often the source was polled.
+    pub fn polls(&self) -> u32 {
+        *self.polls.lock().unwrap()
+    }
+}
+
+#[async_trait]
+impl Source for MockSource {
+    fn base_settings(&self) -> &SourceBaseSettings {
+        &self.base
+    }
+
+    async fn is_active(&self, _cancel: &CancellationToken) -> SourceIsActiveResult {
+        *self.polls.lock().unwrap() += 1;
+        let mut script = self.script.lock().unwrap();
+        let result = match script.len() {
+            1 => script[0],
+            _ => script.pop_front().unwrap(),
+        };
+        result.map_err(mock_error)
+    }
+
+    async fn wait_for_change(&self) {
+        self.changed.notified().await
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MockCommand {
+    On,
+    Off,
+    Standby,
+}
+
+/// A sink that records its commands. They succeed, unless failures are scripted. Clones share
+/// the record and the script.
+#[derive(Clone)]
+pub struct MockSink {
+    base: SinkBaseSettings,
+    calls: Arc<Mutex<Vec<(MockCommand, Instant)>>>,
+    failures: Arc<Mutex<VecDeque<ErrorKind>>>,
+    delay: Arc<Mutex<Duration>>,
+}
+
+impl MockSink {
+    pub fn named(name: &str) -> Self {
+        Self::new(&format!(
+            r#"name = "{name}"
+            enable = true
+            timeout-sec = 5"#
+        ))
+    }
+
+    pub fn new(base_toml: &str) -> Self {
+        Self {
+            base: parse(base_toml),
+            calls: Default::default(),
+            failures: Default::default(),
+            delay: Default::default(),
+        }
+    }
+
+    /// Take this long to complete commands from now on.
+    pub fn set_delay(&self, delay: Duration) {
+        *self.delay.lock().unwrap() = delay;
+    }
+
+    /// Fail the next commands with errors of these kinds, in order.
+    pub fn fail_next(&self, kinds: impl IntoIterator<Item = ErrorKind>) {
+        self.failures.lock().unwrap().extend(kinds);
+    }
+
+    /// The commands received so far, including failed ones.
+    pub fn commands(&self) -> Vec<MockCommand> {
+        self.calls.lock().unwrap().iter().map(|(c, _)| *c).collect()
+    }
+
+    /// The commands received so far, with the time they were received at.
+    pub fn calls(&self) -> Vec<(MockCommand, Instant)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    async fn record(&self, command: MockCommand) -> SinkCommandResult {
+        self.calls.lock().unwrap().push((command, Instant::now()));
+        let delay = *self.delay.lock().unwrap();
+        sleep(delay).await;
+        match self.failures.lock().unwrap().pop_front() {
+            Some(kind) => Err(mock_error(kind)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl Sink for MockSink {
+    fn base_settings(&self) -> &SinkBaseSettings {
+        &self.base
+    }
+
+    async fn on(&self) -> SinkCommandResult {
+        self.record(MockCommand::On).await
+    }
+
+    async fn off(&self) -> SinkCommandResult {
+        self.record(MockCommand::Off).await
+    }
+
+    fn supports_standby(&self) -> bool {
+        true
+    }
+
+    async fn standby(&self) -> SinkCommandResult {
+        self.record(MockCommand::Standby).await
+    }
+}
+
+/// A running [`State`] with mock sinks and sources. It stops when dropped.
+pub struct Harness {
+    pub state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+impl Harness {
+    /// Run the state with the config, which needs at least a `[general]` section. Sinks and
+    /// sources in it are ignored, the mocks are used instead.
+    pub async fn start(
+        config_toml: &str,
+        sources: impl IntoIterator<Item = MockSource>,
+        sinks: impl IntoIterator<Item = MockSink>,
+    ) -> Self {
+        let config: Settings = parse(config_toml);
+        let mut state = State::new(config.general, &config.zone, &config.route);
+        state
+            .try_register_sources(sources.into_iter().map(|s| Ok(Box::new(s) as _)))
+            .await
+            .unwrap();
+        state
+            .try_register_sinks(sinks.into_iter().map(|s| Ok(Box::new(s) as _)))
+            .await
+            .unwrap();
+        let state = Arc::new(state);
+        let task = tokio::spawn({
+            let state = state.clone();
+            async move { state.run().await }
+        });
+        Self { state, task }
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}